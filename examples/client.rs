@@ -7,13 +7,14 @@ use futures::future::{FutureExt as _, FusedFuture as _, Fuse};
 use futures::stream::{StreamExt as _, TryStreamExt as _, FuturesUnordered};
 use regex::Regex;
 use rustix::termios;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::{env, fs};
 use std::future::Future;
 use std::os::unix::io::AsRawFd as _;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
 use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
 
@@ -210,19 +211,13 @@ async fn run_client(opts: Opts) -> Result<ExitCode> {
         .context("could not open TCP connection to the server")?;
     log::info!("successfully connected");
 
-    let remote_tunnel_addrs = opts.remote_tunnels.into_iter()
-        .map(|spec| {
-            let bind_addr = (spec.bind_host.unwrap_or("".into()), spec.bind_port);
-            let connect_addr = (spec.connect_host, spec.connect_port);
-            (bind_addr, connect_addr)
-        })
-        .collect::<HashMap<_, _>>();
+    let remote_forwards = Arc::new(Mutex::new(Vec::new()));
 
     let (client, client_rx, client_fut) = makiko::Client::open(socket, config)?;
     let client_task = TaskHandle(tokio::task::spawn(client_fut));
 
     let event_task = TaskHandle(tokio::task::spawn(
-        run_events(client.clone(), client_rx, remote_tunnel_addrs.clone(), hostname, opts.host_file)
+        run_events(client.clone(), client_rx, remote_forwards.clone(), hostname, opts.host_file)
     ));
 
     let interact_task = TaskHandle(tokio::task::spawn(enclose!{(client) async move {
@@ -230,7 +225,7 @@ async fn run_client(opts: Opts) -> Result<ExitCode> {
             .context("could not authenticate")?;
         log::info!("successfully authenticated");
 
-        bind_remote_tunnels(&client, &remote_tunnel_addrs).await?;
+        bind_remote_tunnels(&client, opts.remote_tunnels, &remote_forwards).await?;
 
         let session_task = TaskHandle(tokio::task::spawn(enclose!{(client) async move {
             run_session(client, opts.command, opts.want_tty).await
@@ -275,7 +270,7 @@ async fn run_client(opts: Opts) -> Result<ExitCode> {
 async fn run_events(
     client: makiko::Client,
     mut client_rx: makiko::ClientReceiver,
-    remote_tunnel_addrs: HashMap<(String, u16), (String, u16)>,
+    remote_forwards: Arc<Mutex<Vec<makiko::forward::RemoteForward>>>,
     hostname: String,
     mut host_file: Option<HostFile>,
 ) -> Result<()> {
@@ -302,11 +297,13 @@ async fn run_events(
                     })).fuse();
                 },
                 Some(makiko::ClientEvent::Tunnel(accept)) => {
-                    let connect_addr = remote_tunnel_addrs.get(&accept.connected_addr);
-                    let Some(connect_addr) = connect_addr else { continue };
-                    tunnel_tasks.push(TaskHandle(tokio::task::spawn(
-                        run_remote_tunnel(accept, connect_addr.clone())
-                    )));
+                    let conn = {
+                        let forwards = remote_forwards.lock().unwrap();
+                        let Some(forward) = forwards.iter().find(|f| f.matches(&accept)) else { continue };
+                        forward.accept(accept)
+                    };
+                    let Some(conn) = conn else { continue };
+                    tunnel_tasks.push(TaskHandle(tokio::task::spawn(run_remote_tunnel(conn))));
                 },
                 Some(_) => continue,
                 None => break,
@@ -373,31 +370,25 @@ async fn verify_pubkey(
     }
 }
 
-async fn run_remote_tunnel(accept: makiko::AcceptTunnel, connect_addr: (String, u16)) -> Result<()> {
-    match tokio::net::TcpStream::connect(&connect_addr).await {
-        Ok(socket) => {
-            let config = makiko::ChannelConfig::default();
-            let (tunnel, tunnel_rx) = accept.accept(config).await?;
-            run_tunnel_socket(tunnel, tunnel_rx, socket).await
-        },
-        Err(err) => {
-            log::warn!("Could not open tunnel to {:?}: {}", connect_addr, err);
-            accept.reject(makiko::ChannelOpenError {
-                reason_code: makiko::codes::open::CONNECT_FAILED,
-                description: format!("Connect attempt failed: {}", err),
-                description_lang: "".into(),
-            });
-            Ok(())
-        },
+async fn run_remote_tunnel(conn: makiko::forward::RemoteForwardConn) -> Result<()> {
+    let originator_addr = conn.originator_addr().clone();
+    if let Err(err) = conn.run().await {
+        log::warn!("Remote tunnel from {:?} failed: {}", originator_addr, err);
     }
+    Ok(())
 }
 
 async fn bind_remote_tunnels(
     client: &makiko::Client,
-    remote_tunnel_addrs: &HashMap<(String, u16), (String, u16)>,
+    specs: Vec<TunnelSpec>,
+    remote_forwards: &Mutex<Vec<makiko::forward::RemoteForward>>,
 ) -> Result<()> {
-    for bind_addr in remote_tunnel_addrs.keys() {
-        client.bind_tunnel(bind_addr.clone())?.wait().await?;
+    for spec in specs {
+        let bind_addr = (spec.bind_host.unwrap_or("".into()), spec.bind_port);
+        let connect_addr = (spec.connect_host, spec.connect_port);
+        let forward = makiko::forward::RemoteForward::bind(
+            client.clone(), makiko::ChannelConfig::default(), bind_addr, connect_addr).await?;
+        remote_forwards.lock().unwrap().push(forward);
     }
     Ok(())
 }
@@ -629,56 +620,20 @@ async fn run_session(client: makiko::Client, command: Option<String>, want_tty:
 async fn run_local_tunnel(client: makiko::Client, spec: TunnelSpec) -> Result<()> {
     let bind_addr = (spec.bind_host.unwrap_or("localhost".into()), spec.bind_port);
     let listener = tokio::net::TcpListener::bind(bind_addr).await?;
+    let connect_addr = (spec.connect_host, spec.connect_port);
+    let forward = makiko::forward::LocalForward::new(
+        client, makiko::ChannelConfig::default(), listener, connect_addr);
+
     let mut socket_tasks = FuturesUnordered::new();
     loop {
         tokio::select!{
-            res = listener.accept() => {
-                let (socket, peer_addr) = res?;
-
-                let config = makiko::ChannelConfig::default();
-                let connect_addr = (spec.connect_host.clone(), spec.connect_port);
-                let originator_addr = (peer_addr.ip().to_string(), peer_addr.port());
-                let (tunnel, tunnel_rx) = client.connect_tunnel(
-                    config, connect_addr, originator_addr).await?;
-
-                let task = TaskHandle(tokio::task::spawn(run_tunnel_socket(tunnel, tunnel_rx, socket)));
-                socket_tasks.push(task);
+            res = forward.accept() => {
+                let Some(conn) = res? else { break };
+                socket_tasks.push(TaskHandle(tokio::task::spawn(conn.run())));
             },
             Some(res) = socket_tasks.next() => res?,
         }
     }
-}
-
-async fn run_tunnel_socket(
-    tunnel: makiko::Tunnel,
-    mut tunnel_rx: makiko::TunnelReceiver,
-    socket: tokio::net::TcpStream,
-) -> Result<()> {
-    let (mut socket_read, mut socket_write) = socket.into_split();
-
-    let socket_to_tunnel = TaskHandle(tokio::task::spawn(async move {
-        let mut buffer = BytesMut::new();
-        while socket_read.read_buf(&mut buffer).await? != 0 {
-            tunnel.send_data(buffer.split().freeze()).await?;
-        }
-        tunnel.send_eof().await?;
-        Result::<_>::Ok(())
-    }));
-
-    let tunnel_to_socket = TaskHandle(tokio::task::spawn(async move {
-        while let Some(event) = tunnel_rx.recv().await? {
-            match event {
-                makiko::TunnelEvent::Data(mut data) =>
-                    socket_write.write_all_buf(&mut data).await?,
-                makiko::TunnelEvent::Eof =>
-                    break,
-                _ => {},
-            }
-        }
-        Result::<_>::Ok(())
-    }));
-
-    tokio::try_join!(socket_to_tunnel, tunnel_to_socket)?;
     Ok(())
 }
 
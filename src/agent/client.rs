@@ -0,0 +1,275 @@
+use bytes::Bytes;
+use derivative::Derivative;
+use futures_core::future::BoxFuture;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncReadExt as _, AsyncWriteExt as _};
+use tokio::sync::Mutex;
+use crate::codec::{PacketDecode, PacketEncode};
+use crate::codes::{agent_msg, agent_sign};
+use crate::error::{Error, Result};
+use crate::pubkey::{Certificate, Pubkey, PubkeyAlgo, Signer};
+use crate::util::AsyncReadWrite;
+
+/// Maximum length of a message that we are willing to receive from the agent.
+const MAX_MESSAGE_LEN: usize = 256 * 1024;
+
+/// Connection to an SSH agent.
+///
+/// Use this object to list the keys held by the agent and to ask the agent to sign data with these
+/// keys. To authenticate to an SSH server using a key from the agent, please use
+/// [`Client::auth_pubkey_agent()`][crate::Client::auth_pubkey_agent()].
+///
+/// You can cheaply clone this object and share the clones between tasks; the requests are sent to
+/// the agent one by one. Note that if you drop a future returned from one of the methods before it
+/// completes, the connection may be left in an inconsistent state and you should not use it
+/// anymore.
+#[derive(Derivative, Clone)]
+#[derivative(Debug)]
+pub struct Agent {
+    #[derivative(Debug = "ignore")]
+    stream: Arc<Mutex<Pin<Box<dyn AsyncReadWrite + Send>>>>,
+}
+
+/// Key held by the SSH agent.
+///
+/// You can obtain the identities from [`Agent::list_identities()`].
+#[derive(Debug, Clone)]
+pub struct AgentIdentity {
+    /// Public key of this identity.
+    ///
+    /// If the identity is a [`certificate`][Self::certificate], this is the certified public key.
+    pub pubkey: Pubkey,
+
+    /// OpenSSH certificate, if the agent holds a certificate for the key.
+    pub certificate: Option<Certificate>,
+
+    /// Comment associated with the key (typically the file name of the key).
+    pub comment: String,
+
+    blob: Bytes,
+}
+
+/// [`Signer`] that signs data using a key held by the SSH agent.
+///
+/// You can obtain this object from [`Agent::signer()`] and use it with
+/// [`Client::auth_pubkey()`][crate::Client::auth_pubkey()] or
+/// [`Client::auth_pubkey_cert()`][crate::Client::auth_pubkey_cert()].
+#[derive(Debug, Clone)]
+pub struct AgentSigner {
+    agent: Agent,
+    identity: AgentIdentity,
+}
+
+impl Agent {
+    /// Create an agent client that communicates over the given stream.
+    ///
+    /// The stream must be connected to the agent, for example using a Unix socket.
+    pub fn new<S>(stream: S) -> Agent
+        where S: AsyncRead + AsyncWrite + Send + 'static
+    {
+        Agent { stream: Arc::new(Mutex::new(Box::pin(stream))) }
+    }
+
+    /// Connect to the agent listening on the Unix socket at the given path.
+    #[cfg(unix)]
+    pub async fn connect(path: impl AsRef<std::path::Path>) -> Result<Agent> {
+        let stream = tokio::net::UnixStream::connect(path).await.map_err(Error::AgentIo)?;
+        Ok(Agent::new(stream))
+    }
+
+    /// Connect to the agent using the `SSH_AUTH_SOCK` environment variable.
+    ///
+    /// This is the usual way to find the agent. If the variable is not set, returns
+    /// [`Error::AgentNotFound`].
+    #[cfg(unix)]
+    pub async fn connect_env() -> Result<Agent> {
+        let path = std::env::var_os("SSH_AUTH_SOCK").ok_or(Error::AgentNotFound)?;
+        Self::connect(path).await
+    }
+
+    /// List the keys held by the agent.
+    ///
+    /// This corresponds to `SSH_AGENTC_REQUEST_IDENTITIES`. Keys in formats that we don't
+    /// support are silently skipped.
+    pub async fn list_identities(&self) -> Result<Vec<AgentIdentity>> {
+        let mut payload = PacketEncode::new();
+        payload.put_u8(agent_msg::REQUEST_IDENTITIES);
+        let (msg_id, mut reply) = self.request(payload.finish()).await?;
+        if msg_id != agent_msg::IDENTITIES_ANSWER {
+            return Err(unexpected_reply(msg_id))
+        }
+
+        let num_keys = reply.get_u32()?;
+        let mut identities = Vec::new();
+        for _ in 0..num_keys {
+            let blob = reply.get_bytes()?;
+            let comment = reply.get_string()?;
+            match decode_identity(blob, comment.clone()) {
+                Ok(identity) => identities.push(identity),
+                Err(err) => log::debug!("ignoring key {:?} from SSH agent: {}", comment, err),
+            }
+        }
+        Ok(identities)
+    }
+
+    /// Ask the agent to sign `data` with the key of `identity` using `algo`.
+    ///
+    /// This corresponds to `SSH_AGENTC_SIGN_REQUEST`. For the "rsa-sha2-256" and "rsa-sha2-512"
+    /// algorithms (and the corresponding certificate algorithms), we ask the agent to use SHA-2
+    /// instead of the default SHA-1. Returns the signature blob in the SSH format.
+    pub async fn sign(&self, identity: &AgentIdentity, algo: &PubkeyAlgo, data: &[u8]) -> Result<Bytes> {
+        let flags = match algo.name {
+            "rsa-sha2-256" | "rsa-sha2-256-cert-v01@openssh.com" => agent_sign::RSA_SHA2_256,
+            "rsa-sha2-512" | "rsa-sha2-512-cert-v01@openssh.com" => agent_sign::RSA_SHA2_512,
+            _ => 0,
+        };
+
+        let mut payload = PacketEncode::new();
+        payload.put_u8(agent_msg::SIGN_REQUEST);
+        payload.put_bytes(&identity.blob);
+        payload.put_bytes(data);
+        payload.put_u32(flags);
+        let (msg_id, mut reply) = self.request(payload.finish()).await?;
+        if msg_id != agent_msg::SIGN_RESPONSE {
+            return Err(unexpected_reply(msg_id))
+        }
+        reply.get_bytes()
+    }
+
+    /// Get a [`Signer`] that signs data using the key of `identity`.
+    pub fn signer(&self, identity: &AgentIdentity) -> AgentSigner {
+        AgentSigner { agent: self.clone(), identity: identity.clone() }
+    }
+
+    async fn request(&self, payload: Bytes) -> Result<(u8, PacketDecode)> {
+        let mut stream = self.stream.lock().await;
+
+        stream.write_u32(payload.len() as u32).await.map_err(Error::AgentIo)?;
+        stream.write_all(&payload).await.map_err(Error::AgentIo)?;
+        stream.flush().await.map_err(Error::AgentIo)?;
+
+        let reply_len = stream.read_u32().await.map_err(Error::AgentIo)? as usize;
+        if reply_len == 0 || reply_len > MAX_MESSAGE_LEN {
+            return Err(Error::AgentProtocol("received message with invalid length"))
+        }
+        let mut reply = vec![0; reply_len];
+        stream.read_exact(&mut reply).await.map_err(Error::AgentIo)?;
+
+        let mut reply = PacketDecode::new(reply.into());
+        let msg_id = reply.get_u8()?;
+        Ok((msg_id, reply))
+    }
+}
+
+impl AgentIdentity {
+    /// Get the public key algorithms that can be used with this identity.
+    ///
+    /// For plain keys, this is the same as [`Pubkey::algos()`], for certificates this is
+    /// [`Certificate::algos()`].
+    pub fn algos(&self) -> &'static [&'static PubkeyAlgo] {
+        match &self.certificate {
+            Some(cert) => cert.algos(),
+            None => self.pubkey.algos(),
+        }
+    }
+
+    pub(crate) fn blob(&self) -> Bytes {
+        self.blob.clone()
+    }
+}
+
+impl Signer for AgentSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.identity.pubkey.clone()
+    }
+
+    fn sign<'a>(&'a self, algo: &'static PubkeyAlgo, data: &'a [u8]) -> BoxFuture<'a, Result<Bytes>> {
+        Box::pin(self.agent.sign(&self.identity, algo, data))
+    }
+}
+
+fn decode_identity(blob: Bytes, comment: String) -> Result<AgentIdentity> {
+    let type_str = PacketDecode::new(blob.clone()).get_string()?;
+    let (pubkey, certificate) = if type_str.ends_with("-cert-v01@openssh.com") {
+        let cert = Certificate::decode(blob.clone())?;
+        (cert.pubkey.clone(), Some(cert))
+    } else {
+        (Pubkey::decode(blob.clone())?, None)
+    };
+    Ok(AgentIdentity { pubkey, certificate, comment, blob })
+}
+
+fn unexpected_reply(msg_id: u8) -> Error {
+    if msg_id == agent_msg::FAILURE {
+        Error::AgentFailure
+    } else {
+        Error::AgentProtocol("received unexpected message")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pubkey::{self, Privkey};
+
+    fn privkey() -> Privkey {
+        Privkey::Ed25519(ed25519_dalek::SigningKey::from_bytes(&[42; 32]).into())
+    }
+
+    // minimal in-process stand-in for `ssh-agent` that holds a single key
+    async fn run_fake_agent(mut stream: tokio::io::DuplexStream, privkey: Privkey) {
+        while let Ok(len) = stream.read_u32().await {
+            let mut request = vec![0; len as usize];
+            stream.read_exact(&mut request).await.unwrap();
+            let mut request = PacketDecode::new(request.into());
+
+            let mut reply = PacketEncode::new();
+            match request.get_u8().unwrap() {
+                agent_msg::REQUEST_IDENTITIES => {
+                    reply.put_u8(agent_msg::IDENTITIES_ANSWER);
+                    reply.put_u32(2);
+                    reply.put_bytes(&privkey.pubkey().encode());
+                    reply.put_str("test key");
+                    reply.put_bytes(b"\x00\x00\x00\x0bunknown-key");
+                    reply.put_str("unknown key");
+                },
+                agent_msg::SIGN_REQUEST => {
+                    let blob = request.get_bytes().unwrap();
+                    let data = request.get_bytes().unwrap();
+                    let flags = request.get_u32().unwrap();
+                    if blob == privkey.pubkey().encode() && flags == 0 {
+                        reply.put_u8(agent_msg::SIGN_RESPONSE);
+                        reply.put_bytes(&(pubkey::SSH_ED25519.sign)(&privkey, &data).unwrap());
+                    } else {
+                        reply.put_u8(agent_msg::FAILURE);
+                    }
+                },
+                _ => reply.put_u8(agent_msg::FAILURE),
+            }
+
+            let reply = reply.finish();
+            stream.write_u32(reply.len() as u32).await.unwrap();
+            stream.write_all(&reply).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_and_sign() {
+        let (agent_stream, stream) = tokio::io::duplex(4096);
+        tokio::spawn(run_fake_agent(agent_stream, privkey()));
+        let agent = Agent::new(stream);
+
+        let identities = agent.list_identities().await.unwrap();
+        assert_eq!(identities.len(), 1);
+        assert_eq!(identities[0].pubkey, privkey().pubkey());
+        assert_eq!(identities[0].comment, "test key");
+        assert!(identities[0].certificate.is_none());
+
+        let signature = agent.sign(&identities[0], &pubkey::SSH_ED25519, b"hello").await.unwrap();
+        (pubkey::SSH_ED25519.verify)(&privkey().pubkey(), b"hello", signature).unwrap();
+
+        let res = agent.sign(&identities[0], &pubkey::RSA_SHA2_256, b"hello").await;
+        assert!(matches!(res, Err(Error::AgentFailure)));
+    }
+}
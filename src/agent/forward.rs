@@ -0,0 +1,47 @@
+use tokio::io::{AsyncRead, AsyncWrite};
+use crate::client::{AcceptAgentForward, ChannelConfig, TunnelStream};
+use crate::error::{Error, Result};
+
+/// Accept a forwarded agent connection and bridge it to a local agent.
+///
+/// You receive the `accept` object in
+/// [`ClientEvent::AgentForward`][crate::ClientEvent::AgentForward] after you requested agent
+/// forwarding using [`Session::request_agent_forward()`][crate::Session::request_agent_forward()].
+/// The `agent_stream` should be a new connection to your local agent (see also
+/// [`bridge_to_socket()`]). We copy the data in both directions until both sides are closed.
+///
+/// Note that by forwarding your agent, you allow the server to use your keys while the connection
+/// is open, so you should forward the agent only to servers that you trust.
+pub async fn bridge<S>(accept: AcceptAgentForward, config: ChannelConfig, mut agent_stream: S) -> Result<()>
+    where S: AsyncRead + AsyncWrite + Unpin
+{
+    let (tunnel, tunnel_rx) = accept.accept(config).await?;
+    let mut tunnel_stream = TunnelStream::new(tunnel, tunnel_rx);
+    tokio::io::copy_bidirectional(&mut tunnel_stream, &mut agent_stream).await
+        .map_err(Error::AgentIo)?;
+    Ok(())
+}
+
+/// Accept a forwarded agent connection and bridge it to the agent listening on a Unix socket.
+///
+/// This connects to the Unix socket at `path` (usually the value of the `SSH_AUTH_SOCK`
+/// environment variable) and calls [`bridge()`]. If we cannot connect to the agent, we reject the
+/// connection.
+#[cfg(unix)]
+pub async fn bridge_to_socket(
+    accept: AcceptAgentForward,
+    config: ChannelConfig,
+    path: impl AsRef<std::path::Path>,
+) -> Result<()> {
+    match tokio::net::UnixStream::connect(path).await {
+        Ok(agent_stream) => bridge(accept, config, agent_stream).await,
+        Err(err) => {
+            accept.reject(crate::ChannelOpenError {
+                reason_code: crate::codes::open::CONNECT_FAILED,
+                description: "could not connect to the SSH agent".into(),
+                description_lang: "".into(),
+            });
+            Err(Error::AgentIo(err))
+        },
+    }
+}
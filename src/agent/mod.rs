@@ -0,0 +1,26 @@
+//! Client for the SSH agent.
+//!
+//! The SSH agent (such as OpenSSH `ssh-agent`) holds your private keys and signs data on your
+//! behalf, so that the private keys are never loaded into your process. We implement the client
+//! side of the agent protocol, as described in `draft-miller-ssh-agent`.
+//!
+//! To start, connect to the agent using [`Agent::connect_env()`] (which uses the socket from the
+//! `SSH_AUTH_SOCK` environment variable), [`Agent::connect()`] or [`Agent::new()`]. Then you can
+//! list the keys held by the agent using [`Agent::list_identities()`] and authenticate using
+//! [`Client::auth_pubkey_agent()`][crate::Client::auth_pubkey_agent()]. You can also obtain an
+//! [`AgentSigner`] that implements [`Signer`][crate::Signer].
+//!
+//! # Agent forwarding
+//!
+//! You can also forward your agent to the server, so that programs on the server can use your
+//! keys (for example, to `git clone` from another server). Request the forwarding using
+//! [`Session::request_agent_forward()`][crate::Session::request_agent_forward()], then handle
+//! [`ClientEvent::AgentForward`][crate::ClientEvent::AgentForward] events using [`bridge()`] or
+//! [`bridge_to_socket()`].
+pub use self::client::{Agent, AgentIdentity, AgentSigner};
+pub use self::forward::bridge;
+#[cfg(unix)]
+pub use self::forward::bridge_to_socket;
+
+mod client;
+mod forward;
@@ -20,6 +20,8 @@
 //! - "aes256-cbc" ([`AES256_CBC`])
 //! - "3des-cbc" ([`TDES_CBC`], SHOULD NOT be used, available only with feature `insecure-crypto`).
 //! - "none" ([`NONE`])
+//!
+//! You can also look up the algorithms by name using [`algo_by_name()`].
 use crate::Result;
 use derivative::Derivative;
 pub use self::aes_gcm::{AES128_GCM, AES256_GCM};
@@ -39,13 +41,13 @@ mod none;
 mod stream;
 
 static ALGOS: &[&CipherAlgo] = &[
-    &AES128_GCM, &AES256_GCM,
-    &AES128_CBC, &AES192_CBC, &AES256_CBC,
     &CHACHA20_POLY1305,
-    &NONE,
+    &AES128_GCM, &AES256_GCM,
     &AES128_CTR, &AES192_CTR, &AES256_CTR,
+    &AES128_CBC, &AES192_CBC, &AES256_CBC,
     #[cfg(feature = "insecure-crypto")]
     &TDES_CBC,
+    &NONE,
 ];
 
 /// Algorithm for encrypting and decrypting messages.
@@ -126,6 +128,11 @@ impl CipherAlgoVariant {
     }
 }
 
-pub(crate) fn algo_by_name(name: &str) -> Option<&'static CipherAlgo> {
+/// Find a supported cipher algorithm by its name.
+pub fn algo_by_name(name: &str) -> Option<&'static CipherAlgo> {
     ALGOS.iter().copied().find(|algo| algo.name == name)
 }
+
+pub(crate) fn algos() -> &'static [&'static CipherAlgo] {
+    ALGOS
+}
@@ -0,0 +1,97 @@
+use crate::error::{Error, Result};
+use crate::util::wildcard_matches;
+
+/// Updates `algos` using a list of algorithm names in the OpenSSH syntax.
+///
+/// The `spec` is a comma-separated list of names or wildcard patterns. If it starts with `+`, the
+/// matching algorithms are appended to `algos`; with `-`, they are removed; with `^`, they are
+/// moved to the front; otherwise, `algos` is replaced. Names without wildcards that are not in
+/// `all` are reported as errors, and wildcards never match the "none" algorithm.
+pub(super) fn apply<A>(
+    algos: &mut Vec<&'static A>,
+    spec: &str,
+    all: &[&'static A],
+    name: fn(&A) -> &'static str,
+) -> Result<()> {
+    let (op, list) = match spec.chars().next() {
+        Some(op @ ('+' | '-' | '^')) => (Some(op), &spec[1..]),
+        _ => (None, spec),
+    };
+
+    let mut matched: Vec<&'static A> = Vec::new();
+    for pattern in list.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let is_wildcard = pattern.contains(['*', '?']);
+        let mut found = false;
+        for &algo in all.iter() {
+            let algo_name = name(algo);
+            let matches = if is_wildcard {
+                algo_name != "none" && wildcard_matches(pattern, algo_name)
+            } else {
+                algo_name == pattern
+            };
+            if matches {
+                found = true;
+                if !matched.iter().any(|a| name(a) == algo_name) {
+                    matched.push(algo);
+                }
+            }
+        }
+
+        if !found && !is_wildcard {
+            return Err(Error::UnknownAlgo(pattern.into()))
+        }
+    }
+
+    let contains = |list: &[&'static A], algo: &A| list.iter().any(|a| name(a) == name(algo));
+    match op {
+        Some('+') => for algo in matched {
+            if !contains(algos, algo) {
+                algos.push(algo);
+            }
+        },
+        Some('-') => algos.retain(|algo| !contains(&matched, algo)),
+        Some('^') => {
+            algos.retain(|algo| !contains(&matched, algo));
+            algos.splice(0..0, matched);
+        },
+        _ => *algos = matched,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cipher::{self, CipherAlgo};
+    use super::*;
+
+    fn apply_names(algos: &[&str], spec: &str) -> Result<Vec<&'static str>> {
+        let mut algos: Vec<&'static CipherAlgo> = algos.iter()
+            .map(|name| cipher::algo_by_name(name).unwrap())
+            .collect();
+        apply(&mut algos, spec, cipher::algos(), |algo| algo.name)?;
+        Ok(algos.into_iter().map(|algo| algo.name).collect())
+    }
+
+    #[test]
+    fn test_apply() {
+        let base = ["aes128-ctr", "aes256-ctr"];
+        assert_eq!(apply_names(&base, "aes128-gcm@openssh.com,aes128-ctr").unwrap(),
+            ["aes128-gcm@openssh.com", "aes128-ctr"]);
+        assert_eq!(apply_names(&base, "+aes128-cbc,aes256-ctr").unwrap(),
+            ["aes128-ctr", "aes256-ctr", "aes128-cbc"]);
+        assert_eq!(apply_names(&base, "-aes128-*").unwrap(), ["aes256-ctr"]);
+        assert_eq!(apply_names(&base, "^aes256-ctr,aes128-cbc").unwrap(),
+            ["aes256-ctr", "aes128-cbc", "aes128-ctr"]);
+        assert_eq!(apply_names(&base, "aes*-ctr,aes128-ctr").unwrap(),
+            ["aes128-ctr", "aes192-ctr", "aes256-ctr"]);
+        assert!(!apply_names(&base, "*").unwrap().contains(&"none"));
+        assert_eq!(apply_names(&base, "none").unwrap(), ["none"]);
+    }
+
+    #[test]
+    fn test_apply_unknown() {
+        assert!(matches!(apply_names(&["aes128-ctr"], "+aes128-ctr,foo"),
+            Err(Error::UnknownAlgo(name)) if name == "foo"));
+        assert_eq!(apply_names(&["aes128-ctr"], "+foo*").unwrap(), ["aes128-ctr"]);
+    }
+}
@@ -32,6 +32,7 @@ pub(super) struct AuthState {
     service_requested: bool,
     service_accepted: bool,
     method: Option<Box<dyn AuthMethod + Send>>,
+    info_request_prompts: Option<usize>,
     success: bool,
 }
 
@@ -42,6 +43,7 @@ pub(super) fn init_auth() -> AuthState {
 pub(super) fn start_method(st: &mut ClientState, method: Box<dyn AuthMethod + Send>) -> Result<()> {
     if st.auth_st.method.is_none() {
         st.auth_st.method = Some(method);
+        st.auth_st.info_request_prompts = None;
         client_state::wakeup_client(st);
         Ok(())
     } else {
@@ -51,7 +53,7 @@ pub(super) fn start_method(st: &mut ClientState, method: Box<dyn AuthMethod + Se
 
 pub(super) fn pump_auth(st: &mut ClientState, _cx: &mut Context) -> Result<Pump> {
     if !st.auth_st.service_requested && negotiate::is_ready(st) {
-        send_service_request(st);
+        send_service_request(st)?;
         st.auth_st.service_requested = true;
         return Ok(Pump::Progress)
     }
@@ -64,25 +66,40 @@ pub(super) fn pump_auth(st: &mut ClientState, _cx: &mut Context) -> Result<Pump>
         if negotiate::is_ready(st) {
             let session_id = st.session_id.as_ref().unwrap();
             if let Some(payload) = st.auth_st.method.as_mut().unwrap().send_packet(session_id) {
-                st.codec.send_pipe.feed_packet(&payload);
+                st.codec.send_pipe.feed_packet(&payload)?;
                 return Ok(Pump::Progress)
             }
         }
 
         pump_ready!(st.auth_st.method.as_mut().unwrap().poll())?;
-        st.auth_st.method = None;
+        let method = st.auth_st.method.take().unwrap();
+        st.auth_st.info_request_prompts = method.info_request_prompts();
         return Ok(Pump::Progress)
     }
 
     Ok(Pump::Pending)
 }
 
-fn send_service_request(st: &mut ClientState) {
+// checks that the user can respond to a keyboard-interactive request with `response_count`
+// responses: the server must have sent a request, and we must respond to all its prompts
+pub(super) fn check_info_response(st: &ClientState, response_count: usize) -> Result<()> {
+    match st.auth_st.info_request_prompts {
+        Some(prompt_count) if prompt_count == response_count => Ok(()),
+        Some(prompt_count) => {
+            log::debug!("cannot respond to {} prompts with {} responses", prompt_count, response_count);
+            Err(Error::AuthInfoResponse("number of responses does not match the number of prompts"))
+        },
+        None => Err(Error::AuthInfoResponse("no keyboard-interactive request is pending")),
+    }
+}
+
+fn send_service_request(st: &mut ClientState) -> Result<()> {
     let mut payload = PacketEncode::new();
     payload.put_u8(msg::SERVICE_REQUEST);
     payload.put_str("ssh-userauth");
-    st.codec.send_pipe.feed_packet(&payload.finish());
+    st.codec.send_pipe.feed_packet(&payload.finish())?;
     log::debug!("sending SSH_MSG_SERVICE_REQUEST for 'ssh-userauth'");
+    Ok(())
 }
 
 pub(super) fn recv_service_accept(st: &mut ClientState) -> ResultRecvState {
@@ -135,6 +152,11 @@ fn recv_auth_failure(st: &mut ClientState, payload: &mut PacketDecode) -> Result
 fn recv_auth_success(st: &mut ClientState, _payload: &mut PacketDecode) -> ResultRecvState {
     st.auth_st.success = true;
     log::debug!("received SSH_MSG_USERAUTH_SUCCESS");
+
+    // OpenSSH PROTOCOL, section 1.2: the server starts the delayed compression right after it
+    // sends SSH_MSG_USERAUTH_SUCCESS, and we start it after we receive it
+    st.codec.send_pipe.start_delayed_compress();
+    st.codec.recv_pipe.start_delayed_decompress();
     Ok(None)
 }
 
@@ -149,3 +171,36 @@ fn recv_auth_banner(_st: &mut ClientState, payload: &mut PacketDecode) -> Result
 pub(super) fn is_authenticated(st: &ClientState) -> bool {
     st.auth_st.success
 }
+
+#[cfg(test)]
+pub(super) fn set_authenticated(st: &mut ClientState) {
+    st.auth_st.success = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+    use tokio::sync::mpsc;
+    use crate::client::{Client, ClientConfig};
+    use super::*;
+
+    #[tokio::test]
+    async fn test_info_response_without_request() {
+        let (stream, _server) = tokio::io::duplex(1024);
+        let (client, _client_rx, _client_fut) = Client::open(stream, ClientConfig::default()).unwrap();
+        let res = client.auth_keyboard_interactive_respond(vec!["secret".into()]).await;
+        assert!(matches!(res, Err(Error::AuthInfoResponse(_))));
+    }
+
+    #[test]
+    fn test_info_response_count() {
+        let (event_tx, _event_rx) = mpsc::channel(1);
+        let mut st = client_state::new_client(ClientConfig::default(), Box::new(OsRng), event_tx).unwrap();
+        assert!(matches!(check_info_response(&st, 0), Err(Error::AuthInfoResponse(_))));
+
+        st.auth_st.info_request_prompts = Some(2);
+        assert!(check_info_response(&st, 2).is_ok());
+        assert!(matches!(check_info_response(&st, 1), Err(Error::AuthInfoResponse(_))));
+        assert!(matches!(check_info_response(&st, 3), Err(Error::AuthInfoResponse(_))));
+    }
+}
@@ -0,0 +1,176 @@
+use bytes::Bytes;
+use std::task::Poll;
+use tokio::sync::oneshot;
+use crate::codec::{PacketDecode, PacketEncode};
+use crate::codes::msg;
+use crate::error::{Result, Error};
+use super::super::auth::AuthFailure;
+use super::AuthMethod;
+
+/// Result of the ["keyboard-interactive"][crate::Client::auth_keyboard_interactive]
+/// authentication method.
+#[derive(Debug, Clone)]
+#[must_use]
+pub enum AuthKeyboardInteractiveResult {
+    /// The authentication was successful.
+    Success,
+
+    /// The server asks you to respond to a list of prompts.
+    ///
+    /// You should answer the prompts using
+    /// [`Client::auth_keyboard_interactive_respond()`][crate::Client::auth_keyboard_interactive_respond],
+    /// which returns the next result. The server may send any number of these requests.
+    InfoRequest(AuthInfoRequest),
+
+    /// The authentication was rejected.
+    Failure(AuthFailure),
+}
+
+/// Request for information that the server sends in the "keyboard-interactive" method.
+///
+/// This corresponds to `SSH_MSG_USERAUTH_INFO_REQUEST` (RFC 4256, section 3.2). The `name` and
+/// `instruction` may be empty, and the list of `prompts` may also be empty (in this case, you
+/// should still respond with an empty list of responses).
+#[derive(Debug, Clone)]
+pub struct AuthInfoRequest {
+    /// Human-readable name of the request (e.g. a title of the dialog).
+    pub name: String,
+    /// Human-readable instruction for the user.
+    pub instruction: String,
+    /// Language tag of `name` and `instruction` (per RFC 3066).
+    pub instruction_lang: String,
+    /// Prompts that you should respond to, in order.
+    pub prompts: Vec<AuthInfoPrompt>,
+}
+
+/// A single prompt in [`AuthInfoRequest`].
+#[derive(Debug, Clone)]
+pub struct AuthInfoPrompt {
+    /// Human-readable prompt (e.g. `"Password: "`).
+    pub prompt: String,
+    /// True if the response should be echoed as the user types it.
+    ///
+    /// This is false for secrets such as passwords.
+    pub echo: bool,
+}
+
+#[derive(Debug)]
+enum Packet {
+    Request { username: String },
+    Response { responses: Vec<String> },
+}
+
+#[derive(Debug)]
+pub struct AuthKeyboardInteractive {
+    packet: Option<Packet>,
+    result_tx: Option<oneshot::Sender<AuthKeyboardInteractiveResult>>,
+    info_request_prompts: Option<usize>,
+}
+
+impl AuthKeyboardInteractive {
+    pub fn new_request(
+        username: String,
+        result_tx: oneshot::Sender<AuthKeyboardInteractiveResult>,
+    ) -> AuthKeyboardInteractive {
+        let packet = Packet::Request { username };
+        AuthKeyboardInteractive { packet: Some(packet), result_tx: Some(result_tx), info_request_prompts: None }
+    }
+
+    pub fn new_response(
+        responses: Vec<String>,
+        result_tx: oneshot::Sender<AuthKeyboardInteractiveResult>,
+    ) -> AuthKeyboardInteractive {
+        let packet = Packet::Response { responses };
+        AuthKeyboardInteractive { packet: Some(packet), result_tx: Some(result_tx), info_request_prompts: None }
+    }
+}
+
+impl AuthMethod for AuthKeyboardInteractive {
+    fn recv_success(&mut self) -> Result<()> {
+        if let Some(result_tx) = self.result_tx.take() {
+            let _: Result<_, _> = result_tx.send(AuthKeyboardInteractiveResult::Success);
+        }
+        Ok(())
+    }
+
+    fn recv_failure(&mut self, failure: AuthFailure) -> Result<()> {
+        if let Some(result_tx) = self.result_tx.take() {
+            let _: Result<_, _> = result_tx.send(AuthKeyboardInteractiveResult::Failure(failure));
+        }
+        Ok(())
+    }
+
+    fn recv_packet(&mut self, msg_id: u8, payload: &mut PacketDecode) -> Result<()> {
+        if msg_id == msg::USERAUTH_INFO_REQUEST {
+            let name = payload.get_string()?;
+            let instruction = payload.get_string()?;
+            let instruction_lang = payload.get_string()?;
+            let num_prompts = payload.get_u32()?;
+            let mut prompts = Vec::new();
+            for _ in 0..num_prompts {
+                let prompt = payload.get_string()?;
+                let echo = payload.get_bool()?;
+                prompts.push(AuthInfoPrompt { prompt, echo });
+            }
+            log::debug!("received SSH_MSG_USERAUTH_INFO_REQUEST with {} prompts", prompts.len());
+
+            if let Some(result_tx) = self.result_tx.take() {
+                self.info_request_prompts = Some(prompts.len());
+                let request = AuthInfoRequest { name, instruction, instruction_lang, prompts };
+                let _: Result<_, _> = result_tx.send(AuthKeyboardInteractiveResult::InfoRequest(request));
+            }
+            Ok(())
+        } else {
+            Err(Error::PacketNotImplemented(msg_id))
+        }
+    }
+
+    fn send_packet(&mut self, _session_id: &[u8]) -> Option<Bytes> {
+        if self.result_tx.is_some() {
+            let mut payload = PacketEncode::new();
+            match self.packet.take()? {
+                Packet::Request { username } => {
+                    payload.put_u8(msg::USERAUTH_REQUEST);
+                    payload.put_str(&username);
+                    payload.put_str("ssh-connection");
+                    payload.put_str("keyboard-interactive");
+                    payload.put_str(""); // language tag
+                    payload.put_str(""); // submethods
+                    log::debug!("sending SSH_MSG_USERAUTH_REQUEST for method 'keyboard-interactive'");
+                },
+                Packet::Response { responses } => {
+                    payload.put_u8(msg::USERAUTH_INFO_RESPONSE);
+                    payload.put_u32(responses.len() as u32);
+                    for response in responses.iter() {
+                        payload.put_str(response);
+                    }
+                    log::debug!("sending SSH_MSG_USERAUTH_INFO_RESPONSE with {} responses", responses.len());
+                },
+            }
+            return Some(payload.finish())
+        }
+        None
+    }
+
+    fn poll(&mut self) -> Poll<Result<()>> {
+        if self.result_tx.is_some() {
+            Poll::Pending
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn info_request_prompts(&self) -> Option<usize> {
+        self.info_request_prompts
+    }
+}
+
+impl AuthKeyboardInteractiveResult {
+    /// Returns `Ok` if this is a success, `Err` otherwise.
+    pub fn success_or_error(&self) -> Result<()> {
+        match self {
+            Self::Success => Ok(()),
+            Self::InfoRequest(_) | Self::Failure(_) => Err(Error::AuthFailed),
+        }
+    }
+}
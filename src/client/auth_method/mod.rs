@@ -4,6 +4,7 @@ use crate::codec::PacketDecode;
 use crate::error::Result;
 use super::auth::AuthFailure;
 
+pub mod keyboard_interactive;
 pub mod none;
 pub mod password;
 pub mod pubkey;
@@ -14,4 +15,10 @@ pub trait AuthMethod {
     fn recv_packet(&mut self, msg_id: u8, payload: &mut PacketDecode) -> Result<()>;
     fn send_packet(&mut self, session_id: &[u8]) -> Option<Bytes>;
     fn poll(&mut self) -> Poll<Result<()>>;
+
+    // number of prompts in the keyboard-interactive request that the user should respond to, if
+    // the method finished with such a request
+    fn info_request_prompts(&self) -> Option<usize> {
+        None
+    }
 }
@@ -1,11 +1,10 @@
 use bytes::Bytes;
-use derivative::Derivative;
 use std::task::Poll;
 use tokio::sync::oneshot;
 use crate::codec::{PacketDecode, PacketEncode};
 use crate::codes::msg;
 use crate::error::{Result, Error};
-use crate::pubkey::{PubkeyAlgo, Pubkey, Privkey};
+use crate::pubkey::{PubkeyAlgo, Pubkey};
 use super::super::auth::AuthFailure;
 use super::AuthMethod;
 
@@ -20,28 +19,34 @@ pub enum AuthPubkeyResult {
     Failure(AuthFailure),
 }
 
-#[derive(Derivative)]
-#[derivative(Debug)]
+#[derive(Debug)]
 pub struct AuthPubkey {
     username: String,
-    #[derivative(Debug = "ignore")]
-    privkey: Privkey,
     pubkey_blob: Bytes,
     pubkey_algo: &'static PubkeyAlgo,
+    signed_tx: Option<oneshot::Sender<Bytes>>,
+    signature_rx: oneshot::Receiver<Result<Bytes>>,
     request_sent: bool,
     result_tx: Option<oneshot::Sender<Result<AuthPubkeyResult>>>,
 }
 
 impl AuthPubkey {
+    /// Creates the "publickey" method that does not sign the request by itself.
+    ///
+    /// Instead, we send the data that should be signed to `signed_tx` and wait for the signature
+    /// from `signature_rx`. This allows the caller to produce the signature asynchronously (e.g.
+    /// using an SSH agent). Whoever sends the signature must then wake up the client.
     pub fn new(
         username: String,
-        privkey: Privkey,
+        pubkey_blob: Bytes,
         pubkey_algo: &'static PubkeyAlgo,
+        signed_tx: oneshot::Sender<Bytes>,
+        signature_rx: oneshot::Receiver<Result<Bytes>>,
         result_tx: oneshot::Sender<Result<AuthPubkeyResult>>,
     ) -> AuthPubkey {
-        let pubkey_blob = privkey.pubkey().encode();
         AuthPubkey {
-            username, privkey, pubkey_blob, pubkey_algo,
+            username, pubkey_blob, pubkey_algo,
+            signed_tx: Some(signed_tx), signature_rx,
             request_sent: false, result_tx: Some(result_tx),
         }
     }
@@ -70,27 +75,32 @@ impl AuthMethod for AuthPubkey {
 
     fn send_packet(&mut self, session_id: &[u8]) -> Option<Bytes> {
         if !self.request_sent && self.result_tx.is_some() {
-            let mut signed = PacketEncode::new();
-            signed.put_bytes(session_id);
-            signed.put_u8(msg::USERAUTH_REQUEST);
-            signed.put_str(&self.username);
-            signed.put_str("ssh-connection");
-            signed.put_str("publickey");
-            signed.put_bool(true);
-            signed.put_str(self.pubkey_algo.name);
-            signed.put_bytes(&self.pubkey_blob);
-            let signed = signed.finish();
-
-            let signature = match (self.pubkey_algo.sign)(&self.privkey, &signed) {
-                Ok(signature) => signature,
-                Err(err) => {
+            if let Some(signed_tx) = self.signed_tx.take() {
+                let mut signed = PacketEncode::new();
+                signed.put_bytes(session_id);
+                signed.put_u8(msg::USERAUTH_REQUEST);
+                signed.put_str(&self.username);
+                signed.put_str("ssh-connection");
+                signed.put_str("publickey");
+                signed.put_bool(true);
+                signed.put_str(self.pubkey_algo.name);
+                signed.put_bytes(&self.pubkey_blob);
+                let _: Result<_, _> = signed_tx.send(signed.finish());
+            }
+
+            let signature = match self.signature_rx.try_recv() {
+                Ok(Ok(signature)) => signature,
+                Ok(Err(err)) => {
                     // if the signing failed, the user most likely gave us incompatible `privkey`
                     // and `pubkey_algo`. instead of raising the error from this method (and
                     // killing the whole client), we send the error to `result_tx`, so that it will
                     // be returned from `Client::auth_pubkey()`
-                    if let Some(result_tx) = self.result_tx.take() {
-                        let _: Result<_, _> = result_tx.send(Err(err));
-                    }
+                    self.send_result(Err(err));
+                    return None
+                },
+                Err(oneshot::error::TryRecvError::Empty) => return None,
+                Err(oneshot::error::TryRecvError::Closed) => {
+                    self.send_result(Err(Error::AuthAborted));
                     return None
                 },
             };
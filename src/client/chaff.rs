@@ -0,0 +1,104 @@
+use std::future::Future as _;
+use std::pin::Pin;
+use std::task::Context;
+use std::time::Duration;
+use tokio::time::{Instant, Sleep};
+use crate::codec::PacketEncode;
+use crate::codes::msg;
+use crate::error::Result;
+use super::{auth, conn, negotiate};
+use super::client_state::ClientState;
+use super::pump::Pump;
+
+// after the last keystroke, we keep sending chaff for a random duration from this range (the same
+// as in OpenSSH)
+const CHAFF_AFTER_MIN_MS: u64 = 1024;
+const CHAFF_AFTER_RANGE_MS: u64 = 2048;
+
+// length of the data in a chaff packet, chosen so that the chaff packet has the same length as
+// `SSH_MSG_CHANNEL_DATA` with a single byte. The keystrokes held back between two ticks are sent
+// in one packet, so this matches the usual case of a single keystroke per tick; when the user types
+// faster than the ticks, the data packet is longer than the chaff (OpenSSH behaves the same)
+const CHAFF_DATA_LEN: usize = 5;
+
+#[derive(Default)]
+pub(super) struct ChaffState {
+    sleep: Option<Pin<Box<Sleep>>>,
+    chaff_until: Option<Instant>,
+    tick: bool,
+    keystroke_sent: bool,
+}
+
+pub(super) fn init_chaff() -> ChaffState {
+    ChaffState::default()
+}
+
+// decides whether a channel with a pty can send data (a "keystroke") now: while we obscure the
+// timing, the keystrokes are held back and sent only on the ticks of the timer
+pub(super) fn may_send_keystroke(st: &mut ClientState) -> bool {
+    let Some(interval) = st.config.obscure_keystroke_timing else { return true };
+
+    let chaff_after = CHAFF_AFTER_MIN_MS + st.rng.as_rngcore().next_u64() % CHAFF_AFTER_RANGE_MS;
+    let chaff_until = Instant::now() + Duration::from_millis(chaff_after);
+
+    let chaff_st = &mut st.chaff_st;
+    if chaff_st.tick {
+        chaff_st.keystroke_sent = true;
+        chaff_st.chaff_until = Some(chaff_until);
+        return true
+    }
+
+    if chaff_st.sleep.is_none() {
+        log::trace!("starting to obscure keystroke timing");
+        chaff_st.sleep = Some(Box::pin(tokio::time::sleep(interval)));
+        chaff_st.chaff_until = Some(chaff_until);
+    }
+    false
+}
+
+pub(super) fn pump_chaff(st: &mut ClientState, cx: &mut Context) -> Result<Pump> {
+    let Some(interval) = st.config.obscure_keystroke_timing else { return Ok(Pump::Pending) };
+    let Some(sleep) = st.chaff_st.sleep.as_mut() else { return Ok(Pump::Pending) };
+    pump_ready!(sleep.as_mut().poll(cx));
+
+    // on every tick, we send all keystrokes that were held back since the previous tick (merged
+    // into a single packet for every channel); if there are none, we send a chaff packet instead,
+    // so that the server (and anybody who watches the connection) sees a packet on every tick
+    st.chaff_st.tick = true;
+    st.chaff_st.keystroke_sent = false;
+    let res = conn::pump_channels(st, cx);
+    st.chaff_st.tick = false;
+    res?;
+
+    let now = Instant::now();
+    if !st.chaff_st.keystroke_sent {
+        if !matches!(st.chaff_st.chaff_until, Some(until) if now < until) {
+            log::trace!("stopping to obscure keystroke timing");
+            st.chaff_st.sleep = None;
+            st.chaff_st.chaff_until = None;
+            return Ok(Pump::Progress)
+        }
+
+        if auth::is_authenticated(st) && negotiate::is_ready(st) {
+            send_chaff(st)?;
+        }
+    }
+
+    // keep the ticks on a fixed grid, unless we fell behind
+    let sleep = st.chaff_st.sleep.as_mut().unwrap();
+    let deadline = sleep.deadline() + interval;
+    sleep.as_mut().reset(if deadline > now { deadline } else { now + interval });
+    Ok(Pump::Progress)
+}
+
+fn send_chaff(st: &mut ClientState) -> Result<()> {
+    let mut data = [0; CHAFF_DATA_LEN];
+    st.rng.as_rngcore().fill_bytes(&mut data);
+
+    let mut payload = PacketEncode::new();
+    payload.put_u8(msg::IGNORE);
+    payload.put_bytes(&data);
+    st.codec.send_pipe.feed_packet(&payload.finish())?;
+    log::trace!("sending SSH_MSG_IGNORE as chaff");
+    Ok(())
+}
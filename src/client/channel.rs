@@ -3,6 +3,7 @@ use parking_lot::Mutex;
 use std::future::Future;
 use std::sync::{Arc, Weak};
 use std::task::{Context, Poll};
+use std::time::Duration;
 use tokio::sync::{mpsc, oneshot};
 use crate::error::{Result, Error};
 use super::channel_state::{self, ChannelState, ChannelSendData};
@@ -305,6 +306,20 @@ pub struct ChannelConfig {
     /// on this value. If your value exceeds these limits, we will silently clamp it to the allowed
     /// range.
     pub recv_packet_len_max: usize,
+
+    /// Timeout for opening the channel.
+    ///
+    /// If the server does not confirm or reject the channel within this duration, opening the
+    /// channel fails with [`Error::ChannelOpenTimeout`]. If the server confirms the channel later,
+    /// we close it immediately. By default, this is `None` (no timeout).
+    pub open_timeout: Option<Duration>,
+
+    /// Timeout for requests on the channel.
+    ///
+    /// If the server does not reply to a request on a [`Session`][super::Session] within this
+    /// duration, [`SessionResp::wait()`][super::SessionResp::wait()] fails with
+    /// [`Error::ChannelReqTimeout`]. By default, this is `None` (no timeout).
+    pub request_timeout: Option<Duration>,
 }
 
 impl Default for ChannelConfig {
@@ -312,6 +327,8 @@ impl Default for ChannelConfig {
         ChannelConfig {
             recv_window_max: 500_000,
             recv_packet_len_max: 100_000,
+            open_timeout: None,
+            request_timeout: None,
         }
     }
 }
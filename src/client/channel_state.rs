@@ -1,4 +1,4 @@
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use futures_core::ready;
 use parking_lot::Mutex;
 use std::cmp::min;
@@ -12,7 +12,7 @@ use tokio_util::sync::PollSender;
 use crate::codec::{PacketEncode, PacketDecode};
 use crate::codes::msg;
 use crate::error::{Result, Error};
-use super::negotiate;
+use super::{chaff, negotiate};
 use super::channel::{ChannelEvent, ChannelReq, ChannelReply, DataType};
 use super::client_state::{self, ClientState};
 use super::pump::Pump;
@@ -34,6 +34,7 @@ pub(super) struct ChannelState {
     close_sent: bool,
     close_recvd: bool,
     closed: bool,
+    interactive: bool,
     event_tx: PollSender<ChannelEvent>,
     send_reqs: VecDeque<ChannelReq>,
     send_datas: VecDeque<SendData>,
@@ -75,6 +76,7 @@ pub(super) fn init_channel(init: ChannelInit) -> ChannelState {
         close_sent: false,
         close_recvd: false,
         closed: false,
+        interactive: false,
         event_tx: PollSender::new(init.event_tx),
         send_reqs: VecDeque::new(),
         send_datas: VecDeque::new(),
@@ -96,7 +98,7 @@ pub(super) fn pump_channel(
 
     if (channel_st.close_recvd || channel_st.want_close) && !channel_st.close_sent {
         if negotiate::is_ready(st) {
-            send_channel_close(st, channel_st);
+            send_channel_close(st, channel_st)?;
             channel_st.close_sent = true;
             return Ok(Pump::Progress)
         }
@@ -113,7 +115,7 @@ pub(super) fn pump_channel(
 
     if negotiate::is_ready(st) && !channel_st.close_sent {
         if let Some(req) = channel_st.send_reqs.pop_front() {
-            send_channel_request(st, channel_st, &req);
+            send_channel_request(st, channel_st, &req)?;
             if let Some(reply_tx) = req.reply_tx {
                 channel_st.recv_replies.push_back(RecvReply { reply_tx });
             }
@@ -127,14 +129,19 @@ pub(super) fn pump_channel(
                     Ok(reply) => reply,
                     Err(_) => ChannelReply::Failure,
                 };
-                send_channel_reply(st, channel_st, reply);
+                send_channel_reply(st, channel_st, reply)?;
                 channel_st.send_replies.pop_front();
                 return Ok(Pump::Progress)
             }
         }
 
-        if let Some(mut data) = channel_st.send_datas.pop_front() {
-            if send_channel_data(st, channel_st, &mut data.data) {
+        if channel_st.send_datas.front().is_some_and(|data| is_keystroke(channel_st, &data.data)) {
+            if send_len_max(channel_st) > 0 && chaff::may_send_keystroke(st) {
+                send_keystrokes(st, channel_st)?;
+                return Ok(Pump::Progress)
+            }
+        } else if let Some(mut data) = channel_st.send_datas.pop_front() {
+            if send_channel_data(st, channel_st, &mut data.data)? {
                 let _: Result<_, _> = data.sent_tx.send(());
                 return Ok(Pump::Progress)
             } else {
@@ -144,7 +151,7 @@ pub(super) fn pump_channel(
 
         let recv_window_delta = channel_st.recv_window_max - channel_st.recv_window;
         if recv_window_delta >= channel_st.recv_window_max / 8 {
-            send_channel_window_adjust(st, channel_st, recv_window_delta);
+            send_channel_window_adjust(st, channel_st, recv_window_delta)?;
             channel_st.recv_window += recv_window_delta;
             return Ok(Pump::Progress)
         }
@@ -163,21 +170,31 @@ pub(super) fn send_request(
     if channel_st.closed {
         return Err(Error::ChannelClosed)
     }
+    if req.request_type == "pty-req" {
+        // data sent to a channel with a pty is probably typed by a human, so we may need to
+        // obscure the keystroke timing
+        channel_st.interactive = true;
+    }
     channel_st.send_reqs.push_back(req);
     client_state::wakeup_client(st);
     Ok(())
 }
 
-fn send_channel_request(st: &mut ClientState, channel_st: &ChannelState, req: &ChannelReq) {
+fn send_channel_request(
+    st: &mut ClientState,
+    channel_st: &ChannelState,
+    req: &ChannelReq,
+) -> Result<()> {
     let mut payload = PacketEncode::new();
     payload.put_u8(msg::CHANNEL_REQUEST);
     payload.put_u32(channel_st.their_id);
     payload.put_str(&req.request_type);
     payload.put_bool(req.reply_tx.is_some());
     payload.put_raw(&req.payload);
-    st.codec.send_pipe.feed_packet(&payload.finish());
+    st.codec.send_pipe.feed_packet(&payload.finish())?;
     log::debug!("sending SSH_MSG_CHANNEL_REQUEST {:?} for our channel {}",
         req.request_type, channel_st.our_id);
+    Ok(())
 }
 
 pub(super) fn recv_channel_success(channel_st: &mut ChannelState) -> ResultRecvState {
@@ -226,7 +243,11 @@ pub(super) fn recv_channel_request(
     send_event(channel_mutex, ChannelEvent::Request(channel_req))
 }
 
-fn send_channel_reply(st: &mut ClientState, channel_st: &ChannelState, reply: ChannelReply) {
+fn send_channel_reply(
+    st: &mut ClientState,
+    channel_st: &ChannelState,
+    reply: ChannelReply,
+) -> Result<()> {
     let mut payload = PacketEncode::new();
     match reply {
         ChannelReply::Success => {
@@ -239,7 +260,8 @@ fn send_channel_reply(st: &mut ClientState, channel_st: &ChannelState, reply: Ch
         },
     }
     payload.put_u32(channel_st.their_id);
-    st.codec.send_pipe.feed_packet(&payload.finish());
+    st.codec.send_pipe.feed_packet(&payload.finish())?;
+    Ok(())
 }
 
 
@@ -258,48 +280,90 @@ pub(super) fn send_data(
     Ok(async { sent_rx.await.map_err(|_| Error::ChannelClosed) })
 }
 
-fn send_channel_data(st: &mut ClientState, channel_st: &mut ChannelState, data: &mut ChannelSendData) -> bool {
+fn is_keystroke(channel_st: &ChannelState, data: &ChannelSendData) -> bool {
+    channel_st.interactive && matches!(data, ChannelSendData::Data(_, DataType::Standard))
+}
+
+fn send_len_max(channel_st: &ChannelState) -> usize {
+    min(channel_st.send_window, channel_st.send_len_max)
+}
+
+// sends all keystrokes that are queued at the front of `send_datas` in a single
+// `SSH_MSG_CHANNEL_DATA`, as far as the window allows: when we obscure the keystroke timing, the
+// server sees (at most) one packet per tick, no matter how many keystrokes were held back
+fn send_keystrokes(st: &mut ClientState, channel_st: &mut ChannelState) -> Result<()> {
+    let send_len_max = send_len_max(channel_st);
+    let mut send_data = BytesMut::new();
+    while let Some(front) = channel_st.send_datas.front_mut() {
+        let ChannelSendData::Data(ref mut data, DataType::Standard) = front.data else { break };
+        let send_len = min(data.len(), send_len_max - send_data.len());
+        send_data.extend_from_slice(&data.split_to(send_len));
+        if !data.is_empty() { break }
+
+        let data = channel_st.send_datas.pop_front().unwrap();
+        let _: Result<_, _> = data.sent_tx.send(());
+    }
+
+    if !send_data.is_empty() {
+        send_data_packet(st, channel_st, &send_data, DataType::Standard)?;
+    }
+    Ok(())
+}
+
+fn send_channel_data(
+    st: &mut ClientState,
+    channel_st: &mut ChannelState,
+    data: &mut ChannelSendData,
+) -> Result<bool> {
     match data {
         ChannelSendData::Data(ref mut data, data_type) => {
-            if data.is_empty() { return true }
+            if data.is_empty() { return Ok(true) }
 
-            let send_len = min(data.len(), min(channel_st.send_window, channel_st.send_len_max));
-            if send_len == 0 { return false }
+            let send_len = min(data.len(), send_len_max(channel_st));
+            if send_len == 0 { return Ok(false) }
             let send_data = data.split_to(send_len);
-
-            let mut payload = PacketEncode::new();
-            match data_type {
-                DataType::Standard => {
-                    payload.put_u8(msg::CHANNEL_DATA);
-                    payload.put_u32(channel_st.their_id);
-                    log::trace!("sending SSH_MSG_CHANNEL_DATA for our channel {} with {} bytes",
-                        channel_st.our_id, send_data.len());
-                },
-                DataType::Extended(code) => {
-                    payload.put_u8(msg::CHANNEL_EXTENDED_DATA);
-                    payload.put_u32(channel_st.their_id);
-                    payload.put_u32(*code);
-                    log::trace!("sending SSH_MSG_CHANNEL_EXTENDED_DATA for our channel {}, code {}, with {} bytes",
-                        channel_st.our_id, code, send_data.len());
-                },
-            }
-            payload.put_bytes(&send_data);
-            st.codec.send_pipe.feed_packet(&payload.finish());
-
-            channel_st.send_window -= send_len;
-            false
+            send_data_packet(st, channel_st, &send_data, *data_type)?;
+            Ok(false)
         },
         ChannelSendData::Eof => {
             let mut payload = PacketEncode::new();
             payload.put_u8(msg::CHANNEL_EOF);
             payload.put_u32(channel_st.their_id);
-            st.codec.send_pipe.feed_packet(&payload.finish());
+            st.codec.send_pipe.feed_packet(&payload.finish())?;
             log::debug!("sending SSH_MSG_CHANNEL_EOF for our channel {}", channel_st.our_id);
-            true
+            Ok(true)
         },
     }
 }
 
+fn send_data_packet(
+    st: &mut ClientState,
+    channel_st: &mut ChannelState,
+    send_data: &[u8],
+    data_type: DataType,
+) -> Result<()> {
+    let mut payload = PacketEncode::new();
+    match data_type {
+        DataType::Standard => {
+            payload.put_u8(msg::CHANNEL_DATA);
+            payload.put_u32(channel_st.their_id);
+            log::trace!("sending SSH_MSG_CHANNEL_DATA for our channel {} with {} bytes",
+                channel_st.our_id, send_data.len());
+        },
+        DataType::Extended(code) => {
+            payload.put_u8(msg::CHANNEL_EXTENDED_DATA);
+            payload.put_u32(channel_st.their_id);
+            payload.put_u32(code);
+            log::trace!("sending SSH_MSG_CHANNEL_EXTENDED_DATA for our channel {}, code {}, with {} bytes",
+                channel_st.our_id, code, send_data.len());
+        },
+    }
+    payload.put_bytes(send_data);
+    st.codec.send_pipe.feed_packet(&payload.finish())?;
+    channel_st.send_window -= send_data.len();
+    Ok(())
+}
+
 pub(super) fn recv_channel_data(
     channel_st: &mut ChannelState,
     channel_mutex: Arc<Mutex<ChannelState>>,
@@ -349,14 +413,15 @@ fn send_channel_window_adjust(
     st: &mut ClientState,
     channel_st: &mut ChannelState,
     adjust: usize,
-) {
+) -> Result<()> {
     let mut payload = PacketEncode::new();
     payload.put_u8(msg::CHANNEL_WINDOW_ADJUST);
     payload.put_u32(channel_st.their_id);
     payload.put_u32(adjust as u32);
-    st.codec.send_pipe.feed_packet(&payload.finish());
+    st.codec.send_pipe.feed_packet(&payload.finish())?;
     log::trace!("sending SSH_MSG_CHANNEL_WINDOW_ADJUST for our channel {} with {} bytes",
         channel_st.our_id, adjust);
+    Ok(())
 }
 
 pub(super) fn recv_channel_window_adjust(
@@ -384,12 +449,19 @@ pub(super) fn close(st: &mut ClientState, channel_st: &mut ChannelState) {
     }
 }
 
-fn send_channel_close(st: &mut ClientState, channel_st: &ChannelState) {
+// closes a channel that nobody is interested in (for example, because opening the channel timed
+// out before the server confirmed it)
+pub(super) fn close_unused(channel_st: &mut ChannelState) {
+    channel_st.want_close = true;
+}
+
+fn send_channel_close(st: &mut ClientState, channel_st: &ChannelState) -> Result<()> {
     let mut payload = PacketEncode::new();
     payload.put_u8(msg::CHANNEL_CLOSE);
     payload.put_u32(channel_st.their_id);
-    st.codec.send_pipe.feed_packet(&payload.finish());
+    st.codec.send_pipe.feed_packet(&payload.finish())?;
     log::debug!("sending SSH_MSG_CHANNEL_CLOSE for our channel {}", channel_st.our_id);
+    Ok(())
 }
 
 pub(super) fn recv_channel_close(channel_st: &mut ChannelState) -> ResultRecvState {
@@ -433,3 +505,48 @@ fn send_event(channel_mutex: Arc<Mutex<ChannelState>>, event: ChannelEvent) -> R
 
     Ok(Some(Box::new(SendEventState { channel_mutex, event: Some(event) })))
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+    use crate::client::ClientConfig;
+    use super::*;
+
+    #[test]
+    fn test_send_keystrokes_merged() {
+        let (client_event_tx, _client_event_rx) = mpsc::channel(1);
+        let mut st = client_state::new_client(ClientConfig::default(), Box::new(OsRng), client_event_tx).unwrap();
+        let (event_tx, _event_rx) = mpsc::channel(1);
+        let mut channel_st = init_channel(ChannelInit {
+            our_id: 0, their_id: 0, event_tx,
+            send_window: 5, send_len_max: 1000, recv_window_max: 1000,
+        });
+        channel_st.interactive = true;
+
+        let mut sent_rxs = Vec::new();
+        for data in ["ab", "c", "def", "g"] {
+            let (sent_tx, sent_rx) = oneshot::channel();
+            let data = ChannelSendData::Data(Bytes::from_static(data.as_bytes()), DataType::Standard);
+            channel_st.send_datas.push_back(SendData { data, sent_tx });
+            sent_rxs.push(sent_rx);
+        }
+        let (sent_tx, _sent_rx) = oneshot::channel();
+        channel_st.send_datas.push_back(SendData { data: ChannelSendData::Eof, sent_tx });
+
+        // the first packet is limited by the window
+        send_keystrokes(&mut st, &mut channel_st).unwrap();
+        assert_eq!(channel_st.send_window, 0);
+        assert!(sent_rxs[0].try_recv().is_ok());
+        assert!(sent_rxs[1].try_recv().is_ok());
+        assert!(sent_rxs[2].try_recv().is_err());
+        assert_eq!(channel_st.send_datas.len(), 3);
+
+        // the second packet stops before the EOF
+        channel_st.send_window = 100;
+        send_keystrokes(&mut st, &mut channel_st).unwrap();
+        assert_eq!(channel_st.send_window, 98);
+        assert!(sent_rxs[2].try_recv().is_ok());
+        assert!(sent_rxs[3].try_recv().is_ok());
+        assert!(matches!(channel_st.send_datas.front().unwrap().data, ChannelSendData::Eof));
+    }
+}
@@ -10,13 +10,17 @@ use std::task::{Context, Poll};
 use std::time::Duration;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::sync::{mpsc, oneshot};
+use crate::agent::{Agent, AgentIdentity};
 use crate::cipher::{self, CipherAlgo};
 use crate::codec::{PacketDecode, PacketEncode};
+use crate::compress::{self, CompressAlgo};
 use crate::error::{Error, Result, DisconnectError};
-use crate::kex::{self, KexAlgo};
+use crate::kex::{self, KexAlgo, DhGexConfig};
 use crate::mac::{self, MacAlgo};
-use crate::pubkey::{self, PubkeyAlgo, Pubkey, Privkey};
-use super::{auth, negotiate};
+use crate::pubkey::{self, PubkeyAlgo, Pubkey, Certificate, Signer};
+use crate::util::{self, SyncFuture};
+use super::{algo_list, auth, negotiate, ping};
+use super::auth_method::keyboard_interactive::{AuthKeyboardInteractive, AuthKeyboardInteractiveResult};
 use super::auth_method::none::{AuthNone, AuthNoneResult};
 use super::auth_method::password::{AuthPassword, AuthPasswordResult};
 use super::auth_method::pubkey::{AuthPubkey, AuthPubkeyResult, CheckPubkey};
@@ -24,6 +28,8 @@ use super::channel::{Channel, ChannelReceiver, ChannelConfig};
 use super::client_event::ClientReceiver;
 use super::client_state::{self, ClientState};
 use super::conn::{self, OpenChannel};
+use super::jump::JumpStream;
+use super::negotiate::NegotiatedAlgos;
 use super::session::{Session, SessionReceiver};
 use super::tunnel::{Tunnel, TunnelReceiver};
 
@@ -101,7 +107,7 @@ impl Client {
         let (result_tx, result_rx) = oneshot::channel();
         let method = AuthNone::new(username, result_tx);
         auth::start_method(&mut self.upgrade()?.lock(), Box::new(method))?;
-        result_rx.await.map_err(|_| Error::AuthAborted)
+        self.wait_auth(result_rx).await
     }
 
     /// Try to authenticate using the "password" method.
@@ -117,7 +123,54 @@ impl Client {
         let (result_tx, result_rx) = oneshot::channel();
         let method = AuthPassword::new(username, password, result_tx);
         auth::start_method(&mut self.upgrade()?.lock(), Box::new(method))?;
-        result_rx.await.map_err(|_| Error::AuthAborted)
+        self.wait_auth(result_rx).await
+    }
+
+    /// Try to authenticate using the "keyboard-interactive" method.
+    ///
+    /// In the "keyboard-interactive" method (RFC 4256), the server sends you any number of
+    /// requests with a list of prompts (such as a password or a one-time code), and you respond to
+    /// each request. This is typically backed by PAM on the server.
+    ///
+    /// If the server sends a request, you will get [`AuthKeyboardInteractiveResult::InfoRequest`]
+    /// with the prompts. You should then answer these prompts by calling
+    /// [`auth_keyboard_interactive_respond()`][Self::auth_keyboard_interactive_respond()], which
+    /// returns the next result, until you get a success or a failure.
+    ///
+    /// If a previous authentication attempt was successful, this call immediately succeeds. If you
+    /// start another authentication attempt before this attempt is resolved, it will fail with
+    /// [`Error::AuthPending`].
+    pub async fn auth_keyboard_interactive(&self, username: String) -> Result<AuthKeyboardInteractiveResult> {
+        let (result_tx, result_rx) = oneshot::channel();
+        let method = AuthKeyboardInteractive::new_request(username, result_tx);
+        auth::start_method(&mut self.upgrade()?.lock(), Box::new(method))?;
+        self.wait_auth(result_rx).await
+    }
+
+    /// Respond to a request from the "keyboard-interactive" method.
+    ///
+    /// You should call this method only after you received an
+    /// [`AuthKeyboardInteractiveResult::InfoRequest`] from
+    /// [`auth_keyboard_interactive()`][Self::auth_keyboard_interactive()] or from a previous call
+    /// of this method. The `responses` must correspond to the prompts in the
+    /// [`AuthInfoRequest`][super::AuthInfoRequest], in the same order.
+    ///
+    /// If the server did not send a request (or you already responded to it), or if the number
+    /// of `responses` does not match the number of prompts, this fails with
+    /// [`Error::AuthInfoResponse`] and we don't send anything to the server.
+    pub async fn auth_keyboard_interactive_respond(
+        &self,
+        responses: Vec<String>,
+    ) -> Result<AuthKeyboardInteractiveResult> {
+        let (result_tx, result_rx) = oneshot::channel();
+        {
+            let client_st = self.upgrade()?;
+            let mut st = client_st.lock();
+            auth::check_info_response(&st, responses.len())?;
+            let method = AuthKeyboardInteractive::new_response(responses, result_tx);
+            auth::start_method(&mut st, Box::new(method))?;
+        }
+        self.wait_auth(result_rx).await
     }
 
     /// Try to authenticate using the "publickey" method.
@@ -125,24 +178,107 @@ impl Client {
     /// With the "publickey" method (RFC 4252, section 7), the server knows your public key and you
     /// prove that you own the corresponding private key.
     ///
-    /// You must specify the private key `privkey` and also `pubkey_algo`, the pubkey algorithm
-    /// that is used to prove that you own the private key (see [`Pubkey::algos_secure()`] and
+    /// You must specify the `signer` that holds the private key (usually a [`Privkey`][crate::Privkey], but you can
+    /// use any implementation of [`Signer`]) and also `pubkey_algo`, the pubkey algorithm that is
+    /// used to prove that you own the private key (see [`Pubkey::algos_secure()`] and
     /// [`Pubkey::algos_compatible_less_secure()`]). If you supply `pubkey_algo` that is not
-    /// compatible with the `privkey`, you will get an [`Error::PrivkeyFormat`].
+    /// compatible with the private key, you will get an [`Error::PrivkeyFormat`].
     ///
     /// If a previous authentication attempt was successful, this call immediately succeeds. If you
     /// start another authentication attempt before this attempt is resolved, it will fail with
     /// [`Error::AuthPending`].
-    pub async fn auth_pubkey(
+    pub async fn auth_pubkey<S: Signer>(
+        &self,
+        username: String,
+        signer: S,
+        pubkey_algo: &'static PubkeyAlgo,
+    ) -> Result<AuthPubkeyResult> {
+        let pubkey_blob = signer.pubkey().encode();
+        self.auth_pubkey_blob(username, &signer, pubkey_blob, pubkey_algo).await
+    }
+
+    /// Try to authenticate using the "publickey" method with an OpenSSH certificate.
+    ///
+    /// This works like [`auth_pubkey()`][Self::auth_pubkey()], but instead of the bare public key,
+    /// we present the user certificate `cert` to the server, so the server does not need to know
+    /// your public key in advance; it only needs to trust the certificate authority that signed
+    /// the certificate.
+    ///
+    /// The certificate must certify the public key of `signer`, otherwise you will get an
+    /// [`Error::CertificateMismatch`]. The `pubkey_algo` must be one of the certificate algorithms
+    /// (see [`Certificate::algos()`]), otherwise you will get an [`Error::PubkeyFormat`].
+    pub async fn auth_pubkey_cert<S: Signer>(
+        &self,
+        username: String,
+        signer: S,
+        cert: &Certificate,
+        pubkey_algo: &'static PubkeyAlgo,
+    ) -> Result<AuthPubkeyResult> {
+        if cert.pubkey != signer.pubkey() {
+            return Err(Error::CertificateMismatch)
+        } else if !cert.algos().iter().any(|algo| algo.name == pubkey_algo.name) {
+            return Err(Error::PubkeyFormat)
+        }
+
+        self.auth_pubkey_blob(username, &signer, cert.encode(), pubkey_algo).await
+    }
+
+    /// Try to authenticate using the "publickey" method with a key held by an SSH agent.
+    ///
+    /// This works like [`auth_pubkey()`][Self::auth_pubkey()], but the private key never leaves
+    /// the `agent`: we ask the agent to sign the authentication request using the key of
+    /// `identity` (see [`Agent::list_identities()`]). If the identity is an OpenSSH certificate,
+    /// we present the certificate to the server, as in
+    /// [`auth_pubkey_cert()`][Self::auth_pubkey_cert()].
+    ///
+    /// The `pubkey_algo` must be one of [`AgentIdentity::algos()`], otherwise you will get an
+    /// [`Error::PubkeyFormat`]. If the agent refuses to sign the request, you will get an
+    /// [`Error::AgentFailure`].
+    pub async fn auth_pubkey_agent(
+        &self,
+        username: String,
+        agent: &Agent,
+        identity: &AgentIdentity,
+        pubkey_algo: &'static PubkeyAlgo,
+    ) -> Result<AuthPubkeyResult> {
+        if !identity.algos().iter().any(|algo| algo.name == pubkey_algo.name) {
+            return Err(Error::PubkeyFormat)
+        }
+
+        self.auth_pubkey_blob(username, &agent.signer(identity), identity.blob(), pubkey_algo).await
+    }
+
+    async fn auth_pubkey_blob(
         &self,
         username: String,
-        privkey: Privkey,
+        signer: &dyn Signer,
+        pubkey_blob: Bytes,
         pubkey_algo: &'static PubkeyAlgo,
     ) -> Result<AuthPubkeyResult> {
         let (result_tx, result_rx) = oneshot::channel();
-        let method = AuthPubkey::new(username, privkey, pubkey_algo, result_tx);
+        let (signed_tx, signed_rx) = oneshot::channel();
+        let (signature_tx, signature_rx) = oneshot::channel();
+        let method = AuthPubkey::new(username, pubkey_blob, pubkey_algo, signed_tx, signature_rx, result_tx);
         auth::start_method(&mut self.upgrade()?.lock(), Box::new(method))?;
-        result_rx.await.map_err(|_| Error::AuthAborted)?
+
+        let auth_timeout = self.upgrade()?.lock().config.auth_timeout;
+        util::timeout(auth_timeout, Error::AuthTimeout, async move {
+            // if we are already authenticated, the method is resolved without asking for the
+            // signature
+            if let Ok(signed) = signed_rx.await {
+                // the future from the signer might not be `Sync`, but we want our future to be `Sync`
+                let signature = SyncFuture::new(signer.sign(pubkey_algo, &signed)).await;
+                let _: Result<_, _> = signature_tx.send(signature);
+                client_state::wakeup_client(&mut self.upgrade()?.lock());
+            }
+            result_rx.await.map_err(|_| Error::AuthAborted)?
+        }).await?
+    }
+
+    async fn wait_auth<T>(&self, result_rx: oneshot::Receiver<T>) -> Result<T> {
+        let auth_timeout = self.upgrade()?.lock().config.auth_timeout;
+        util::timeout(auth_timeout, Error::AuthTimeout, result_rx).await?
+            .map_err(|_| Error::AuthAborted)
     }
 
     /// Check whether "publickey" authentication method would be acceptable.
@@ -159,7 +295,7 @@ impl Client {
         let (result_tx, result_rx) = oneshot::channel();
         let method = CheckPubkey::new(username, pubkey, pubkey_algo, result_tx);
         auth::start_method(&mut self.upgrade()?.lock(), Box::new(method))?;
-        result_rx.await.map_err(|_| Error::AuthAborted)
+        self.wait_auth(result_rx).await
     }
 
     /// Get the public key algorithms that the server supports for authentication.
@@ -186,6 +322,49 @@ impl Client {
         Ok(auth::is_authenticated(&self.upgrade()?.lock()))
     }
 
+    /// Get the algorithms that are currently used on the connection.
+    ///
+    /// Returns `None` until the first key exchange is finished. After every key exchange (see
+    /// [`Self::rekey()`]), the result is updated with the newly negotiated algorithms.
+    pub fn negotiated_algos(&self) -> Result<Option<NegotiatedAlgos>> {
+        Ok(self.upgrade()?.lock().negotiated_algos.clone())
+    }
+
+    /// Get the number of key exchanges that were finished on this connection.
+    ///
+    /// The initial key exchange is counted, so this returns 1 after the connection is
+    /// established and increases with every rekeying.
+    pub fn kex_count(&self) -> Result<u64> {
+        Ok(self.upgrade()?.lock().kex_count)
+    }
+
+    /// Get the identification string of the server (such as `SSH-2.0-OpenSSH_9.6`).
+    ///
+    /// The string is returned without the terminating CR LF. Returns `None` if we haven't
+    /// received the identification string yet.
+    pub fn server_ident(&self) -> Result<Option<Bytes>> {
+        Ok(self.upgrade()?.lock().their_ident.clone())
+    }
+
+    /// Get the lines that the server sent before its identification string.
+    ///
+    /// RFC 4253, section 4.2 allows the server to send other lines of data before the
+    /// identification string. These lines are returned without the line terminator. The list is
+    /// empty if the server did not send any such lines or if we haven't received the
+    /// identification string yet.
+    pub fn server_prefix_lines(&self) -> Result<Vec<Bytes>> {
+        Ok(self.upgrade()?.lock().their_prefix_lines.clone())
+    }
+
+    /// Get the session identifier.
+    ///
+    /// The session identifier is the exchange hash from the first key exchange (RFC 4253,
+    /// section 7.2) and it does not change when the connection is rekeyed. Returns `None` until
+    /// the first key exchange is finished.
+    pub fn session_id(&self) -> Result<Option<Bytes>> {
+        Ok(self.upgrade()?.lock().session_id.as_deref().map(Bytes::copy_from_slice))
+    }
+
     /// Open an SSH session to execute a program or the shell.
     ///
     /// If the session is opened successfully, you receive two objects:
@@ -233,6 +412,36 @@ impl Client {
         Tunnel::connect(self, config, connect_addr, originator_addr).await
     }
 
+    /// Open an SSH connection to another server through this client ("ProxyJump").
+    ///
+    /// We ask the server (the "jump host") to open a tunnel to `connect_addr`, exactly as in
+    /// [`connect_tunnel()`][Self::connect_tunnel()], and then start a new SSH connection over this
+    /// tunnel with the given `config`, as in [`Client::open()`]. The returned objects work in the
+    /// same way as the objects returned from [`Client::open()`]: you must authenticate the nested
+    /// client, handle the events from its [`ClientReceiver`] and poll its [`ClientFuture`].
+    ///
+    /// Note that the nested connection is carried by this client, so you must also keep polling
+    /// the [`ClientFuture`] of this client (and receiving its events) while you use the nested
+    /// client. To connect through multiple jump hosts, call this method on the nested client.
+    ///
+    /// When you drop the nested [`ClientFuture`], the tunnel is closed. If this client is closed
+    /// while the nested client is still connected, the nested [`ClientFuture`] fails with
+    /// [`Error::ReadIo`] that wraps [`Error::JumpClosed`], or [`Error::JumpFailed`] with the cause
+    /// if this client failed with an error. See [`JumpStream`] for details.
+    ///
+    /// This method will wait until you are authenticated before doing anything.
+    pub async fn connect_jump(
+        &self,
+        tunnel_config: ChannelConfig,
+        connect_addr: (String, u16),
+        config: ClientConfig,
+    ) -> Result<(Client, ClientReceiver, ClientFuture<JumpStream>)> {
+        let originator_addr = ("127.0.0.1".into(), 0);
+        let (tunnel, tunnel_rx) = Tunnel::connect(self, tunnel_config, connect_addr, originator_addr).await?;
+        let failure = self.upgrade()?.lock().failure.clone();
+        Client::open(JumpStream::new(tunnel, tunnel_rx, failure), config)
+    }
+
     /// Start listening for connections on the server and tunnels them to us ("remote
     /// forwarding").
     ///
@@ -261,7 +470,7 @@ impl Client {
             reply_tx: Some(reply_tx),
         })?;
 
-        Ok(ClientResp::map(reply_rx, |payload| {
+        Ok(ClientResp::map(reply_rx, self.global_req_timeout()?, |payload| {
             if payload.remaining_len() >= 4 {
                 payload.get_u32().map(|x| Some(x as u16))
             } else {
@@ -287,7 +496,69 @@ impl Client {
             reply_tx: Some(reply_tx),
         })?;
 
-        Ok(ClientResp::map(reply_rx, |_payload| Ok(())))
+        Ok(ClientResp::map(reply_rx, self.global_req_timeout()?, |_payload| Ok(())))
+    }
+
+    /// Open a tunnel by asking the server to connect to a Unix domain socket.
+    ///
+    /// This is the same as [`connect_tunnel()`][Self::connect_tunnel()], but the server will
+    /// connect to the Unix socket at `socket_path` instead of a TCP/IP address. This uses the
+    /// `direct-streamlocal@openssh.com` channel from OpenSSH, so it may not be supported by other
+    /// servers.
+    ///
+    /// This method will wait until you are authenticated before doing anything.
+    pub async fn connect_streamlocal(
+        &self,
+        config: ChannelConfig,
+        socket_path: String,
+    ) -> Result<(Tunnel, TunnelReceiver)> {
+        Tunnel::connect_streamlocal(self, config, socket_path).await
+    }
+
+    /// Start listening for connections on a Unix domain socket on the server and tunnel them to
+    /// us.
+    ///
+    /// This is the same as [`bind_tunnel()`][Self::bind_tunnel()], but the server will listen on
+    /// the Unix socket at `socket_path` instead of a TCP/IP address. This uses the
+    /// `streamlocal-forward@openssh.com` request from OpenSSH, so it may not be supported by other
+    /// servers.
+    ///
+    /// Once somebody connects to the socket on the server, you will receive
+    /// [`ClientEvent::StreamlocalTunnel`][super::ClientEvent::StreamlocalTunnel] from the
+    /// [`ClientReceiver`], and you then may accept the tunnel.
+    ///
+    /// This method will wait until you are authenticated before doing anything.
+    pub fn bind_streamlocal(&self, socket_path: String) -> Result<ClientResp<()>> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+
+        let mut payload = PacketEncode::new();
+        payload.put_str(&socket_path);
+        self.send_request(GlobalReq {
+            request_type: "streamlocal-forward@openssh.com".into(),
+            payload: payload.finish(),
+            reply_tx: Some(reply_tx),
+        })?;
+
+        Ok(ClientResp::map(reply_rx, self.global_req_timeout()?, |_payload| Ok(())))
+    }
+
+    /// Stop listening for connections on a Unix domain socket on the server.
+    ///
+    /// This cancels the forwarding set up by [`bind_streamlocal()`][Self::bind_streamlocal()].
+    ///
+    /// This method will wait until you are authenticated before doing anything.
+    pub fn unbind_streamlocal(&self, socket_path: String) -> Result<ClientResp<()>> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+
+        let mut payload = PacketEncode::new();
+        payload.put_str(&socket_path);
+        self.send_request(GlobalReq {
+            request_type: "cancel-streamlocal-forward@openssh.com".into(),
+            payload: payload.finish(),
+            reply_tx: Some(reply_tx),
+        })?;
+
+        Ok(ClientResp::map(reply_rx, self.global_req_timeout()?, |_payload| Ok(())))
     }
 
     /// Open a raw SSH channel (low level API).
@@ -314,6 +585,7 @@ impl Client {
         -> Result<(Channel, ChannelReceiver, Bytes)> 
     {
         let (result_tx, result_rx) = oneshot::channel();
+        let open_timeout = config.open_timeout;
         let open = OpenChannel {
             channel_type,
             recv_window_max: config.recv_window_max(),
@@ -323,7 +595,8 @@ impl Client {
         };
         conn::open_channel(&mut self.upgrade()?.lock(), open);
 
-        let result = result_rx.await.map_err(|_| Error::ChannelClosed)??;
+        let result = util::timeout(open_timeout, Error::ChannelOpenTimeout, result_rx).await?
+            .map_err(|_| Error::ChannelClosed)??;
 
         let channel = Channel {
             client_st: self.client_st.clone(), 
@@ -353,6 +626,32 @@ impl Client {
         self.send_request(req)
     }
 
+    /// Measure the round-trip time to the server.
+    ///
+    /// If the server supports the `ping@openssh.com` extension (OpenSSH 9.5 and newer), we send
+    /// `SSH2_MSG_PING` and wait for the `SSH2_MSG_PONG`. Otherwise, we fall back to a keepalive
+    /// request (see [`send_keepalive()`][Self::send_keepalive()]) and wait for the reply, which
+    /// includes the time that the server takes to process the request.
+    ///
+    /// This method will wait until you are authenticated before it sends anything, but this
+    /// waiting is not included in the returned time. If the server does not respond within
+    /// [`ClientConfig::global_req_timeout`], this returns [`Error::GlobalReqTimeout`].
+    pub async fn ping(&self) -> Result<Duration> {
+        let (rtt_tx, rtt_rx) = oneshot::channel();
+        let timeout = {
+            let client_st = self.upgrade()?;
+            let mut st = client_st.lock();
+            ping::ping(&mut st, rtt_tx);
+            st.config.global_req_timeout
+        };
+        util::timeout(timeout, Error::GlobalReqTimeout, rtt_rx).await?
+            .map_err(|_| Error::ClientClosed)
+    }
+
+    fn global_req_timeout(&self) -> Result<Option<Duration>> {
+        Ok(self.upgrade()?.lock().config.global_req_timeout)
+    }
+
     /// Send a global request (low level API).
     ///
     /// This sends `SSH_MSG_GLOBAL_REQUEST` to the server (RFC 4254, section 4). We simply enqueue
@@ -438,22 +737,25 @@ pub enum GlobalReply {
 #[must_use = "please use .wait().await to await the response, or .ignore() to ignore it"]
 pub struct ClientResp<T> {
     reply_rx: oneshot::Receiver<GlobalReply>,
+    timeout: Option<Duration>,
     #[derivative(Debug = "ignore")]
     map_fn: Box<dyn FnOnce(&mut PacketDecode) -> Result<T> + Send + Sync>,
 }
 
 impl<T> ClientResp<T> {
-    fn map<F>(reply_rx: oneshot::Receiver<GlobalReply>, map_fn: F) -> Self 
+    fn map<F>(reply_rx: oneshot::Receiver<GlobalReply>, timeout: Option<Duration>, map_fn: F) -> Self 
         where F: FnOnce(&mut PacketDecode) -> Result<T> + Send + Sync + 'static
     {
-        Self { reply_rx, map_fn: Box::new(map_fn) }
+        Self { reply_rx, timeout, map_fn: Box::new(map_fn) }
     }
 
     /// Wait for the response from the server.
     ///
-    /// If the request failed, this returns an error ([`Error::GlobalReq`]).
+    /// If the request failed, this returns an error ([`Error::GlobalReq`]). If the server does not
+    /// reply within [`ClientConfig::global_req_timeout`] after you call this method, this returns
+    /// [`Error::GlobalReqTimeout`].
     pub async fn wait(self) -> Result<T> {
-        match self.reply_rx.await {
+        match util::timeout(self.timeout, Error::GlobalReqTimeout, self.reply_rx).await? {
             Ok(GlobalReply::Success(payload)) => (self.map_fn)(&mut PacketDecode::new(payload)),
             Ok(GlobalReply::Failure) => Err(Error::GlobalReq),
             Err(_) => Err(Error::ClientClosed),
@@ -495,6 +797,7 @@ impl<IO> Future for ClientFuture<IO>
         let res = client_state::poll_client(&mut client_st, this.stream, cx);
         if let Poll::Ready(Err(ref err)) = res {
             log::debug!("client future returned error: {:#}", err);
+            client_state::fail_client(&mut client_st, err);
         }
         res
     }
@@ -524,6 +827,11 @@ pub struct ClientConfig {
     ///
     /// We will use the first algorithm that is also supported by the server. If there is no
     /// overlap, the connnection will abort.
+    ///
+    /// The algorithms for OpenSSH host certificates (such as
+    /// ["ssh-ed25519-cert-v01@openssh.com"][pubkey::SSH_ED25519_CERT_V01]) are not enabled by
+    /// default. If you enable them, the server may present a host certificate, which you can
+    /// obtain from [`AcceptPubkey::certificate()`][super::AcceptPubkey::certificate()].
     pub server_pubkey_algos: Vec<&'static PubkeyAlgo>,
 
     /// Supported [encryption algorithms][crate::cipher].
@@ -538,6 +846,16 @@ pub struct ClientConfig {
     /// overlap, the connnection will abort.
     pub mac_algos: Vec<&'static MacAlgo>,
 
+    /// Supported [compression algorithms][crate::compress].
+    ///
+    /// We will use the first algorithm that is also supported by the server. If there is no
+    /// overlap, the connnection will abort.
+    ///
+    /// By default, we only support "none" (no compression). If you want to enable compression,
+    /// you should prefer the delayed ["zlib@openssh.com"][compress::ZLIB_OPENSSH] and keep "none"
+    /// as the last option, in case that the server does not support compression.
+    pub compression_algos: Vec<&'static CompressAlgo>,
+
     /// Start key re-exchange after this many bytes.
     ///
     /// The amount of data that symmetric ciphers can securely encrypt is usually limited, so we
@@ -560,12 +878,102 @@ pub struct ClientConfig {
     /// By default, we perform the re-exchange after one hour (as recommended by the SSH
     /// specification).
     pub rekey_after_duration: Duration,
+
+    /// Require strict key exchange.
+    ///
+    /// We always offer strict key exchange (`kex-strict-c-v00@openssh.com`), which mitigates the
+    /// Terrapin attack (CVE-2023-48795) by resetting the packet sequence numbers after each key
+    /// exchange and rejecting unexpected packets during the initial key exchange. If the server
+    /// also supports strict key exchange, we enforce it.
+    ///
+    /// If this option is enabled and the server does not support strict key exchange, the
+    /// connection will abort with [`Error::StrictKexUnsupported`]. By default, this option is
+    /// disabled, so that we can connect to older servers.
+    pub require_strict_kex: bool,
+
+    /// Sizes of the group for the Diffie-Hellman group exchange.
+    ///
+    /// This is only used by the [group exchange key exchange
+    /// algorithms][kex::DIFFIE_HELLMAN_GROUP_EXCHANGE_SHA256] (RFC 4419), where the server chooses
+    /// the Diffie-Hellman group. See [`DhGexConfig`] for details.
+    pub dh_gex: DhGexConfig,
+
+    /// Interval for sending keepalive requests.
+    ///
+    /// If this is set and we don't receive any data from the server for this duration, we send a
+    /// keepalive request (see [`Client::send_keepalive()`]). If the server doesn't respond to
+    /// [`keepalive_count_max`][Self::keepalive_count_max] requests in a row, we consider the
+    /// connection dead and the [`ClientFuture`] fails with [`Error::KeepaliveTimeout`]. This
+    /// corresponds to the `ServerAliveInterval` option of the OpenSSH client.
+    ///
+    /// Keepalives are sent only after you are authenticated, and they require the Tokio runtime
+    /// with the time driver enabled. By default, this is `None`, so we don't send any keepalives.
+    pub keepalive_interval: Option<Duration>,
+
+    /// Maximum number of keepalive requests that the server may leave unanswered.
+    ///
+    /// See [`keepalive_interval`][Self::keepalive_interval] for details. This corresponds to the
+    /// `ServerAliveCountMax` option of the OpenSSH client. By default, this is 3 (the same as in
+    /// OpenSSH).
+    pub keepalive_count_max: u32,
+
+    /// Timeout for receiving the identification string from the server.
+    ///
+    /// If the server does not send its identification string (the first line of the SSH
+    /// protocol) within this duration after [`Client::open()`], the [`ClientFuture`] fails with
+    /// [`Error::IdentTimeout`]. By default, this is `None` (no timeout).
+    ///
+    /// All timeouts require the Tokio runtime with the time driver enabled.
+    pub ident_timeout: Option<Duration>,
+
+    /// Timeout for the initial key exchange.
+    ///
+    /// If the first key exchange does not finish within this duration after we receive the
+    /// identification string from the server, the [`ClientFuture`] fails with
+    /// [`Error::KexTimeout`]. Note that this includes the time that you spend deciding whether to
+    /// accept the server public key ([`ClientEvent::ServerPubkey`][super::ClientEvent::ServerPubkey]).
+    /// By default, this is `None` (no timeout).
+    pub kex_timeout: Option<Duration>,
+
+    /// Timeout for each authentication attempt.
+    ///
+    /// If the server does not respond to an authentication attempt (such as
+    /// [`Client::auth_password()`]) within this duration, the method returns
+    /// [`Error::AuthTimeout`]. The SSH protocol does not allow us to cancel the attempt, so it
+    /// stays pending and further attempts will fail with [`Error::AuthPending`] until the server
+    /// responds; in most cases, you should simply disconnect. By default, this is `None` (no
+    /// timeout).
+    pub auth_timeout: Option<Duration>,
+
+    /// Timeout for replies to global requests.
+    ///
+    /// If the server does not reply to a global request within this duration,
+    /// [`ClientResp::wait()`] returns [`Error::GlobalReqTimeout`]. This timeout also applies to
+    /// [`Client::ping()`]. By default, this is `None` (no timeout).
+    pub global_req_timeout: Option<Duration>,
+
+    /// Interval for obscuring the timing of keystrokes.
+    ///
+    /// When you type in an interactive session, the timing of the packets reveals the timing of
+    /// your keystrokes, which can be used to infer what you typed (such as a password). If this is
+    /// set, data sent to channels with a pty (see [`Session::request_pty()`]) is sent only at
+    /// fixed intervals of this duration, and we send `SSH_MSG_IGNORE` packets as chaff on the
+    /// intervals without any data, both during typing and for a random period of 1 to 3 seconds
+    /// after the last keystroke. This corresponds to the `ObscureKeystrokeTiming` option of the
+    /// OpenSSH client, which uses an interval of 20 ms by default.
+    ///
+    /// This delays all data sent to such channels by up to one interval and requires the Tokio
+    /// runtime with the time driver enabled. By default, this is `None`, so we send the data
+    /// immediately.
+    pub obscure_keystroke_timing: Option<Duration>,
 }
 
 impl Default for ClientConfig {
     fn default() -> Self {
         ClientConfig {
             kex_algos: vec![
+                &kex::MLKEM768X25519_SHA256,
+                &kex::SNTRUP761X25519_SHA512, &kex::SNTRUP761X25519_SHA512_OPENSSH,
                 &kex::CURVE25519_SHA256, &kex::CURVE25519_SHA256_LIBSSH,
             ],
             server_pubkey_algos: vec![
@@ -581,8 +989,18 @@ impl Default for ClientConfig {
                 &mac::HMAC_SHA2_256_ETM, &mac::HMAC_SHA2_512_ETM,
                 &mac::HMAC_SHA2_256, &mac::HMAC_SHA2_512,
             ],
+            compression_algos: vec![&compress::NONE],
             rekey_after_bytes: 1 << 30,
             rekey_after_duration: Duration::from_secs(60 * 60),
+            require_strict_kex: false,
+            dh_gex: DhGexConfig::default(),
+            keepalive_interval: None,
+            keepalive_count_max: 3,
+            ident_timeout: None,
+            kex_timeout: None,
+            auth_timeout: None,
+            global_req_timeout: None,
+            obscure_keystroke_timing: None,
         }
     }
 }
@@ -595,10 +1013,15 @@ impl ClientConfig {
     pub fn default_compatible_less_secure() -> ClientConfig {
         Self::default().with(|c| {
             c.kex_algos.extend_from_slice(&[
+                &kex::ECDH_SHA2_NISTP256,
+                &kex::ECDH_SHA2_NISTP384,
+                &kex::ECDH_SHA2_NISTP521,
+                &kex::DIFFIE_HELLMAN_GROUP_EXCHANGE_SHA256,
                 &kex::DIFFIE_HELLMAN_GROUP14_SHA256,
                 &kex::DIFFIE_HELLMAN_GROUP16_SHA512,
                 &kex::DIFFIE_HELLMAN_GROUP18_SHA512,
                 &kex::DIFFIE_HELLMAN_GROUP14_SHA1,
+                &kex::DIFFIE_HELLMAN_GROUP_EXCHANGE_SHA1,
             ]);
             c.server_pubkey_algos.extend_from_slice(&[
                 &pubkey::ECDSA_SHA2_NISTP256,
@@ -629,6 +1052,50 @@ impl ClientConfig {
         })
     }
 
+    /// Update [`kex_algos`][Self::kex_algos] from a list of algorithms in the OpenSSH format.
+    ///
+    /// The `spec` uses the same syntax as `KexAlgorithms` and similar options in `ssh_config`:
+    /// it is a comma-separated list of algorithm names, which may contain wildcards `*` and `?`.
+    ///
+    /// - If `spec` starts with `+`, the matching algorithms are appended to the list (for example,
+    ///   `"+diffie-hellman-group14-sha1"`).
+    /// - If `spec` starts with `-`, the matching algorithms are removed from the list (for
+    ///   example, `"-diffie-hellman-group*"`).
+    /// - If `spec` starts with `^`, the matching algorithms are moved to the front of the list
+    ///   (for example, `"^curve25519-sha256"`).
+    /// - Otherwise, the list is replaced with the matching algorithms.
+    ///
+    /// If a name without wildcards does not correspond to a supported algorithm, this method
+    /// returns [`Error::UnknownAlgo`] and leaves the configuration unchanged. Wildcards never
+    /// match the "none" algorithm.
+    pub fn apply_kex_algos(&mut self, spec: &str) -> Result<()> {
+        algo_list::apply(&mut self.kex_algos, spec, kex::algos(), |algo| algo.name)
+    }
+
+    /// Update [`server_pubkey_algos`][Self::server_pubkey_algos] from a list of algorithms in the
+    /// OpenSSH format (such as `HostKeyAlgorithms` in `ssh_config`).
+    ///
+    /// See [`apply_kex_algos()`][Self::apply_kex_algos()] for the syntax.
+    pub fn apply_server_pubkey_algos(&mut self, spec: &str) -> Result<()> {
+        algo_list::apply(&mut self.server_pubkey_algos, spec, pubkey::algos(), |algo| algo.name)
+    }
+
+    /// Update [`cipher_algos`][Self::cipher_algos] from a list of algorithms in the OpenSSH
+    /// format (such as `Ciphers` in `ssh_config`).
+    ///
+    /// See [`apply_kex_algos()`][Self::apply_kex_algos()] for the syntax.
+    pub fn apply_cipher_algos(&mut self, spec: &str) -> Result<()> {
+        algo_list::apply(&mut self.cipher_algos, spec, cipher::algos(), |algo| algo.name)
+    }
+
+    /// Update [`mac_algos`][Self::mac_algos] from a list of algorithms in the OpenSSH format
+    /// (such as `MACs` in `ssh_config`).
+    ///
+    /// See [`apply_kex_algos()`][Self::apply_kex_algos()] for the syntax.
+    pub fn apply_mac_algos(&mut self, spec: &str) -> Result<()> {
+        algo_list::apply(&mut self.mac_algos, spec, mac::algos(), |algo| algo.name)
+    }
+
     /// Update the configuration in pseudo-builder pattern style.
     ///
     /// This method applies your closure to `self` and returns the mutated configuration.
@@ -9,7 +9,7 @@ use std::task::{Context, Poll};
 use tokio::sync::{mpsc, oneshot};
 use crate::codec::PacketDecode;
 use crate::error::{Result, Error, ChannelOpenError};
-use crate::pubkey::Pubkey;
+use crate::pubkey::{Pubkey, Certificate};
 use super::channel::{Channel, ChannelConfig, ChannelReceiver};
 use super::client_state::ClientState;
 use super::conn::AcceptedChannel;
@@ -52,6 +52,15 @@ impl ClientReceiver {
                 if accept.channel_type == "forwarded-tcpip" && self.specialize_channels {
                     let accept = AcceptTunnel::decode(accept)?;
                     Poll::Ready(Ok(Some(ClientEvent::Tunnel(accept))))
+                } else if accept.channel_type == "forwarded-streamlocal@openssh.com" && self.specialize_channels {
+                    let accept = AcceptStreamlocalTunnel::decode(accept)?;
+                    Poll::Ready(Ok(Some(ClientEvent::StreamlocalTunnel(accept))))
+                } else if accept.channel_type == "x11" && self.specialize_channels {
+                    let accept = AcceptX11::decode(accept)?;
+                    Poll::Ready(Ok(Some(ClientEvent::X11(accept))))
+                } else if accept.channel_type == "auth-agent@openssh.com" && self.specialize_channels {
+                    let accept = AcceptAgentForward { accept };
+                    Poll::Ready(Ok(Some(ClientEvent::AgentForward(accept))))
                 } else {
                     Poll::Ready(Ok(Some(ClientEvent::Channel(accept))))
                 }
@@ -67,7 +76,11 @@ impl ClientReceiver {
     /// [`ClientEvent::Channel`]. However, to provide a higher level API, we treat some channels
     /// specially: when the channel type is `"forwarded-tcpip"`, you will get a
     /// [`ClientEvent::Tunnel`] event instead of [`ClientEvent::Channel`] event, so you can easily
-    /// work with the high-level [`Tunnel`] API instead of the low-level [`Channel`] API.
+    /// work with the high-level [`Tunnel`] API instead of the low-level [`Channel`] API. Similarly,
+    /// when the channel type is `"forwarded-streamlocal@openssh.com"`, you will get a
+    /// [`ClientEvent::StreamlocalTunnel`] event, when the channel type is `"x11"`, you will get a
+    /// [`ClientEvent::X11`] event, and when the channel type is `"auth-agent@openssh.com"`, you
+    /// will get a [`ClientEvent::AgentForward`] event.
     ///
     /// In most cases, the default behavior is perfectly OK and you want to keep this enabled. But
     /// if you want to use the low-level API, you can disable this behavior by calling this method
@@ -103,7 +116,9 @@ pub enum ClientEvent {
     /// The [`Pubkey`] is the public key that the server provided as part of the key exchange. We
     /// have already verified that the server owns the private key. You should use the
     /// [`AcceptPubkey`] object to either [`.accept()`][AcceptPubkey::accept()] or
-    /// [`.reject()`][AcceptPubkey::reject()] the key.
+    /// [`.reject()`][AcceptPubkey::reject()] the key. If the server presented a host certificate,
+    /// the [`Pubkey`] is the certified key and you can obtain the certificate from
+    /// [`AcceptPubkey::certificate()`].
     ServerPubkey(Pubkey, AcceptPubkey),
     
     /// Server sent us a debugging message.
@@ -127,6 +142,32 @@ pub enum ClientEvent {
     /// connection attempt and accept or reject the tunnel.
     Tunnel(AcceptTunnel),
 
+    /// Server attempts to open a tunnel from a Unix domain socket.
+    ///
+    /// You normally recieve this event when you have requested forwarding of a Unix socket using
+    /// [`Client::bind_streamlocal()`][super::Client::bind_streamlocal()] and somebody connects to
+    /// the socket on the server. You can use the [`AcceptStreamlocalTunnel`] object to inspect the
+    /// connection attempt and accept or reject the tunnel.
+    StreamlocalTunnel(AcceptStreamlocalTunnel),
+
+    /// Server attempts to open an X11 connection.
+    ///
+    /// You receive this event when you have requested X11 forwarding using
+    /// [`Session::request_x11()`][super::Session::request_x11()] and a program on the server
+    /// connects to the forwarded display. You can use the [`AcceptX11`] object to inspect the
+    /// connection attempt and accept or reject it; use [`x11::bridge()`][crate::x11::bridge()] to
+    /// forward the connection to your local X server.
+    X11(AcceptX11),
+
+    /// Server attempts to connect to your SSH agent.
+    ///
+    /// You receive this event when you have requested agent forwarding using
+    /// [`Session::request_agent_forward()`][super::Session::request_agent_forward()] and a program
+    /// on the server connects to the forwarded agent. You can use the [`AcceptAgentForward`]
+    /// object to accept or reject the connection; use [`agent::bridge()`][crate::agent::bridge()]
+    /// to forward the connection to your local agent.
+    AgentForward(AcceptAgentForward),
+
     /// Server attempts to open a channel (low level API).
     ///
     /// This is the `SSH_MSG_CHANNEL_OPEN` message described in RFC 4255, section 5.1. You can use
@@ -176,6 +217,7 @@ pub struct AuthBanner {
 #[derive(Debug)]
 pub struct AcceptPubkey {
     pub(super) accepted_tx: oneshot::Sender<Result<PubkeyAccepted>>,
+    pub(super) cert: Option<Box<Certificate>>,
 }
 
 /// A "witness" that the user has called [`AcceptPubkey::accept()`].
@@ -183,6 +225,22 @@ pub struct AcceptPubkey {
 pub(super) struct PubkeyAccepted(());
 
 impl AcceptPubkey {
+    /// The host certificate presented by the server, if any.
+    ///
+    /// If we negotiated one of the certificate algorithms (such as
+    /// ["ssh-ed25519-cert-v01@openssh.com"][crate::pubkey::SSH_ED25519_CERT_V01]), the server
+    /// presents a host certificate instead of a bare public key. The [`Pubkey`] in
+    /// [`ClientEvent::ServerPubkey`] is the certified key, and you can use this method to obtain
+    /// the whole certificate and verify it (for example, using
+    /// [`host_file::File::match_host_port_cert()`][crate::host_file::File::match_host_port_cert()]).
+    ///
+    /// Note that we do not validate the certificate (we only check that the server owns the
+    /// private key), so you should not accept the key just because the server presented a
+    /// certificate.
+    pub fn certificate(&self) -> Option<&Certificate> {
+        self.cert.as_deref()
+    }
+
     /// Accept the server public key.
     ///
     /// You assert that this public key really belongs to the server that you want to connect to.
@@ -335,3 +393,146 @@ impl AcceptTunnel {
         self.accept.reject_prohibited();
     }
 }
+
+
+
+/// Tell us whether to accept a tunnel from a Unix domain socket opened by the server.
+///
+/// You receive this object in [`ClientEvent::StreamlocalTunnel`] and use it to accept or reject
+/// a tunnel that the server wants to open.
+///
+/// Dropping this object is the same as calling [`reject_prohibited()`][Self::reject_prohibited].
+#[derive(Debug)]
+pub struct AcceptStreamlocalTunnel {
+    accept: AcceptChannel,
+
+    /// The path of the Unix socket on the SSH server that the remote peer has connected to.
+    ///
+    /// This should be equal to the path that you have passed to
+    /// [`Client::bind_streamlocal()`][super::Client::bind_streamlocal()].
+    pub socket_path: String,
+}
+
+impl AcceptStreamlocalTunnel {
+    fn decode(accept: AcceptChannel) -> Result<AcceptStreamlocalTunnel> {
+        let mut payload = PacketDecode::new(accept.open_payload.clone());
+        let socket_path = payload.get_string()?;
+        Ok(AcceptStreamlocalTunnel { accept, socket_path })
+    }
+
+    /// Accept the opened tunnel.
+    ///
+    /// This works in the same way as [`AcceptTunnel::accept()`].
+    pub async fn accept(self, config: ChannelConfig) -> Result<(Tunnel, TunnelReceiver)> {
+        let (channel, channel_rx) = self.accept.accept(config, Bytes::new()).await?;
+        Tunnel::accept(channel, channel_rx)
+    }
+
+    /// Reject the tunnel.
+    ///
+    /// The `error` specifies the reasons for the rejection that will be sent to the server.
+    pub fn reject(self, error: ChannelOpenError) {
+        self.accept.reject(error);
+    }
+
+    /// Reject the tunnel with reasonable default error.
+    ///
+    /// This is the same as calling [`reject()`][Self::reject()] with reason code
+    /// `ADMINISTRATIVELY_PROHIBITED`.
+    pub fn reject_prohibited(self) {
+        self.accept.reject_prohibited();
+    }
+}
+
+
+
+/// Tell us whether to accept an X11 connection opened by the server.
+///
+/// You receive this object in [`ClientEvent::X11`] and use it to accept or reject the `x11`
+/// channel that the server wants to open.
+///
+/// Dropping this object is the same as calling [`reject_prohibited()`][Self::reject_prohibited].
+#[derive(Debug)]
+pub struct AcceptX11 {
+    accept: AcceptChannel,
+
+    /// The address of the X11 client on the server that has connected to the forwarded display.
+    pub originator_addr: (String, u16),
+}
+
+impl AcceptX11 {
+    fn decode(accept: AcceptChannel) -> Result<AcceptX11> {
+        let mut payload = PacketDecode::new(accept.open_payload.clone());
+        let originator_host = payload.get_string()?;
+        let originator_port = payload.get_u32()? as u16;
+        let originator_addr = (originator_host, originator_port);
+        Ok(AcceptX11 { accept, originator_addr })
+    }
+
+    /// Accept the X11 connection.
+    ///
+    /// The returned [`Tunnel`] and [`TunnelReceiver`] carry the raw X11 protocol. Note that the
+    /// X11 client on the server authenticates using the cookie that you sent in
+    /// [`Session::request_x11()`][super::Session::request_x11()]; if you used a fake cookie, you
+    /// probably want to use [`x11::bridge()`][crate::x11::bridge()] instead of this method, which
+    /// replaces the fake cookie with the real one.
+    pub async fn accept(self, config: ChannelConfig) -> Result<(Tunnel, TunnelReceiver)> {
+        let (channel, channel_rx) = self.accept.accept(config, Bytes::new()).await?;
+        Tunnel::accept(channel, channel_rx)
+    }
+
+    /// Reject the X11 connection.
+    ///
+    /// The `error` specifies the reasons for the rejection that will be sent to the server.
+    pub fn reject(self, error: ChannelOpenError) {
+        self.accept.reject(error);
+    }
+
+    /// Reject the X11 connection with reasonable default error.
+    ///
+    /// This is the same as calling [`reject()`][Self::reject()] with reason code
+    /// `ADMINISTRATIVELY_PROHIBITED`.
+    pub fn reject_prohibited(self) {
+        self.accept.reject_prohibited();
+    }
+}
+
+
+
+/// Tell us whether to accept a connection to the forwarded SSH agent.
+///
+/// You receive this object in [`ClientEvent::AgentForward`] and use it to accept or reject the
+/// `auth-agent@openssh.com` channel that the server wants to open.
+///
+/// Dropping this object is the same as calling [`reject_prohibited()`][Self::reject_prohibited].
+#[derive(Debug)]
+pub struct AcceptAgentForward {
+    accept: AcceptChannel,
+}
+
+impl AcceptAgentForward {
+    /// Accept the connection.
+    ///
+    /// The returned [`Tunnel`] and [`TunnelReceiver`] carry the raw messages of the SSH agent
+    /// protocol. In most cases, you will want to use [`agent::bridge()`][crate::agent::bridge()]
+    /// instead of this method, which accepts the connection and forwards it to your local agent.
+    pub async fn accept(self, config: ChannelConfig) -> Result<(Tunnel, TunnelReceiver)> {
+        let (channel, channel_rx) = self.accept.accept(config, Bytes::new()).await?;
+        Tunnel::accept(channel, channel_rx)
+    }
+
+    /// Reject the connection.
+    ///
+    /// The `error` specifies the reasons for the rejection that will be sent to the server.
+    pub fn reject(self, error: ChannelOpenError) {
+        self.accept.reject(error);
+    }
+
+    /// Reject the connection with reasonable default error.
+    ///
+    /// This is the same as calling [`reject()`][Self::reject()] with reason code
+    /// `ADMINISTRATIVELY_PROHIBITED`.
+    pub fn reject_prohibited(self) {
+        self.accept.reject_prohibited();
+    }
+}
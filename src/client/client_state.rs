@@ -1,5 +1,8 @@
 use bytes::Bytes;
+use parking_lot::Mutex;
+use std::fmt::Write as _;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll, Waker};
 use tokio::sync::mpsc;
 use tokio_util::sync::PollSender;
@@ -8,13 +11,17 @@ use crate::codes::msg;
 use crate::error::{Error, Result, DisconnectError};
 use crate::util::{self, AsyncReadWrite, CryptoRngCore};
 use super::auth::{self, AuthState};
+use super::chaff::{self, ChaffState};
 use super::client::ClientConfig;
 use super::client_event::ClientEvent;
 use super::conn::{self, ConnState};
 use super::ext::TheirExtInfo;
-use super::negotiate::{self, NegotiateState, LastKex};
+use super::keepalive::{self, KeepaliveState};
+use super::negotiate::{self, NegotiateState, NegotiatedAlgos, LastKex};
+use super::ping::{self, PingState};
 use super::pump::Pump;
 use super::recv::{self, RecvState};
+use super::timeout::{self, TimeoutState};
 
 pub(super) struct ClientState {
     pub config: ClientConfig,
@@ -24,6 +31,10 @@ pub(super) struct ClientState {
     pub negotiate_st: Box<NegotiateState>,
     pub auth_st: Box<AuthState>,
     pub conn_st: Box<ConnState>,
+    pub keepalive_st: KeepaliveState,
+    pub ping_st: PingState,
+    pub chaff_st: ChaffState,
+    pub timeout_st: TimeoutState,
     pub rng: Box<dyn CryptoRngCore + Send>,
 
     pub event_tx: PollSender<ClientEvent>,
@@ -31,11 +42,16 @@ pub(super) struct ClientState {
 
     pub our_ident: Bytes,
     pub their_ident: Option<Bytes>,
+    pub their_prefix_lines: Vec<Bytes>,
     our_disconnect: Option<DisconnectError>,
     disconnect_sent: bool,
     pub session_id: Option<Vec<u8>>,
     pub last_kex: LastKex,
+    pub negotiated_algos: Option<NegotiatedAlgos>,
+    pub kex_count: u64,
+    pub strict_kex: bool,
     pub their_ext_info: TheirExtInfo,
+    pub failure: Arc<Mutex<Option<String>>>,
 }
 
 pub(super) fn new_client(
@@ -58,16 +74,25 @@ pub(super) fn new_client(
         negotiate_st: Box::new(negotiate::init_negotiate()),
         auth_st: Box::new(auth::init_auth()),
         conn_st: Box::new(conn::init_conn()),
+        keepalive_st: keepalive::init_keepalive(),
+        ping_st: ping::init_ping(),
+        chaff_st: chaff::init_chaff(),
+        timeout_st: timeout::init_timeout(),
         rng,
         event_tx: PollSender::new(event_tx),
         waker: None,
         our_ident,
         their_ident: None,
+        their_prefix_lines: Vec::new(),
         our_disconnect: None,
         disconnect_sent: false,
         session_id: None,
         last_kex: negotiate::init_last_kex(),
+        negotiated_algos: None,
+        kex_count: 0,
+        strict_kex: false,
         their_ext_info: TheirExtInfo::default(),
+        failure: Arc::new(Mutex::new(None)),
     })
 }
 
@@ -78,7 +103,7 @@ pub(super) fn poll_client(
 ) -> Poll<Result<()>> {
     if st.our_disconnect.is_some() && !st.disconnect_sent {
         let error = st.our_disconnect.take().unwrap();
-        send_disconnect(st, error);
+        send_disconnect(st, error)?;
         st.disconnect_sent = true;
     }
 
@@ -90,6 +115,10 @@ pub(super) fn poll_client(
             while negotiate::pump_negotiate(st, cx)?.is_progress() { progress = true }
             while auth::pump_auth(st, cx)?.is_progress() { progress = true }
             while conn::pump_conn(st, cx)?.is_progress() { progress = true }
+            while keepalive::pump_keepalive(st, cx)?.is_progress() { progress = true }
+            while ping::pump_ping(st, cx)?.is_progress() { progress = true }
+            while chaff::pump_chaff(st, cx)?.is_progress() { progress = true }
+            while timeout::pump_timeout(st, cx)?.is_progress() { progress = true }
 
             if pump_read(st, stream.as_mut(), cx)?.is_progress() { continue }
         }
@@ -108,6 +137,20 @@ pub(super) fn poll_client(
     Poll::Pending
 }
 
+// records the error that stopped the client, so that nested connections can report it (see
+// `JumpStream`), and drops all channels, so that their receivers observe the end of the channel
+// instead of waiting forever
+pub(super) fn fail_client(st: &mut ClientState, err: &Error) {
+    let mut msg = err.to_string();
+    let mut source = std::error::Error::source(err);
+    while let Some(err) = source {
+        let _: std::fmt::Result = write!(msg, ": {}", err);
+        source = err.source();
+    }
+    *st.failure.lock() = Some(msg);
+    conn::drop_channels(st);
+}
+
 pub(super) fn wakeup_client(st: &mut ClientState) {
     if let Some(waker) = st.waker.take() {
         waker.wake();
@@ -152,6 +195,7 @@ fn pump_read_ident(
     let ident = Bytes::copy_from_slice(&ident);
     log::debug!("received peer identifier: {:?}", ident);
     st.their_ident = Some(ident);
+    st.their_prefix_lines = st.codec.recv_pipe.take_prefix_lines();
 
     Ok(Pump::Progress)
 }
@@ -238,14 +282,15 @@ pub(super) fn disconnect(st: &mut ClientState, error: DisconnectError) -> Result
     }
 }
 
-fn send_disconnect(st: &mut ClientState, error: DisconnectError) {
+fn send_disconnect(st: &mut ClientState, error: DisconnectError) -> Result<()> {
     let mut payload = PacketEncode::new();
     payload.put_u8(msg::DISCONNECT);
     payload.put_u32(error.reason_code);
     payload.put_str(&error.description);
     payload.put_str(&error.description_lang);
-    st.codec.send_pipe.feed_packet(&payload.finish());
+    st.codec.send_pipe.feed_packet(&payload.finish())?;
     log::debug!("sending SSH_MSG_DISCONNECT with reason code {}", error.reason_code);
+    Ok(())
 }
 
 fn sanitize_config(config: &mut ClientConfig) {
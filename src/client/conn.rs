@@ -92,6 +92,11 @@ pub(super) fn init_conn() -> ConnState {
     ConnState::default()
 }
 
+pub(super) fn drop_channels(st: &mut ClientState) {
+    st.conn_st.open_channels.clear();
+    st.conn_st.channels.lock().clear();
+}
+
 pub(super) fn pump_conn(st: &mut ClientState, cx: &mut Context) -> Result<Pump> {
     if !auth::is_authenticated(st) {
         return Ok(Pump::Pending)
@@ -99,7 +104,7 @@ pub(super) fn pump_conn(st: &mut ClientState, cx: &mut Context) -> Result<Pump>
 
     if negotiate::is_ready(st) {
         if let Some(req) = st.conn_st.send_reqs.pop_front() {
-            let packet_seq = send_global_request(st, &req);
+            let packet_seq = send_global_request(st, &req)?;
             if let Some(reply_tx) = req.reply_tx {
                 st.conn_st.recv_replies.push_back(RecvReply { reply_tx, packet_seq });
             }
@@ -110,7 +115,7 @@ pub(super) fn pump_conn(st: &mut ClientState, cx: &mut Context) -> Result<Pump>
     pump_channels(st, cx)
 }
 
-fn pump_channels(st: &mut ClientState, cx: &mut Context) -> Result<Pump> {
+pub(super) fn pump_channels(st: &mut ClientState, cx: &mut Context) -> Result<Pump> {
     let channels = st.conn_st.channels.clone();
     let mut channels = channels.lock();
     let mut progress = Pump::Pending;
@@ -146,7 +151,7 @@ fn pump_channel(
     match replace(conn_channel_st, ConnChannelState::Closed) {
         ConnChannelState::Open(mut open_st) => {
             if !open_st.open_sent && negotiate::is_ready(st) {
-                send_channel_open(st, &open_st);
+                send_channel_open(st, &open_st)?;
                 open_st.open_sent = true;
                 progress = Pump::Progress;
             }
@@ -155,15 +160,15 @@ fn pump_channel(
         ConnChannelState::Accept(mut accept_st) => {
             match Pin::new(&mut accept_st.accepted_rx).poll(cx) {
                 Poll::Ready(Ok(Ok(accepted))) => {
-                    send_channel_open_confirmation(st, &accept_st, &accepted);
+                    send_channel_open_confirmation(st, &accept_st, &accepted)?;
                     *conn_channel_st = init_accepted_channel(accept_st, accepted);
                     progress = Pump::Progress;
                 },
                 Poll::Ready(Ok(Err(open_err))) => {
-                    send_channel_open_failure(st, &accept_st, Some(open_err));
+                    send_channel_open_failure(st, &accept_st, Some(open_err))?;
                 },
                 Poll::Ready(Err(_)) => {
-                    send_channel_open_failure(st, &accept_st, None);
+                    send_channel_open_failure(st, &accept_st, None)?;
                 },
                 Poll::Pending => {
                     *conn_channel_st = ConnChannelState::Accept(accept_st);
@@ -221,7 +226,7 @@ fn alloc_our_id(channels: &HashMap<u32, ConnChannelState>) -> u32 {
     panic!("no free channel ids")
 }
 
-fn send_channel_open(st: &mut ClientState, open_st: &OpenChannelState) {
+fn send_channel_open(st: &mut ClientState, open_st: &OpenChannelState) -> Result<()> {
     let mut payload = PacketEncode::new();
     payload.put_u8(msg::CHANNEL_OPEN);
     payload.put_str(&open_st.open.channel_type);
@@ -229,9 +234,10 @@ fn send_channel_open(st: &mut ClientState, open_st: &OpenChannelState) {
     payload.put_u32(open_st.open.recv_window_max as u32);
     payload.put_u32(open_st.open.recv_packet_len_max as u32);
     payload.put_raw(&open_st.open.open_payload);
-    st.codec.send_pipe.feed_packet(&payload.finish());
+    st.codec.send_pipe.feed_packet(&payload.finish())?;
     log::debug!("sending SSH_MSG_CHANNEL_OPEN {:?} for our channel {}",
         open_st.open.channel_type, open_st.our_id);
+    Ok(())
 }
 
 fn recv_channel_open_confirmation(
@@ -288,7 +294,10 @@ fn init_confirmed_channel(
         event_rx,
         confirm_payload: confirm.confirm_payload,
     };
-    let _: Result<_, _> = open_st.open.result_tx.send(Ok(result));
+    if open_st.open.result_tx.send(Ok(result)).is_err() {
+        log::debug!("nobody is waiting for our channel {} anymore, closing it", open_st.our_id);
+        channel_state::close_unused(&mut channel_st.lock());
+    }
 
     ConnChannelState::Ready(channel_st)
 }
@@ -477,7 +486,7 @@ fn send_channel_open_confirmation(
     st: &mut ClientState,
     accept_st: &AcceptChannelState,
     accepted: &AcceptedChannel,
-) {
+) -> Result<()> {
     let mut payload = PacketEncode::new();
     payload.put_u8(msg::CHANNEL_OPEN_CONFIRMATION);
     payload.put_u32(accept_st.their_id);
@@ -485,15 +494,16 @@ fn send_channel_open_confirmation(
     payload.put_u32(accepted.recv_window_max as u32);
     payload.put_u32(accepted.recv_packet_len_max as u32);
     payload.put_raw(&accepted.confirm_payload);
-    st.codec.send_pipe.feed_packet(&payload.finish());
+    st.codec.send_pipe.feed_packet(&payload.finish())?;
     log::debug!("sending SSH_MSG_CHANNEL_OPEN_CONFIRMATION for our channel {}", accept_st.our_id);
+    Ok(())
 }
 
 fn send_channel_open_failure(
     st: &mut ClientState,
     accept_st: &AcceptChannelState,
     open_err: Option<ChannelOpenError>,
-) {
+) -> Result<()> {
     let open_err = open_err.unwrap_or_else(|| {
         ChannelOpenError {
             reason_code: open::ADMINISTRATIVELY_PROHIBITED,
@@ -508,9 +518,10 @@ fn send_channel_open_failure(
     payload.put_u32(open_err.reason_code);
     payload.put_str(&open_err.description);
     payload.put_str(&open_err.description_lang);
-    st.codec.send_pipe.feed_packet(&payload.finish());
+    st.codec.send_pipe.feed_packet(&payload.finish())?;
     log::debug!("sending SSH_MSG_CHANNEL_OPEN_FAILURE for our channel {}, reason: {}",
         accept_st.our_id, open::to_str(open_err.reason_code).unwrap_or("unknown"));
+    Ok(())
 }
 
 
@@ -521,15 +532,15 @@ pub(super) fn send_request(st: &mut ClientState, req: GlobalReq) -> Result<()> {
     Ok(())
 }
 
-fn send_global_request(st: &mut ClientState, req: &GlobalReq) -> u32 {
+fn send_global_request(st: &mut ClientState, req: &GlobalReq) -> Result<u32> {
     let mut payload = PacketEncode::new();
     payload.put_u8(msg::GLOBAL_REQUEST);
     payload.put_str(&req.request_type);
     payload.put_bool(req.reply_tx.is_some());
     payload.put_raw(&req.payload);
-    let packet_seq = st.codec.send_pipe.feed_packet(&payload.finish());
+    let packet_seq = st.codec.send_pipe.feed_packet(&payload.finish())?;
     log::debug!("sending SSH_MSG_GLOBAL_REQUEST {:?}", req.request_type);
-    packet_seq
+    Ok(packet_seq)
 }
 
 fn recv_request_success(st: &mut ClientState, payload: &mut PacketDecode) -> ResultRecvState {
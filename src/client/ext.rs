@@ -1,20 +1,23 @@
 use crate::codec::{PacketEncode, PacketDecode};
 use crate::codes::msg;
+use crate::error::Result;
 use super::client_state::ClientState;
 use super::recv::ResultRecvState;
 
 #[derive(Debug, Default)]
 pub(super) struct TheirExtInfo {
     pub auth_pubkey_algo_names: Option<Vec<String>>,
+    pub ping: bool,
 }
 
-pub(super) fn send_ext_info(st: &mut ClientState) {
+pub(super) fn send_ext_info(st: &mut ClientState) -> Result<()> {
     // no extensions at the moment!
     let mut payload = PacketEncode::new();
     payload.put_u8(msg::EXT_INFO);
     payload.put_u32(0);
-    st.codec.send_pipe.feed_packet(&payload.finish());
+    st.codec.send_pipe.feed_packet(&payload.finish())?;
     log::debug!("sending SSH_MSG_EXT_INFO");
+    Ok(())
 }
 
 pub(super) fn recv_ext_info(st: &mut ClientState, payload: &mut PacketDecode) -> ResultRecvState {
@@ -26,6 +29,8 @@ pub(super) fn recv_ext_info(st: &mut ClientState, payload: &mut PacketDecode) ->
         let ext_name = payload.get_string()?;
         if ext_name == "server-sig-algs" {
             ext_info.auth_pubkey_algo_names = Some(payload.get_name_list()?);
+        } else if ext_name == "ping@openssh.com" {
+            ext_info.ping = payload.get_string()? == "0";
         } else {
             payload.get_bytes()?;
         }
@@ -0,0 +1,123 @@
+use futures_core::ready;
+use parking_lot::Mutex;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncBufRead, AsyncWrite};
+use crate::error::Error;
+use super::tunnel::{Tunnel, TunnelReceiver, TunnelStream};
+
+/// I/O stream of an SSH connection that is tunneled through another SSH connection ("ProxyJump").
+///
+/// You obtain this stream from [`Client::connect_jump()`][super::Client::connect_jump()], which
+/// opens a `direct-tcpip` tunnel on the jump host and passes this stream to
+/// [`Client::open()`][super::Client::open()]. It is a [`TunnelStream`] with two additions that
+/// tie the lifetime of the nested connection to the tunnel:
+///
+/// - When the stream is dropped (usually together with the [`ClientFuture`][super::ClientFuture]
+///   of the nested connection), the tunnel is closed, so that the channel does not leak on the jump
+///   host connection.
+/// - When the jump host connection is closed while the nested connection is still reading from
+///   the tunnel, the read fails with [`Error::JumpClosed`] instead of returning an ordinary end of
+///   file, so that the nested [`ClientFuture`][super::ClientFuture] fails with an error. If the
+///   jump host connection failed with an error, the read fails with [`Error::JumpFailed`], which
+///   describes the cause.
+///
+/// Because [`Client::connect_jump()`][super::Client::connect_jump()] also works on clients that
+/// are themselves connected through a jump host, you can build chains of arbitrary depth, and all
+/// clients in the chain use the same [`JumpStream`] type.
+pub struct JumpStream {
+    tunnel: Tunnel,
+    stream: TunnelStream,
+    client_failure: Arc<Mutex<Option<String>>>,
+}
+
+impl JumpStream {
+    pub(super) fn new(
+        tunnel: Tunnel,
+        tunnel_rx: TunnelReceiver,
+        client_failure: Arc<Mutex<Option<String>>>,
+    ) -> Self {
+        let stream = TunnelStream::new(tunnel.clone(), tunnel_rx);
+        Self { tunnel, stream, client_failure }
+    }
+
+    /// Get the [`Tunnel`] to the jump host that carries this stream.
+    pub fn tunnel(&self) -> &Tunnel {
+        &self.tunnel
+    }
+
+    fn check_eof(&self) -> std::io::Result<()> {
+        if let Some(msg) = self.client_failure.lock().clone() {
+            Err(Error::JumpFailed(msg).into())
+        } else if self.tunnel.is_client_closed() {
+            Err(Error::JumpClosed.into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for JumpStream {
+    fn drop(&mut self) {
+        let _: Result<_, _> = self.tunnel.close();
+    }
+}
+
+impl AsyncRead for JumpStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let filled_len = buf.filled().len();
+        ready!(Pin::new(&mut this.stream).poll_read(cx, buf))?;
+        if buf.filled().len() == filled_len && buf.remaining() > 0 {
+            this.check_eof()?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncBufRead for JumpStream {
+    fn poll_fill_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::io::Result<&[u8]>> {
+        let this = self.get_mut();
+        let is_eof = ready!(Pin::new(&mut this.stream).poll_fill_buf(cx))?.is_empty();
+        if is_eof {
+            this.check_eof()?;
+        }
+        Pin::new(&mut this.stream).poll_fill_buf(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        Pin::new(&mut self.get_mut().stream).consume(amt)
+    }
+}
+
+impl AsyncWrite for JumpStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+    }
+}
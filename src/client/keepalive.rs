@@ -0,0 +1,85 @@
+use bytes::Bytes;
+use std::future::Future as _;
+use std::pin::Pin;
+use std::task::Context;
+use tokio::sync::oneshot;
+use tokio::time::{Instant, Sleep};
+use crate::error::{Error, Result};
+use super::auth;
+use super::client::GlobalReq;
+use super::client_state::ClientState;
+use super::conn;
+use super::pump::Pump;
+
+#[derive(Default)]
+pub(super) struct KeepaliveState {
+    sleep: Option<Pin<Box<Sleep>>>,
+    recvd_bytes: u64,
+    unanswered: u32,
+}
+
+pub(super) fn init_keepalive() -> KeepaliveState {
+    KeepaliveState::default()
+}
+
+pub(super) fn pump_keepalive(st: &mut ClientState, cx: &mut Context) -> Result<Pump> {
+    let Some(interval) = st.config.keepalive_interval else { return Ok(Pump::Pending) };
+    if !auth::is_authenticated(st) {
+        return Ok(Pump::Pending)
+    }
+
+    // any packet from the server proves that the server is alive; also, while we are not reading
+    // packets (because the previous packet is blocked on a full channel), we cannot expect to
+    // receive the replies, so we postpone the keepalives
+    let recvd_bytes = st.codec.recv_pipe.recvd_bytes();
+    let is_recv_blocked = st.recv_st.is_some();
+    let keepalive_st = &mut st.keepalive_st;
+    let sleep = keepalive_st.sleep.get_or_insert_with(|| Box::pin(tokio::time::sleep(interval)));
+    if recvd_bytes != keepalive_st.recvd_bytes || is_recv_blocked {
+        keepalive_st.recvd_bytes = recvd_bytes;
+        keepalive_st.unanswered = 0;
+        sleep.as_mut().reset(Instant::now() + interval);
+    }
+
+    pump_ready!(sleep.as_mut().poll(cx));
+    if keepalive_st.unanswered >= st.config.keepalive_count_max {
+        log::debug!("server did not respond to {} keepalive requests", keepalive_st.unanswered);
+        return Err(Error::KeepaliveTimeout)
+    }
+    keepalive_st.unanswered += 1;
+    sleep.as_mut().reset(Instant::now() + interval);
+
+    // we need to ask for a reply, otherwise the server would not respond, but we don't care about
+    // the reply itself
+    let (reply_tx, _reply_rx) = oneshot::channel();
+    let req = GlobalReq {
+        request_type: "keepalive@openssh.com".to_owned(),
+        payload: Bytes::new(),
+        reply_tx: Some(reply_tx),
+    };
+    conn::send_request(st, req)?;
+    Ok(Pump::Progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::client::{Client, ClientConfig};
+    use crate::error::Error;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_keepalive_timeout() {
+        let (stream, _server) = tokio::io::duplex(1024);
+        let config = ClientConfig::default().with(|c| {
+            c.keepalive_interval = Some(Duration::from_millis(20));
+            c.keepalive_count_max = 2;
+        });
+        let (client, _client_rx, client_fut) = Client::open(stream, config).unwrap();
+
+        // the server never answers, so we pretend that we are already authenticated to start the
+        // keepalives
+        auth::set_authenticated(&mut client.client_st.upgrade().unwrap().lock());
+        assert!(matches!(client_fut.await, Err(Error::KeepaliveTimeout)));
+    }
+}
@@ -1,4 +1,7 @@
 pub use self::auth::AuthFailure; 
+pub use self::auth_method::keyboard_interactive::{
+    AuthKeyboardInteractiveResult, AuthInfoRequest, AuthInfoPrompt,
+};
 pub use self::auth_method::none::AuthNoneResult;
 pub use self::auth_method::password::{AuthPasswordResult, AuthPasswordPrompt};
 pub use self::auth_method::pubkey::AuthPubkeyResult;
@@ -7,18 +10,23 @@ pub use self::channel::{
     DataType, DATA_STANDARD, DATA_STDERR,
 };
 pub use self::client::{Client, ClientResp, ClientFuture, ClientConfig, GlobalReq, GlobalReply};
+pub use self::jump::JumpStream;
+pub use self::negotiate::NegotiatedAlgos;
 pub use self::client_event::{
     ClientReceiver, ClientEvent, AcceptPubkey, DebugMsg, AuthBanner, AcceptTunnel, AcceptChannel,
+    AcceptStreamlocalTunnel, AcceptX11, AcceptAgentForward,
 };
 pub use self::session::{
     Session, SessionReceiver, SessionEvent, SessionResp, ExitSignal,
-    PtyRequest, PtyTerminalModes, WindowChange,
+    PtyRequest, PtyTerminalModes, WindowChange, X11Request,
 };
 pub use self::tunnel::{Tunnel, TunnelReceiver, TunnelEvent, TunnelReader, TunnelWriter, TunnelStream};
 
 #[macro_use] mod pump;
+mod algo_list;
 mod auth;
 mod auth_method;
+mod chaff;
 mod channel;
 mod channel_state;
 mod client;
@@ -26,7 +34,11 @@ mod client_event;
 mod client_state;
 mod conn;
 mod ext;
+mod jump;
+mod keepalive;
 mod negotiate;
+mod ping;
 mod recv;
 mod session;
+mod timeout;
 mod tunnel;
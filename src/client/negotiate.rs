@@ -8,11 +8,12 @@ use std::time::Instant;
 use tokio::sync::oneshot;
 use crate::error::{Error, Result, AlgoNegotiateError};
 use crate::cipher::{CipherAlgo, CipherAlgoVariant, PacketEncrypt, PacketDecrypt};
-use crate::codec::{PacketEncode, PacketDecode};
+use crate::codec::{PacketEncode, PacketDecode, RecvPacket};
+use crate::compress::CompressAlgo;
 use crate::codes::msg;
 use crate::kex::{Kex, KexAlgo, KexInput, KexOutput};
 use crate::mac::{self, MacAlgo, MacAlgoVariant};
-use crate::pubkey::{PubkeyAlgo, Pubkey, SignatureVerified};
+use crate::pubkey::{self, PubkeyAlgo, SignatureVerified};
 use super::{auth, ext};
 use super::client_event::{ClientEvent, AcceptPubkey, PubkeyAccepted};
 use super::client_state::{self, ClientState};
@@ -33,6 +34,7 @@ pub(super) struct NegotiateState {
     pubkey_accepted: Option<PubkeyAccepted>,
     new_keys_sent: bool,
     new_keys_recvd: bool,
+    kex_init_not_first: bool,
     done_txs: Vec<oneshot::Sender<Result<()>>>,
 }
 
@@ -55,6 +57,8 @@ struct OurKexInit {
     cipher_algos_stc: Vec<&'static CipherAlgo>,
     mac_algos_cts: Vec<&'static MacAlgo>,
     mac_algos_stc: Vec<&'static MacAlgo>,
+    compress_algos_cts: Vec<&'static CompressAlgo>,
+    compress_algos_stc: Vec<&'static CompressAlgo>,
     packet_seq: u32,
 }
 
@@ -67,6 +71,8 @@ struct TheirKexInit {
     cipher_algos_stc: Vec<String>,
     mac_algos_cts: Vec<String>,
     mac_algos_stc: Vec<String>,
+    compress_algos_cts: Vec<String>,
+    compress_algos_stc: Vec<String>,
 }
 
 struct Algos {
@@ -76,6 +82,35 @@ struct Algos {
     cipher_stc: &'static CipherAlgo,
     mac_cts: &'static MacAlgo,
     mac_stc: &'static MacAlgo,
+    compress_cts: &'static CompressAlgo,
+    compress_stc: &'static CompressAlgo,
+}
+
+/// Algorithms negotiated in a key exchange.
+///
+/// You can obtain the algorithms that are currently in use from
+/// [`Client::negotiated_algos()`][super::Client::negotiated_algos()].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct NegotiatedAlgos {
+    /// Key exchange algorithm.
+    pub kex: &'static KexAlgo,
+    /// Algorithm of the server host key.
+    pub server_pubkey: &'static PubkeyAlgo,
+    /// Cipher for packets that we send to the server.
+    pub cipher_cts: &'static CipherAlgo,
+    /// Cipher for packets that we receive from the server.
+    pub cipher_stc: &'static CipherAlgo,
+    /// MAC for packets that we send to the server (`None` if [`cipher_cts`][Self::cipher_cts]
+    /// is an AEAD cipher, which does not use a separate MAC).
+    pub mac_cts: Option<&'static MacAlgo>,
+    /// MAC for packets that we receive from the server (`None` if
+    /// [`cipher_stc`][Self::cipher_stc] is an AEAD cipher).
+    pub mac_stc: Option<&'static MacAlgo>,
+    /// Compression for packets that we send to the server.
+    pub compress_cts: &'static CompressAlgo,
+    /// Compression for packets that we receive from the server.
+    pub compress_stc: &'static CompressAlgo,
 }
 
 #[derive(Debug)]
@@ -116,20 +151,20 @@ pub(super) fn pump_negotiate(st: &mut ClientState, cx: &mut Context) -> Result<P
         },
         State::KexInit => {
             if st.negotiate_st.our_kex_init.is_none() {
-                st.negotiate_st.our_kex_init = Some(send_kex_init(st));
+                st.negotiate_st.our_kex_init = Some(send_kex_init(st)?);
             }
 
             if st.negotiate_st.our_kex_init.is_some() && st.negotiate_st.their_kex_init.is_some() {
                 st.negotiate_st.algos = Some(negotiate_algos(st)?);
                 let kex_algo = st.negotiate_st.algos.as_ref().unwrap().kex;
-                st.negotiate_st.kex = Some((kex_algo.make_kex)(&mut *st.rng)?);
+                st.negotiate_st.kex = Some((kex_algo.make_kex)(&mut *st.rng, &st.config.dh_gex)?);
                 st.negotiate_st.state = State::Kex;
                 return Ok(Pump::Progress)
             }
         },
         State::Kex => {
             if let Some(payload) = st.negotiate_st.kex.as_mut().unwrap().send_packet()? {
-                st.codec.send_pipe.feed_packet(&payload);
+                st.codec.send_pipe.feed_packet(&payload)?;
                 return Ok(Pump::Progress)
             }
 
@@ -147,8 +182,11 @@ pub(super) fn pump_negotiate(st: &mut ClientState, cx: &mut Context) -> Result<P
             }
 
             let pubkey_algo = st.negotiate_st.algos.as_ref().unwrap().server_pubkey;
-            let pubkey = Pubkey::decode(kex_output.server_pubkey.clone())?;
+            let (pubkey, cert) = pubkey::decode_host_key(pubkey_algo, kex_output.server_pubkey.clone())?;
             log::debug!("server pubkey {}", pubkey);
+            if let Some(cert) = cert.as_ref() {
+                log::debug!("server certificate {:?} signed by {}", cert.key_id, cert.signature_key);
+            }
 
             let signature_verified = (pubkey_algo.verify)(
                 &pubkey, &kex_output.exchange_hash, kex_output.server_exchange_hash_sign.clone())?;
@@ -156,7 +194,7 @@ pub(super) fn pump_negotiate(st: &mut ClientState, cx: &mut Context) -> Result<P
             st.negotiate_st.kex_output = Some(kex_output);
 
             let (accepted_tx, accepted_rx) = oneshot::channel();
-            let accept_tx = AcceptPubkey { accepted_tx };
+            let accept_tx = AcceptPubkey { accepted_tx, cert: cert.map(Box::new) };
             st.negotiate_st.pubkey_event = Some(ClientEvent::ServerPubkey(pubkey, accept_tx));
             st.negotiate_st.accepted_rx = Some(accepted_rx);
             st.negotiate_st.state = State::AcceptPubkey;
@@ -183,7 +221,7 @@ pub(super) fn pump_negotiate(st: &mut ClientState, cx: &mut Context) -> Result<P
             assert!(st.negotiate_st.pubkey_accepted.is_some());
 
             if !st.negotiate_st.new_keys_sent {
-                send_new_keys(st);
+                send_new_keys(st)?;
                 st.negotiate_st.new_keys_sent = true;
                 maybe_send_ext_info(st)?;
                 return Ok(Pump::Progress)
@@ -195,6 +233,20 @@ pub(super) fn pump_negotiate(st: &mut ClientState, cx: &mut Context) -> Result<P
             }
         },
         State::Done => {
+            let algos = st.negotiate_st.algos.as_ref().unwrap();
+            let mac_algo = |cipher: &CipherAlgo, mac| if cipher.variant.is_aead() { None } else { Some(mac) };
+            st.negotiated_algos = Some(NegotiatedAlgos {
+                kex: algos.kex,
+                server_pubkey: algos.server_pubkey,
+                cipher_cts: algos.cipher_cts,
+                cipher_stc: algos.cipher_stc,
+                mac_cts: mac_algo(algos.cipher_cts, algos.mac_cts),
+                mac_stc: mac_algo(algos.cipher_stc, algos.mac_stc),
+                compress_cts: algos.compress_cts,
+                compress_stc: algos.compress_stc,
+            });
+            st.kex_count += 1;
+
             for done_tx in st.negotiate_st.done_txs.drain(..) {
                 let _: Result<_, _> = done_tx.send(Ok(()));
             }
@@ -236,7 +288,7 @@ pub(super) fn recv_kex_packet(
     }
 }
 
-fn send_kex_init(st: &mut ClientState) -> OurKexInit {
+fn send_kex_init(st: &mut ClientState) -> Result<OurKexInit> {
     let cookie: [u8; 16] = st.rng.gen();
 
     fn get_algo_names<A: NamedAlgo>(algos: &[&A]) -> Vec<&'static str> {
@@ -251,6 +303,10 @@ fn send_kex_init(st: &mut ClientState) -> OurKexInit {
         let mut names = get_algo_names(&st.config.kex_algos);
         // RFC 8308
         names.push("ext-info-c");
+        // OpenSSH PROTOCOL, section 1.10
+        if !st.last_kex.done {
+            names.push("kex-strict-c-v00@openssh.com");
+        }
         names
     });
     payload.put_name_list(&get_algo_names(&st.config.server_pubkey_algos));
@@ -258,18 +314,18 @@ fn send_kex_init(st: &mut ClientState) -> OurKexInit {
     payload.put_name_list(&get_algo_names(&st.config.cipher_algos));
     payload.put_name_list(&get_algo_names(&st.config.mac_algos));
     payload.put_name_list(&get_algo_names(&st.config.mac_algos));
-    payload.put_name_list(&["none"]);
-    payload.put_name_list(&["none"]);
+    payload.put_name_list(&get_algo_names(&st.config.compression_algos));
+    payload.put_name_list(&get_algo_names(&st.config.compression_algos));
     payload.put_name_list(&[]);
     payload.put_name_list(&[]);
     payload.put_bool(false);
     payload.put_u32(0);
     let payload = payload.finish();
-    let packet_seq = st.codec.send_pipe.feed_packet(&payload);
+    let packet_seq = st.codec.send_pipe.feed_packet(&payload)?;
 
     log::debug!("sending SSH_MSG_KEXINIT");
 
-    OurKexInit {
+    Ok(OurKexInit {
         payload,
         kex_algos: st.config.kex_algos.clone(),
         server_pubkey_algos: st.config.server_pubkey_algos.clone(),
@@ -277,8 +333,10 @@ fn send_kex_init(st: &mut ClientState) -> OurKexInit {
         cipher_algos_stc: st.config.cipher_algos.clone(),
         mac_algos_cts: st.config.mac_algos.clone(),
         mac_algos_stc: st.config.mac_algos.clone(),
+        compress_algos_cts: st.config.compression_algos.clone(),
+        compress_algos_stc: st.config.compression_algos.clone(),
         packet_seq,
-    }
+    })
 }
 
 fn recv_kex_init(st: &mut ClientState, payload: &mut PacketDecode) -> ResultRecvState {
@@ -290,8 +348,8 @@ fn recv_kex_init(st: &mut ClientState, payload: &mut PacketDecode) -> ResultRecv
     let cipher_algos_stc = payload.get_name_list()?; // encryption_algorithms_server_to_client
     let mac_algos_cts = payload.get_name_list()?; // mac_algorithms_client_to_server
     let mac_algos_stc = payload.get_name_list()?; // mac_algorithms_server_to_client
-    payload.get_name_list()?; // compression_algorithms_client_to_server
-    payload.get_name_list()?; // compression_algorithms_server_to_client
+    let compress_algos_cts = payload.get_name_list()?; // compression_algorithms_client_to_server
+    let compress_algos_stc = payload.get_name_list()?; // compression_algorithms_server_to_client
     payload.get_name_list()?; // languages_client_to_server
     payload.get_name_list()?; // languages_server_to_client
     let first_kex_packet_follows = payload.get_bool()?; // first_kex_packet_follows
@@ -309,9 +367,22 @@ fn recv_kex_init(st: &mut ClientState, payload: &mut PacketDecode) -> ResultRecv
         cipher_algos_stc,
         mac_algos_cts,
         mac_algos_stc,
+        compress_algos_cts,
+        compress_algos_stc,
     };
     log::debug!("received SSH_MSG_KEXINIT: {:?}", kex_init);
 
+    if !st.last_kex.done {
+        st.strict_kex = kex_init.kex_algos.iter().any(|name| name == "kex-strict-s-v00@openssh.com");
+        log::debug!("strict kex: {}", st.strict_kex);
+        if st.strict_kex && st.negotiate_st.kex_init_not_first {
+            return Err(Error::Protocol("received SSH_MSG_KEXINIT that was not the first packet \
+                during strict key exchange"))
+        } else if !st.strict_kex && st.config.require_strict_kex {
+            return Err(Error::StrictKexUnsupported)
+        }
+    }
+
     match st.negotiate_st.state {
         State::Idle | State::KexInit if st.negotiate_st.their_kex_init.is_none() => {
             st.negotiate_st.their_kex_init = Some(kex_init);
@@ -322,6 +393,29 @@ fn recv_kex_init(st: &mut ClientState, payload: &mut PacketDecode) -> ResultRecv
     }
 }
 
+/// Enforce strict key exchange (OpenSSH PROTOCOL, section 1.10).
+///
+/// During the initial key exchange in strict mode, the peer's `SSH_MSG_KEXINIT` must be the first
+/// packet that we receive, and we must abort the connection if we receive any packet that is not
+/// part of the key exchange. We still accept `SSH_MSG_DISCONNECT`, so that the caller learns why
+/// the peer aborted the key exchange.
+pub(super) fn check_strict_kex(st: &mut ClientState, packet: &RecvPacket) -> Result<()> {
+    if st.last_kex.done || st.negotiate_st.new_keys_recvd {
+        return Ok(())
+    }
+
+    let msg_id = packet.payload.first().cloned().unwrap_or(0);
+    if msg_id == msg::KEXINIT && packet.packet_seq != 0 {
+        st.negotiate_st.kex_init_not_first = true;
+    }
+
+    if st.strict_kex && !matches!(msg_id, msg::DISCONNECT | msg::KEXINIT | msg::NEWKEYS | 30..=49) {
+        log::debug!("received packet {} during strict key exchange", msg_id);
+        return Err(Error::Protocol("received unexpected packet during strict key exchange"))
+    }
+    Ok(())
+}
+
 pub(super) fn recv_unimplemented(st: &mut ClientState, packet_seq: u32) -> Result<bool> {
     if let Some(our_kex_init) = st.negotiate_st.our_kex_init.as_ref() {
         if our_kex_init.packet_seq == packet_seq {
@@ -396,7 +490,14 @@ fn negotiate_algos(st: &ClientState) -> Result<Algos> {
     let mac_stc = negotiate_mac_algo(
         cipher_stc, &our.mac_algos_stc, &their.mac_algos_stc, "mac server-to-client")?;
 
-    Ok(Algos { kex, server_pubkey, cipher_cts, cipher_stc, mac_cts, mac_stc })
+    let compress_cts = negotiate_algo(
+        &our.compress_algos_cts, &their.compress_algos_cts, "compression client-to-server")?;
+    let compress_stc = negotiate_algo(
+        &our.compress_algos_stc, &their.compress_algos_stc, "compression server-to-client")?;
+
+    Ok(Algos {
+        kex, server_pubkey, cipher_cts, cipher_stc, mac_cts, mac_stc, compress_cts, compress_stc,
+    })
 }
 
 trait NamedAlgo { fn name(&self) -> &'static str; }
@@ -404,6 +505,7 @@ impl NamedAlgo for KexAlgo { fn name(&self) -> &'static str { self.name } }
 impl NamedAlgo for CipherAlgo { fn name(&self) -> &'static str { self.name } }
 impl NamedAlgo for MacAlgo { fn name(&self) -> &'static str { self.name } }
 impl NamedAlgo for PubkeyAlgo { fn name(&self) -> &'static str { self.name } }
+impl NamedAlgo for CompressAlgo { fn name(&self) -> &'static str { self.name } }
 
 fn recv_new_keys(st: &mut ClientState, _payload: &mut PacketDecode) -> ResultRecvState {
     match st.negotiate_st.state {
@@ -441,14 +543,23 @@ fn recv_new_keys(st: &mut ClientState, _payload: &mut PacketDecode) -> ResultRec
         },
     };
 
+    // OpenSSH PROTOCOL, section 1.2: delayed compression starts after authentication succeeds
+    let compress_algo = algos.compress_stc;
+    let delayed = compress_algo.delayed && !auth::is_authenticated(st);
+    let decompress = (compress_algo.make_decompress)();
+
     st.codec.recv_pipe.set_decrypt(packet_decrypt, cipher_algo.block_len, tag_len);
+    st.codec.recv_pipe.set_decompress(decompress, delayed);
+    if st.strict_kex {
+        st.codec.recv_pipe.reset_packet_seq();
+    }
 
     log::debug!("received SSH_MSG_NEWKEYS and applied new keys");
     st.negotiate_st.new_keys_recvd = true;
     Ok(None)
 }
 
-fn send_new_keys(st: &mut ClientState) {
+fn send_new_keys(st: &mut ClientState) -> Result<()> {
     let algos = st.negotiate_st.algos.as_ref().unwrap();
 
     let cipher_algo = algos.cipher_cts;
@@ -475,12 +586,21 @@ fn send_new_keys(st: &mut ClientState) {
         },
     };
 
+    let compress_algo = algos.compress_cts;
+    let delayed = compress_algo.delayed && !auth::is_authenticated(st);
+    let compress = (compress_algo.make_compress)();
+
     let mut payload = PacketEncode::new();
     payload.put_u8(msg::NEWKEYS);
-    st.codec.send_pipe.feed_packet(&payload.finish());
+    st.codec.send_pipe.feed_packet(&payload.finish())?;
 
     st.codec.send_pipe.set_encrypt(packet_encrypt, cipher_algo.block_len, tag_len);
+    st.codec.send_pipe.set_compress(compress, delayed);
+    if st.strict_kex {
+        st.codec.send_pipe.reset_packet_seq();
+    }
     log::debug!("sending SSH_MSG_NEWKEYS and applied new keys");
+    Ok(())
 }
 
 fn derive_key(st: &ClientState, key_type: u8, key_len: usize) -> Vec<u8> {
@@ -491,7 +611,7 @@ fn derive_key(st: &ClientState, key_type: u8, key_len: usize) -> Vec<u8> {
     let session_id = st.session_id.as_ref().unwrap();
 
     let mut to_hash_prefix = PacketEncode::new();
-    to_hash_prefix.put_biguint(&kex_output.shared_secret);
+    kex_output.shared_secret.encode(&mut to_hash_prefix);
     to_hash_prefix.put_raw(&kex_output.exchange_hash);
     
     let mut key = {
@@ -515,7 +635,7 @@ fn maybe_send_ext_info(st: &mut ClientState) -> Result<()> {
     let ext_info_s = st.negotiate_st.their_kex_init.as_ref().unwrap().kex_algos.iter()
         .any(|name| name == "ext-info-s");
     if !st.last_kex.done && ext_info_s {
-        ext::send_ext_info(st);
+        ext::send_ext_info(st)?;
     }
     Ok(())
 }
@@ -534,3 +654,53 @@ pub(super) fn start_kex(st: &mut ClientState, done_tx: Option<oneshot::Sender<Re
         st.negotiate_st.done_txs.push(done_tx);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+    use tokio::io::AsyncWriteExt as _;
+    use crate::client::{Client, ClientConfig};
+    use crate::codec::{PacketEncode, SendPipe};
+    use crate::codes::{disconnect, msg};
+    use crate::error::Error;
+
+    #[tokio::test]
+    async fn test_disconnect_during_strict_kex() {
+        let (stream, mut server) = tokio::io::duplex(1 << 16);
+        let mut send_pipe = SendPipe::new(&mut OsRng).unwrap();
+        send_pipe.feed_ident(b"SSH-2.0-dummy");
+
+        let mut kex_init = PacketEncode::new();
+        kex_init.put_u8(msg::KEXINIT);
+        kex_init.put_raw(&[0; 16]);
+        kex_init.put_name_list(&["curve25519-sha256", "kex-strict-s-v00@openssh.com"]);
+        kex_init.put_name_list(&["ssh-ed25519"]);
+        kex_init.put_name_list(&["aes128-ctr"]);
+        kex_init.put_name_list(&["aes128-ctr"]);
+        kex_init.put_name_list(&["hmac-sha2-256"]);
+        kex_init.put_name_list(&["hmac-sha2-256"]);
+        kex_init.put_name_list(&["none"]);
+        kex_init.put_name_list(&["none"]);
+        kex_init.put_name_list(&[]);
+        kex_init.put_name_list(&[]);
+        kex_init.put_bool(false);
+        kex_init.put_u32(0);
+        send_pipe.feed_packet(&kex_init.finish()).unwrap();
+
+        let mut disconnect = PacketEncode::new();
+        disconnect.put_u8(msg::DISCONNECT);
+        disconnect.put_u32(disconnect::KEY_EXCHANGE_FAILED);
+        disconnect.put_str("no matching host key type found");
+        disconnect.put_str("");
+        send_pipe.feed_packet(&disconnect.finish()).unwrap();
+
+        server.write_all(send_pipe.peek_bytes()).await.unwrap();
+
+        let (_client, _client_rx, client_fut) = Client::open(stream, ClientConfig::default()).unwrap();
+        match client_fut.await {
+            Err(Error::PeerDisconnected(err)) =>
+                assert_eq!(err.reason_code, disconnect::KEY_EXCHANGE_FAILED),
+            res => panic!("unexpected result {:?}", res.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use bytes::Bytes;
+use std::collections::VecDeque;
+use std::future::Future as _;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+use crate::codec::{PacketDecode, PacketEncode};
+use crate::codes::msg;
+use crate::error::Result;
+use super::{auth, conn, negotiate};
+use super::client::{GlobalReq, GlobalReply};
+use super::client_state::{self, ClientState};
+use super::pump::Pump;
+use super::recv::ResultRecvState;
+
+#[derive(Default)]
+pub(super) struct PingState {
+    send_pings: VecDeque<oneshot::Sender<Duration>>,
+    sent_pings: VecDeque<SentPing>,
+    sent_keepalives: Vec<SentKeepalive>,
+    send_pongs: VecDeque<Bytes>,
+    next_ping_id: u64,
+}
+
+struct SentPing {
+    data: Bytes,
+    sent: Instant,
+    rtt_tx: oneshot::Sender<Duration>,
+}
+
+struct SentKeepalive {
+    reply_rx: oneshot::Receiver<GlobalReply>,
+    sent: Instant,
+    rtt_tx: oneshot::Sender<Duration>,
+}
+
+pub(super) fn init_ping() -> PingState {
+    PingState::default()
+}
+
+pub(super) fn pump_ping(st: &mut ClientState, cx: &mut Context) -> Result<Pump> {
+    let mut progress = Pump::Pending;
+
+    // poll the keepalives first, so that we don't include the delay of sending the new requests in
+    // the measured round-trip time
+    let sent_keepalives = &mut st.ping_st.sent_keepalives;
+    let mut i = 0;
+    while i < sent_keepalives.len() {
+        let Poll::Ready(reply_res) = Pin::new(&mut sent_keepalives[i].reply_rx).poll(cx) else {
+            i += 1;
+            continue
+        };
+
+        // both success and failure prove that the server has processed the request
+        let keepalive = sent_keepalives.swap_remove(i);
+        if reply_res.is_ok() {
+            let _: Result<_, _> = keepalive.rtt_tx.send(keepalive.sent.elapsed());
+        }
+        progress = Pump::Progress;
+    }
+
+    if !negotiate::is_ready(st) {
+        return Ok(progress)
+    }
+
+    if let Some(data) = st.ping_st.send_pongs.pop_front() {
+        send_pong(st, &data)?;
+        return Ok(Pump::Progress)
+    }
+
+    if !auth::is_authenticated(st) {
+        return Ok(progress)
+    }
+
+    if let Some(rtt_tx) = st.ping_st.send_pings.pop_front() {
+        if rtt_tx.is_closed() {
+            return Ok(Pump::Progress)
+        }
+
+        if st.their_ext_info.ping {
+            let ping_id = st.ping_st.next_ping_id;
+            st.ping_st.next_ping_id += 1;
+            let data = Bytes::copy_from_slice(&ping_id.to_be_bytes());
+            send_ping(st, &data)?;
+            st.ping_st.sent_pings.push_back(SentPing { data, sent: Instant::now(), rtt_tx });
+        } else {
+            // the server does not support `ping@openssh.com`, so we use a keepalive request: the
+            // server must reply to it, even though it does not understand it
+            let (reply_tx, reply_rx) = oneshot::channel();
+            let req = GlobalReq {
+                request_type: "keepalive@openssh.com".to_owned(),
+                payload: Bytes::new(),
+                reply_tx: Some(reply_tx),
+            };
+            conn::send_request(st, req)?;
+            st.ping_st.sent_keepalives.push(SentKeepalive { reply_rx, sent: Instant::now(), rtt_tx });
+        }
+        return Ok(Pump::Progress)
+    }
+
+    Ok(progress)
+}
+
+pub(super) fn ping(st: &mut ClientState, rtt_tx: oneshot::Sender<Duration>) {
+    st.ping_st.send_pings.push_back(rtt_tx);
+    client_state::wakeup_client(st);
+}
+
+fn send_ping(st: &mut ClientState, data: &[u8]) -> Result<()> {
+    let mut payload = PacketEncode::new();
+    payload.put_u8(msg::PING);
+    payload.put_bytes(data);
+    st.codec.send_pipe.feed_packet(&payload.finish())?;
+    log::debug!("sending SSH2_MSG_PING");
+    Ok(())
+}
+
+fn send_pong(st: &mut ClientState, data: &[u8]) -> Result<()> {
+    let mut payload = PacketEncode::new();
+    payload.put_u8(msg::PONG);
+    payload.put_bytes(data);
+    st.codec.send_pipe.feed_packet(&payload.finish())?;
+    log::debug!("sending SSH2_MSG_PONG");
+    Ok(())
+}
+
+pub(super) fn recv_ping(st: &mut ClientState, payload: &mut PacketDecode) -> ResultRecvState {
+    let data = payload.get_bytes()?;
+
+    // the extension forbids responding to pings before the key exchange is complete
+    if st.kex_count == 0 {
+        log::debug!("received SSH2_MSG_PING before the key exchange, ignoring");
+        return Ok(None)
+    }
+
+    log::debug!("received SSH2_MSG_PING");
+    st.ping_st.send_pongs.push_back(data);
+    client_state::wakeup_client(st);
+    Ok(None)
+}
+
+pub(super) fn recv_pong(st: &mut ClientState, payload: &mut PacketDecode) -> ResultRecvState {
+    let data = payload.get_bytes()?;
+    let sent_pings = &mut st.ping_st.sent_pings;
+    let Some(idx) = sent_pings.iter().position(|sent_ping| sent_ping.data == data) else {
+        log::debug!("received unexpected SSH2_MSG_PONG, ignoring");
+        return Ok(None)
+    };
+
+    log::debug!("received SSH2_MSG_PONG");
+    let sent_ping = sent_pings.remove(idx).unwrap();
+    let _: Result<_, _> = sent_ping.rtt_tx.send(sent_ping.sent.elapsed());
+    Ok(None)
+}
@@ -3,7 +3,7 @@ use std::task::{Context, Poll};
 use crate::codec::{PacketDecode, PacketEncode, RecvPacket};
 use crate::codes::msg;
 use crate::error::{Error, Result, DisconnectError};
-use super::{auth, conn, ext, negotiate};
+use super::{auth, conn, ext, negotiate, ping};
 use super::client_event::{ClientEvent, DebugMsg};
 use super::client_state::ClientState;
 use super::pump::Pump;
@@ -30,6 +30,7 @@ pub(super) fn pump_recv(st: &mut ClientState, cx: &mut Context) -> Result<Pump>
 
 
 pub(super) fn recv_packet(st: &mut ClientState, packet: RecvPacket) -> ResultRecvState {
+    negotiate::check_strict_kex(st, &packet)?;
     let mut payload = PacketDecode::new(packet.payload.clone());
     match recv_packet_dispatch(st, &mut payload) {
         Ok(recv_state) => Ok(recv_state),
@@ -48,6 +49,8 @@ fn recv_packet_dispatch(st: &mut ClientState, payload: &mut PacketDecode) -> Res
         msg::SERVICE_ACCEPT => recv_service_accept(st, payload),
         msg::EXT_INFO => ext::recv_ext_info(st, payload),
         msg::IGNORE => Ok(None),
+        msg::PING => ping::recv_ping(st, payload),
+        msg::PONG => ping::recv_pong(st, payload),
         20..=29 => negotiate::recv_negotiate_packet(st, msg_id, payload),
         30..=49 => negotiate::recv_kex_packet(st, msg_id, payload),
         50..=59 => auth::recv_auth_packet(st, msg_id, payload),
@@ -123,7 +126,7 @@ fn not_implemented(st: &mut ClientState, msg_id: u8, packet: &RecvPacket) -> Res
     let mut reply = PacketEncode::new();
     reply.put_u8(msg::UNIMPLEMENTED);
     reply.put_u32(packet.packet_seq);
-    st.codec.send_pipe.feed_packet(&reply.finish());
+    st.codec.send_pipe.feed_packet(&reply.finish())?;
     Ok(None)
 }
 
@@ -3,9 +3,11 @@ use futures_core::ready;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Duration;
 use tokio::sync::oneshot;
 use crate::codec::{PacketDecode, PacketEncode};
 use crate::error::{Result, Error};
+use crate::util;
 use super::channel::{
     Channel, ChannelReceiver, ChannelEvent, ChannelConfig,
     ChannelReq, ChannelReply, DATA_STANDARD, DATA_STDERR
@@ -36,12 +38,18 @@ use super::client::Client;
 #[derive(Clone)]
 pub struct Session {
     channel: Channel,
+    request_timeout: Option<Duration>,
 }
 
 impl Session {
     pub(super) async fn open(client: &Client, config: ChannelConfig) -> Result<(Session, SessionReceiver)> {
+        let request_timeout = config.request_timeout;
         let (channel, channel_rx, _) = client.open_channel("session".into(), config, Bytes::new()).await?;
-        Ok((Session { channel }, SessionReceiver { channel_rx }))
+        Ok((Session { channel, request_timeout }, SessionReceiver { channel_rx }))
+    }
+
+    fn resp(&self, reply_rx: oneshot::Receiver<ChannelReply>) -> SessionResp {
+        SessionResp { reply_rx, timeout: self.request_timeout }
     }
 
     /// Get the [`Client`] that this session belongs to.
@@ -80,7 +88,7 @@ impl Session {
             payload: payload.finish(),
             reply_tx: Some(reply_tx),
         })?;
-        Ok(SessionResp { reply_rx })
+        Ok(self.resp(reply_rx))
     }
 
     /// Request a pseudo-terminal (pty) for the future process.
@@ -111,7 +119,54 @@ impl Session {
             payload: payload.finish(),
             reply_tx: Some(reply_tx),
         })?;
-        Ok(SessionResp { reply_rx })
+        Ok(self.resp(reply_rx))
+    }
+
+    /// Request forwarding of X11 connections for the future process.
+    ///
+    /// This will ask the server to forward connections to the X11 display of the process
+    /// according to the `request` (RFC 4254, section 6.3). If the server accepts it, the server
+    /// will open an `x11` channel for every connection to the display, which you will receive as
+    /// [`ClientEvent::X11`][super::ClientEvent::X11]. You can use
+    /// [`x11::X11Cookie`][crate::x11::X11Cookie] to avoid sending your real X11 authentication
+    /// cookie to the server.
+    ///
+    /// This method returns immediately without any blocking, but you may use the returned
+    /// [`SessionResp`] to wait for the server response.
+    pub fn request_x11(&self, request: &X11Request) -> Result<SessionResp> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let mut payload = PacketEncode::new();
+        payload.put_bool(request.single_connection);
+        payload.put_str(&request.auth_protocol);
+        payload.put_str(&request.auth_cookie);
+        payload.put_u32(request.screen_number);
+
+        self.channel.send_request(ChannelReq {
+            request_type: "x11-req".into(),
+            payload: payload.finish(),
+            reply_tx: Some(reply_tx),
+        })?;
+        Ok(self.resp(reply_rx))
+    }
+
+    /// Request forwarding of the SSH agent for the future process.
+    ///
+    /// This is the `auth-agent-req@openssh.com` request from OpenSSH. If the server accepts it,
+    /// programs in the session can use your SSH agent: the server will open an
+    /// `auth-agent@openssh.com` channel for every connection to the agent, which you will receive
+    /// as [`ClientEvent::AgentForward`][super::ClientEvent::AgentForward]. You can forward these
+    /// channels to your local agent using [`agent::bridge()`][crate::agent::bridge()].
+    ///
+    /// This method returns immediately without any blocking, but you may use the returned
+    /// [`SessionResp`] to wait for the server response.
+    pub fn request_agent_forward(&self) -> Result<SessionResp> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.channel.send_request(ChannelReq {
+            request_type: "auth-agent-req@openssh.com".into(),
+            payload: Bytes::new(),
+            reply_tx: Some(reply_tx),
+        })?;
+        Ok(self.resp(reply_rx))
     }
 }
 
@@ -131,7 +186,7 @@ impl Session {
             payload: Bytes::new(),
             reply_tx: Some(reply_tx),
         })?;
-        Ok(SessionResp { reply_rx })
+        Ok(self.resp(reply_rx))
     }
 
     /// Start a command on the server.
@@ -147,7 +202,7 @@ impl Session {
             payload: payload.finish(),
             reply_tx: Some(reply_tx),
         })?;
-        Ok(SessionResp { reply_rx })
+        Ok(self.resp(reply_rx))
     }
 
     /// Start an SSH subsystem on the server.
@@ -165,7 +220,7 @@ impl Session {
             payload: payload.finish(),
             reply_tx: Some(reply_tx),
         })?;
-        Ok(SessionResp { reply_rx })
+        Ok(self.resp(reply_rx))
     }
 }
 
@@ -239,14 +294,17 @@ impl Session {
 #[must_use = "please use .wait().await to await the response, or .ignore() to ignore it"]
 pub struct SessionResp {
     reply_rx: oneshot::Receiver<ChannelReply>,
+    timeout: Option<Duration>,
 }
 
 impl SessionResp {
     /// Wait for the response from the server.
     ///
-    /// If the request failed, this returns an error ([`Error::ChannelReq`]).
+    /// If the request failed, this returns an error ([`Error::ChannelReq`]). If the server does
+    /// not respond within [`ChannelConfig::request_timeout`], this returns
+    /// [`Error::ChannelReqTimeout`].
     pub async fn wait(self) -> Result<()> {
-        match self.reply_rx.await {
+        match util::timeout(self.timeout, Error::ChannelReqTimeout, self.reply_rx).await? {
             Ok(ChannelReply::Success) => Ok(()),
             Ok(ChannelReply::Failure) => Err(Error::ChannelReq),
             Err(_) => Err(Error::ChannelClosed),
@@ -418,6 +476,22 @@ pub struct PtyRequest {
     pub modes: PtyTerminalModes,
 }
 
+/// X11 forwarding request.
+///
+/// Request for forwarding of X11 connections, used with [`Session::request_x11()`], as described
+/// in RFC 4254, section 6.3.1.
+#[derive(Debug, Clone, Default)]
+pub struct X11Request {
+    /// If true, the server will forward only a single X11 connection.
+    pub single_connection: bool,
+    /// X11 authentication protocol (e.g. `"MIT-MAGIC-COOKIE-1"`).
+    pub auth_protocol: String,
+    /// X11 authentication cookie, encoded in hexadecimal.
+    pub auth_cookie: String,
+    /// X11 screen number (usually 0).
+    pub screen_number: u32,
+}
+
 /// Terminal modes for a [`PtyRequest`].
 ///
 /// The terminal modes are encoded as a sequence of opcodes that take a single `u32` argument. For
@@ -0,0 +1,78 @@
+use std::future::Future as _;
+use std::pin::Pin;
+use std::task::Context;
+use tokio::time::{Instant, Sleep};
+use crate::error::{Error, Result};
+use super::client_state::ClientState;
+use super::pump::Pump;
+
+pub(super) struct TimeoutState {
+    opened: Instant,
+    ident_recvd: Option<Instant>,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+pub(super) fn init_timeout() -> TimeoutState {
+    TimeoutState {
+        opened: Instant::now(),
+        ident_recvd: None,
+        sleep: None,
+    }
+}
+
+pub(super) fn pump_timeout(st: &mut ClientState, cx: &mut Context) -> Result<Pump> {
+    // the identification string must arrive within `ident_timeout` after we open the connection,
+    // and then the initial key exchange must finish within `kex_timeout`
+    let timeout = if st.their_ident.is_none() {
+        st.config.ident_timeout.map(|t| (st.timeout_st.opened + t, Error::IdentTimeout))
+    } else if st.kex_count == 0 {
+        let ident_recvd = *st.timeout_st.ident_recvd.get_or_insert_with(Instant::now);
+        st.config.kex_timeout.map(|t| (ident_recvd + t, Error::KexTimeout))
+    } else {
+        None
+    };
+
+    let Some((deadline, error)) = timeout else {
+        st.timeout_st.sleep = None;
+        return Ok(Pump::Pending)
+    };
+
+    let sleep = st.timeout_st.sleep.get_or_insert_with(|| Box::pin(tokio::time::sleep_until(deadline)));
+    if sleep.deadline() != deadline {
+        sleep.as_mut().reset(deadline);
+    }
+
+    pump_ready!(sleep.as_mut().poll(cx));
+    log::debug!("timeout: {}", error);
+    Err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt as _;
+    use crate::client::{Client, ClientConfig};
+    use crate::error::Error;
+
+    #[tokio::test]
+    async fn test_ident_timeout() {
+        let (stream, _server) = tokio::io::duplex(1024);
+        let config = ClientConfig::default().with(|c| {
+            c.ident_timeout = Some(Duration::from_millis(50));
+        });
+        let (_client, _client_rx, client_fut) = Client::open(stream, config).unwrap();
+        assert!(matches!(client_fut.await, Err(Error::IdentTimeout)));
+    }
+
+    #[tokio::test]
+    async fn test_kex_timeout() {
+        let (stream, mut server) = tokio::io::duplex(1024);
+        server.write_all(b"SSH-2.0-dummy\r\n").await.unwrap();
+        let config = ClientConfig::default().with(|c| {
+            c.ident_timeout = Some(Duration::from_millis(50));
+            c.kex_timeout = Some(Duration::from_millis(50));
+        });
+        let (_client, _client_rx, client_fut) = Client::open(stream, config).unwrap();
+        assert!(matches!(client_fut.await, Err(Error::KexTimeout)));
+    }
+}
@@ -21,6 +21,10 @@ use super::client::Client;
 /// - You can ask the server to bind to an address and listen for incoming connections. This is
 /// sometimes called "remote forwarding".
 ///
+/// Tunnels can also carry connections to Unix domain sockets on the server, using the OpenSSH
+/// extensions described in the file `PROTOCOL` in the OpenSSH sources (see
+/// [`Client::connect_streamlocal()`] and [`Client::bind_streamlocal()`]).
+///
 /// If you need to have something that implements `AsyncWrite`, consider using [`TunnelWriter`] or
 /// [`TunnelStream`].
 #[derive(Clone)]
@@ -46,6 +50,21 @@ impl Tunnel {
         Ok((Tunnel { channel }, TunnelReceiver { channel_rx }))
     }
 
+    pub(super) async fn connect_streamlocal(
+        client: &Client,
+        config: ChannelConfig,
+        socket_path: String,
+    ) -> Result<(Tunnel, TunnelReceiver)> {
+        let mut open_payload = PacketEncode::new();
+        open_payload.put_str(&socket_path);
+        open_payload.put_str(""); // reserved
+        open_payload.put_u32(0); // reserved
+
+        let (channel, channel_rx, _) = client.open_channel(
+            "direct-streamlocal@openssh.com".into(), config, open_payload.finish()).await?;
+        Ok((Tunnel { channel }, TunnelReceiver { channel_rx }))
+    }
+
     pub(super) fn accept(channel: Channel, channel_rx: ChannelReceiver) -> Result<(Tunnel, TunnelReceiver)> {
         Ok((Tunnel { channel }, TunnelReceiver { channel_rx }))
     }
@@ -72,6 +91,20 @@ impl Tunnel {
     pub async fn send_eof(&self) -> Result<()> {
         self.channel.send_eof().await
     }
+
+    /// Close the tunnel.
+    ///
+    /// We won't send any further data to the server and the tunnel will be closed once the
+    /// server acknowledges our request.
+    ///
+    /// This method is idempotent: if the tunnel is already closed or closing, we do nothing.
+    pub fn close(&self) -> Result<()> {
+        self.channel.close()
+    }
+
+    pub(super) fn is_client_closed(&self) -> bool {
+        self.channel.client().client_st.strong_count() == 0
+    }
 }
 
 /// Receiving half of a [`Tunnel`].
@@ -304,7 +337,8 @@ impl AsyncWrite for TunnelWriter {
 /// This helper wraps [`Tunnel`] and its [`TunnelReceiver`] and implements `AsyncRead`,
 /// `AsyncBufRead` and `AsyncWrite`. This allows you to use tunnels as any other Tokio async
 /// stream. It also enables you to implement `ProxyJump` functionality by opening a new SSH
-/// connection through a tunnel on an existing SSH connection.
+/// connection through a tunnel on an existing SSH connection, but you may prefer to use
+/// [`Client::connect_jump()`], which does this for you.
 ///
 /// The reader wraps a [`TunnelReceiver`], so you **must** be continuously polling the reader in a
 /// timely manner, otherwise the client will stall.
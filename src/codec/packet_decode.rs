@@ -36,6 +36,12 @@ impl PacketDecode {
         Ok(self.buf.get_u32())
     }
 
+    /// Decode a `uint64`.
+    pub fn get_u64(&mut self) -> Result<u64> {
+        self.ensure(8)?;
+        Ok(self.buf.get_u64())
+    }
+
     /// Decode a `string`.
     pub fn get_bytes(&mut self) -> Result<Bytes> {
         let len = self.get_u32()? as usize;
@@ -30,6 +30,11 @@ impl PacketEncode {
         self.buf.put_u32(value);
     }
 
+    /// Encode a `uint64`.
+    pub fn put_u64(&mut self, value: u64) {
+        self.buf.put_u64(value);
+    }
+
     /// Encode a `string`.
     pub fn put_bytes(&mut self, value: &[u8]) {
         self.buf.reserve(4 + value.len());
@@ -1,18 +1,25 @@
 use bytes::{Bytes, BytesMut};
 use crate::{Error, Result};
 use crate::cipher::{self, PacketDecrypt};
+use crate::compress::{self, Decompress};
 use crate::mac::{self, MacVerified};
 
 pub(crate) struct RecvPipe {
     buf: BytesMut,
     state: State,
     decrypt: PacketDecrypt,
+    decompress: Box<dyn Decompress + Send>,
+    delayed_decompress: Option<Box<dyn Decompress + Send>>,
     block_len: usize,
     tag_len: usize,
     packet_seq: u64,
     recvd_bytes: u64,
+    prefix_lines: Vec<Bytes>,
 }
 
+// OpenSSH also gives up after 1024 lines that precede the identification string
+const MAX_PREFIX_LINES: usize = 1024;
+
 #[derive(Debug, Copy, Clone)]
 enum State {
     Ready,
@@ -32,10 +39,13 @@ impl RecvPipe {
             buf: BytesMut::new(),
             state: State::Ready,
             decrypt: PacketDecrypt::EncryptAndMac(Box::new(cipher::Identity), Box::new(mac::Empty)),
+            decompress: Box::new(compress::Identity),
+            delayed_decompress: None,
             block_len: 8,
             tag_len: 0,
             packet_seq: 0,
             recvd_bytes: 0,
+            prefix_lines: Vec::new(),
         }
     }
 
@@ -57,9 +67,20 @@ impl RecvPipe {
                 }
                 return Ok(Some(line));
             }
+
+            if self.prefix_lines.len() >= MAX_PREFIX_LINES {
+                return Err(Error::Protocol("received too many lines before identification string"))
+            }
+            // copy the line, so that we don't keep a reference to `self.buf`
+            self.prefix_lines.push(Bytes::copy_from_slice(&line));
         }
     }
 
+    /// Takes the lines that preceded the identification string in [`Self::consume_ident()`].
+    pub fn take_prefix_lines(&mut self) -> Vec<Bytes> {
+        std::mem::take(&mut self.prefix_lines)
+    }
+
     fn consume_line(&mut self) -> Result<Option<Bytes>> {
         let mut pos = match self.state {
             State::Ready => 0,
@@ -151,6 +172,7 @@ impl RecvPipe {
 
         let payload_len = packet_len - padding_len - 1;
         let payload = packet.freeze().slice(5..(5 + payload_len));
+        let payload = self.decompress.decompress(payload)?;
 
         let packet_seq = self.packet_seq as u32;
         self.packet_seq += 1;
@@ -213,6 +235,26 @@ impl RecvPipe {
         self.tag_len = tag_len;
     }
 
+    pub fn set_decompress(&mut self, decompress: Box<dyn Decompress + Send>, delayed: bool) {
+        if delayed {
+            self.decompress = Box::new(compress::Identity);
+            self.delayed_decompress = Some(decompress);
+        } else {
+            self.decompress = decompress;
+            self.delayed_decompress = None;
+        }
+    }
+
+    pub fn start_delayed_decompress(&mut self) {
+        if let Some(decompress) = self.delayed_decompress.take() {
+            self.decompress = decompress;
+        }
+    }
+
+    pub fn reset_packet_seq(&mut self) {
+        self.packet_seq = 0;
+    }
+
     pub fn recvd_bytes(&self) -> u64 {
         self.recvd_bytes
     }
@@ -289,6 +331,12 @@ mod tests {
             Some(b("SSH-2.0-dummy")),
         );
 
+        // lines before the ident line are preserved
+        let mut pipe = RecvPipe::new();
+        pipe.feed_buf().extend_from_slice(b"spam and eggs\r\nfoo bar\nSSH-2.0-dummy\r\n");
+        assert_eq!(pipe.consume_ident().unwrap(), Some(b("SSH-2.0-dummy")));
+        assert_eq!(pipe.take_prefix_lines(), vec![b("spam and eggs"), b("foo bar")]);
+
         // \r without \n is not a valid line termination
         check_ident(
             b"spam and eggs\r\nSSH-2.0-dummy\rfoo",
@@ -3,12 +3,15 @@ use rand::{RngCore as _, SeedableRng as _};
 use rand_chacha::ChaCha8Rng;
 use crate::{Error, Result};
 use crate::cipher::{self, PacketEncrypt};
+use crate::compress::{self, Compress};
 use crate::mac;
 use crate::util::CryptoRngCore;
 
 pub(crate) struct SendPipe {
     buf: BytesMut,
     encrypt: PacketEncrypt,
+    compress: Box<dyn Compress + Send>,
+    delayed_compress: Option<Box<dyn Compress + Send>>,
     block_len: usize,
     tag_len: usize,
     packet_seq: u64,
@@ -23,6 +26,8 @@ impl SendPipe {
         Ok(SendPipe {
             buf: BytesMut::new(),
             encrypt: PacketEncrypt::EncryptAndMac(Box::new(cipher::Identity), Box::new(mac::Empty)),
+            compress: Box::new(compress::Identity),
+            delayed_compress: None,
             block_len: 8,
             tag_len: 0,
             packet_seq: 0,
@@ -38,9 +43,10 @@ impl SendPipe {
         self.buf.put_slice(&b"\r\n"[..]);
     }
 
-    pub fn feed_packet(&mut self, payload: &[u8]) -> u32 {
+    pub fn feed_packet(&mut self, payload: &[u8]) -> Result<u32> {
         log::trace!("feed packet {}, len {}, seq {}",
             payload.first().cloned().unwrap_or(0), payload.len(), self.packet_seq);
+        let payload = self.compress.compress(payload)?;
 
         let include_len = match self.encrypt {
             PacketEncrypt::EncryptAndMac(_, _) => true,
@@ -62,7 +68,7 @@ impl SendPipe {
         self.buf.reserve(5 + payload.len() + padding_len + self.tag_len);
         self.buf.put_u32((1 + payload.len() + padding_len) as u32);
         self.buf.put_u8(padding_len as u8);
-        self.buf.put_slice(payload);
+        self.buf.put_slice(&payload);
         self.buf.put_bytes(0, padding_len + self.tag_len);
 
         let packet = &mut self.buf[packet_begin..];
@@ -89,7 +95,7 @@ impl SendPipe {
 
         let packet_seq = self.packet_seq as u32;
         self.packet_seq += 1;
-        packet_seq
+        Ok(packet_seq)
     }
 
     pub fn set_encrypt(&mut self, encrypt: PacketEncrypt, block_len: usize, tag_len: usize) {
@@ -98,6 +104,26 @@ impl SendPipe {
         self.tag_len = tag_len;
     }
 
+    pub fn set_compress(&mut self, compress: Box<dyn Compress + Send>, delayed: bool) {
+        if delayed {
+            self.compress = Box::new(compress::Identity);
+            self.delayed_compress = Some(compress);
+        } else {
+            self.compress = compress;
+            self.delayed_compress = None;
+        }
+    }
+
+    pub fn start_delayed_compress(&mut self) {
+        if let Some(compress) = self.delayed_compress.take() {
+            self.compress = compress;
+        }
+    }
+
+    pub fn reset_packet_seq(&mut self) {
+        self.packet_seq = 0;
+    }
+
     pub fn peek_bytes(&self) -> &[u8] {
         &self.buf
     }
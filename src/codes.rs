@@ -31,6 +31,8 @@ pub(crate) mod msg {
     pub const CHANNEL_REQUEST: u8 = 98;
     pub const CHANNEL_SUCCESS: u8 = 99;
     pub const CHANNEL_FAILURE: u8 = 100;
+    pub const PING: u8 = 192;
+    pub const PONG: u8 = 193;
 
     pub const KEX_ECDH_INIT: u8 = 30;
     pub const KEX_ECDH_REPLY: u8 = 31;
@@ -38,8 +40,16 @@ pub(crate) mod msg {
     pub const KEXDH_INIT: u8 = 30;
     pub const KEXDH_REPLY: u8 = 31;
 
+    pub const KEX_DH_GEX_REQUEST_OLD: u8 = 30;
+    pub const KEX_DH_GEX_GROUP: u8 = 31;
+    pub const KEX_DH_GEX_INIT: u8 = 32;
+    pub const KEX_DH_GEX_REPLY: u8 = 33;
+    pub const KEX_DH_GEX_REQUEST: u8 = 34;
+
     pub const USERAUTH_PASSWD_CHANGEREQ: u8 = 60;
     pub const USERAUTH_PK_OK: u8 = 60;
+    pub const USERAUTH_INFO_REQUEST: u8 = 60;
+    pub const USERAUTH_INFO_RESPONSE: u8 = 61;
 }
 
 /// Reason codes for [`DisconnectError`][crate::DisconnectError].
@@ -102,6 +112,98 @@ pub mod open {
     }
 }
 
+/// Status codes for [`SftpError`][crate::SftpError].
+///
+/// These are the `SSH_FX_*` codes from the SFTP protocol (version 3).
+pub mod sftp_status {
+    pub const OK: u32 = 0;
+    pub const EOF: u32 = 1;
+    pub const NO_SUCH_FILE: u32 = 2;
+    pub const PERMISSION_DENIED: u32 = 3;
+    pub const FAILURE: u32 = 4;
+    pub const BAD_MESSAGE: u32 = 5;
+    pub const NO_CONNECTION: u32 = 6;
+    pub const CONNECTION_LOST: u32 = 7;
+    pub const OP_UNSUPPORTED: u32 = 8;
+
+    /// Convert a status code to a string.
+    pub const fn to_str(code: u32) -> Option<&'static str> {
+        Some(match code {
+            OK => "ok",
+            EOF => "eof",
+            NO_SUCH_FILE => "no such file",
+            PERMISSION_DENIED => "permission denied",
+            FAILURE => "failure",
+            BAD_MESSAGE => "bad message",
+            NO_CONNECTION => "no connection",
+            CONNECTION_LOST => "connection lost",
+            OP_UNSUPPORTED => "operation unsupported",
+            _ => return None,
+        })
+    }
+}
+
+pub(crate) mod sftp_msg {
+    pub const INIT: u8 = 1;
+    pub const VERSION: u8 = 2;
+    pub const OPEN: u8 = 3;
+    pub const CLOSE: u8 = 4;
+    pub const READ: u8 = 5;
+    pub const WRITE: u8 = 6;
+    pub const LSTAT: u8 = 7;
+    pub const FSTAT: u8 = 8;
+    pub const SETSTAT: u8 = 9;
+    pub const FSETSTAT: u8 = 10;
+    pub const OPENDIR: u8 = 11;
+    pub const READDIR: u8 = 12;
+    pub const REMOVE: u8 = 13;
+    pub const MKDIR: u8 = 14;
+    pub const RMDIR: u8 = 15;
+    pub const REALPATH: u8 = 16;
+    pub const STAT: u8 = 17;
+    pub const RENAME: u8 = 18;
+    pub const READLINK: u8 = 19;
+    pub const SYMLINK: u8 = 20;
+    pub const STATUS: u8 = 101;
+    pub const HANDLE: u8 = 102;
+    pub const DATA: u8 = 103;
+    pub const NAME: u8 = 104;
+    pub const ATTRS: u8 = 105;
+    pub const EXTENDED: u8 = 200;
+    pub const EXTENDED_REPLY: u8 = 201;
+}
+
+pub(crate) mod sftp_open {
+    pub const READ: u32 = 0x01;
+    pub const WRITE: u32 = 0x02;
+    pub const APPEND: u32 = 0x04;
+    pub const CREAT: u32 = 0x08;
+    pub const TRUNC: u32 = 0x10;
+    pub const EXCL: u32 = 0x20;
+}
+
+pub(crate) mod sftp_attr {
+    pub const SIZE: u32 = 0x01;
+    pub const UIDGID: u32 = 0x02;
+    pub const PERMISSIONS: u32 = 0x04;
+    pub const ACMODTIME: u32 = 0x08;
+    pub const EXTENDED: u32 = 0x8000_0000;
+}
+
+pub(crate) mod agent_msg {
+    pub const FAILURE: u8 = 5;
+    pub const SUCCESS: u8 = 6;
+    pub const REQUEST_IDENTITIES: u8 = 11;
+    pub const IDENTITIES_ANSWER: u8 = 12;
+    pub const SIGN_REQUEST: u8 = 13;
+    pub const SIGN_RESPONSE: u8 = 14;
+}
+
+pub(crate) mod agent_sign {
+    pub const RSA_SHA2_256: u32 = 0x02;
+    pub const RSA_SHA2_512: u32 = 0x04;
+}
+
 /// Signal codes for [`Session::signal()`][crate::Session::signal()] and
 /// [`ExitSignal`][crate::ExitSignal].
 ///
@@ -0,0 +1,48 @@
+//! Compression algorithms.
+//!
+//! The SSH protocol supports compression of the packet payloads, which can reduce the amount of
+//! transferred data on slow links. Note that compression is applied before encryption, so it may
+//! leak information about the plaintext.
+//!
+//! The client and the server exchange lists of supported algorithms, and the first algorithm on
+//! the client's list that is also supported by the server is used for the connection.
+//!
+//! # Supported algorithms
+//!
+//! - "none" ([`NONE`])
+//! - "zlib@openssh.com" ([`ZLIB_OPENSSH`], delayed until the user is authenticated)
+//! - "zlib" ([`ZLIB`])
+use bytes::Bytes;
+use derivative::Derivative;
+use std::borrow::Cow;
+use crate::Result;
+pub use self::none::NONE;
+pub use self::zlib::{ZLIB, ZLIB_OPENSSH};
+pub(crate) use self::none::Identity;
+
+mod none;
+mod zlib;
+
+/// Algorithm for compressing and decompressing packets.
+///
+/// See the [module documentation][self] for details.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct CompressAlgo {
+    /// Name of the algorithm.
+    pub name: &'static str,
+    /// Is the compression delayed until the user is authenticated?
+    pub(crate) delayed: bool,
+    #[derivative(Debug = "ignore")]
+    pub(crate) make_compress: fn() -> Box<dyn Compress + Send>,
+    #[derivative(Debug = "ignore")]
+    pub(crate) make_decompress: fn() -> Box<dyn Decompress + Send>,
+}
+
+pub(crate) trait Compress {
+    fn compress<'p>(&mut self, payload: &'p [u8]) -> Result<Cow<'p, [u8]>>;
+}
+
+pub(crate) trait Decompress {
+    fn decompress(&mut self, payload: Bytes) -> Result<Bytes>;
+}
@@ -0,0 +1,27 @@
+use bytes::Bytes;
+use std::borrow::Cow;
+use crate::Result;
+use super::{CompressAlgo, Compress, Decompress};
+
+/// "none" compression (packets are not compressed).
+pub static NONE: CompressAlgo = CompressAlgo {
+    name: "none",
+    delayed: false,
+    make_compress: || Box::new(Identity),
+    make_decompress: || Box::new(Identity),
+};
+
+#[derive(Debug)]
+pub struct Identity;
+
+impl Compress for Identity {
+    fn compress<'p>(&mut self, payload: &'p [u8]) -> Result<Cow<'p, [u8]>> {
+        Ok(Cow::Borrowed(payload))
+    }
+}
+
+impl Decompress for Identity {
+    fn decompress(&mut self, payload: Bytes) -> Result<Bytes> {
+        Ok(payload)
+    }
+}
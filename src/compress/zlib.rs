@@ -0,0 +1,138 @@
+use bytes::Bytes;
+use flate2::{FlushCompress, FlushDecompress, Status};
+use std::borrow::Cow;
+use crate::error::{Error, Result};
+use super::{CompressAlgo, Compress, Decompress};
+
+/// "zlib" compression from RFC 4253.
+///
+/// The compression starts immediately after the key exchange.
+pub static ZLIB: CompressAlgo = CompressAlgo {
+    name: "zlib",
+    delayed: false,
+    make_compress: || Box::new(ZlibCompress::new()),
+    make_decompress: || Box::new(ZlibDecompress::new()),
+};
+
+/// "zlib@openssh.com" delayed compression from OpenSSH.
+///
+/// This is the same as ["zlib"][ZLIB], but the compression starts only after the user is
+/// successfully authenticated, so that an unauthenticated attacker cannot exploit bugs in the
+/// compression code.
+pub static ZLIB_OPENSSH: CompressAlgo = CompressAlgo {
+    name: "zlib@openssh.com",
+    delayed: true,
+    make_compress: || Box::new(ZlibCompress::new()),
+    make_decompress: || Box::new(ZlibDecompress::new()),
+};
+
+// limit the size of decompressed payload, to prevent "zip bombs"
+const MAX_PAYLOAD_LEN: usize = 1024*1024;
+
+struct ZlibCompress {
+    compress: flate2::Compress,
+}
+
+impl ZlibCompress {
+    fn new() -> Self {
+        Self { compress: flate2::Compress::new(flate2::Compression::default(), true) }
+    }
+}
+
+impl Compress for ZlibCompress {
+    fn compress<'p>(&mut self, payload: &'p [u8]) -> Result<Cow<'p, [u8]>> {
+        // RFC 4253, section 6.2: the compression context is shared by all packets in one direction,
+        // and each packet is terminated by a partial flush
+        let mut output = Vec::with_capacity(payload.len() + 64);
+        let mut input = payload;
+        loop {
+            let total_in = self.compress.total_in();
+            self.compress.compress_vec(input, &mut output, FlushCompress::Partial)
+                .map_err(|_| Error::Compress("zlib compression failed"))?;
+            input = &input[(self.compress.total_in() - total_in) as usize..];
+
+            // the flush is complete when zlib did not fill the whole output buffer
+            if input.is_empty() && output.len() < output.capacity() {
+                break
+            }
+            output.reserve(output.capacity());
+        }
+        Ok(Cow::Owned(output))
+    }
+}
+
+struct ZlibDecompress {
+    decompress: flate2::Decompress,
+}
+
+impl ZlibDecompress {
+    fn new() -> Self {
+        Self { decompress: flate2::Decompress::new(true) }
+    }
+}
+
+impl Decompress for ZlibDecompress {
+    fn decompress(&mut self, payload: Bytes) -> Result<Bytes> {
+        let mut output = Vec::with_capacity(2 * payload.len() + 64);
+        let mut input = &payload[..];
+        loop {
+            let total_in = self.decompress.total_in();
+            let total_out = self.decompress.total_out();
+            let status = self.decompress.decompress_vec(input, &mut output, FlushDecompress::Sync)
+                .map_err(|_| Error::Protocol("received invalid compressed data"))?;
+            input = &input[(self.decompress.total_in() - total_in) as usize..];
+            let progress = self.decompress.total_in() != total_in
+                || self.decompress.total_out() != total_out;
+
+            if input.is_empty() && output.len() < output.capacity() {
+                break
+            } else if output.len() > MAX_PAYLOAD_LEN {
+                return Err(Error::Protocol("received compressed packet that is too long"))
+            } else if !progress && status == Status::BufError && output.len() < output.capacity() {
+                return Err(Error::Protocol("received truncated compressed data"))
+            }
+            output.reserve(output.capacity());
+        }
+        Ok(output.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_decompress() {
+        let mut compress = ZlibCompress::new();
+        let mut decompress = ZlibDecompress::new();
+
+        let payloads: Vec<Vec<u8>> = vec![
+            b"hello".to_vec(),
+            Vec::new(),
+            b"hello hello hello hello".to_vec(),
+            (0..100_000u64).map(|x| (x * x % 251) as u8).collect(),
+            vec![0; 300_000],
+        ];
+
+        // the compression context must be preserved between the packets
+        for payload in payloads.iter() {
+            let compressed = compress.compress(payload).unwrap().into_owned();
+            let decompressed = decompress.decompress(compressed.into()).unwrap();
+            assert_eq!(&decompressed[..], &payload[..]);
+        }
+    }
+
+    #[test]
+    fn test_decompress_too_long() {
+        let mut compress = ZlibCompress::new();
+        let mut decompress = ZlibDecompress::new();
+        let compressed = compress.compress(&vec![0; 2*MAX_PAYLOAD_LEN]).unwrap().into_owned();
+        assert!(decompress.decompress(compressed.into()).is_err());
+    }
+
+    #[test]
+    fn test_decompress_invalid() {
+        let mut decompress = ZlibDecompress::new();
+        assert!(decompress.decompress(Bytes::from_static(b"garbage garbage")).is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt;
 use rsa::pkcs1;
-use crate::codes::{disconnect, open};
+use crate::codes::{disconnect, open, sftp_status};
 
 /// Result type for our [`Error`].
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -17,6 +17,8 @@ pub enum Error {
     Crypto(&'static str),
     #[error("randomness error: {0}")]
     Random(&'static str),
+    #[error("compression error: {0}")]
+    Compress(&'static str),
     #[error("mac verification failed")]
     Mac,
     #[error("signature verification failed")]
@@ -25,6 +27,10 @@ pub enum Error {
     PubkeyFormat,
     #[error("algorithm is not compatible with this private key format")]
     PrivkeyFormat,
+    #[error("certificate does not match the private key")]
+    CertificateMismatch,
+    #[error("could not sign data")]
+    Sign(#[source] Box<dyn std::error::Error + Send + Sync>),
     #[error("server public key was not accepted")]
     PubkeyAccept(#[source] Box<dyn std::error::Error + Send + Sync>),
     #[error("protocol error: {0}")]
@@ -33,12 +39,18 @@ pub enum Error {
     Decode(&'static str),
     #[error("could not negotiate algorithm")]
     AlgoNegotiate(#[source] AlgoNegotiateError),
+    #[error("unknown algorithm {0:?}")]
+    UnknownAlgo(String),
+    #[error("invalid configuration: {0}")]
+    Config(&'static str),
     #[error("we do not implement packet {0}")]
     PacketNotImplemented(u8),
     #[error("authentication method was aborted")]
     AuthAborted,
     #[error("another authentication method is pending")]
     AuthPending,
+    #[error("cannot respond to keyboard-interactive request: {0}")]
+    AuthInfoResponse(&'static str),
     #[error("authentication failed")]
     AuthFailed,
     #[error("channel is closed")]
@@ -53,6 +65,8 @@ pub enum Error {
     RekeyAborted,
     #[error("rekeying was rejected by the peer")]
     RekeyRejected,
+    #[error("peer does not support strict key exchange")]
+    StrictKexUnsupported,
     #[error("IO error when reading")]
     ReadIo(#[source] std::io::Error),
     #[error("IO error when writing")]
@@ -61,12 +75,30 @@ pub enum Error {
     PeerRejectedPacket(u32),
     #[error("connection unexpectedly closed by peer")]
     PeerClosed,
+    #[error("peer did not respond to keepalive requests")]
+    KeepaliveTimeout,
+    #[error("timed out waiting for the identification string from the peer")]
+    IdentTimeout,
+    #[error("initial key exchange timed out")]
+    KexTimeout,
+    #[error("authentication attempt timed out")]
+    AuthTimeout,
+    #[error("opening channel timed out")]
+    ChannelOpenTimeout,
+    #[error("channel request timed out")]
+    ChannelReqTimeout,
+    #[error("global request timed out")]
+    GlobalReqTimeout,
     #[error("peer disconnected")]
     PeerDisconnected(#[source] DisconnectError),
     #[error("client is closed")]
     ClientClosed,
     #[error("client has already disconnected")]
     ClientDisconnected,
+    #[error("connection to the jump host was closed")]
+    JumpClosed,
+    #[error("connection to the jump host failed: {0}")]
+    JumpFailed(String),
     #[error("could not parse PEM file")]
     Pem(#[source] pem::PemError),
     #[error("could not parse file in PKCS#1 format")]
@@ -91,6 +123,32 @@ pub enum Error {
     UnknownPemTag(String),
     #[error("bad passphrase when decoding key")]
     BadKeyPassphrase,
+    #[error("SFTP request failed")]
+    Sftp(#[source] SftpError),
+    #[error("SFTP protocol error: {0}")]
+    SftpProtocol(&'static str),
+    #[error("SFTP session is closed")]
+    SftpClosed,
+    #[error("SSH agent socket not found (SSH_AUTH_SOCK is not set)")]
+    AgentNotFound,
+    #[error("IO error when communicating with SSH agent")]
+    AgentIo(#[source] std::io::Error),
+    #[error("SSH agent protocol error: {0}")]
+    AgentProtocol(&'static str),
+    #[error("SSH agent refused the request")]
+    AgentFailure,
+    #[error("IO error when forwarding X11 connection")]
+    X11Io(#[source] std::io::Error),
+    #[error("X11 connection did not authenticate with the expected cookie")]
+    X11Auth,
+    #[error("IO error in SOCKS connection")]
+    SocksIo(#[source] std::io::Error),
+    #[error("SOCKS protocol error: {0}")]
+    SocksProtocol(&'static str),
+    #[error("IO error in forwarded connection")]
+    ForwardIo(#[source] std::io::Error),
+    #[error("invalid ssh_config: {0}")]
+    SshConfig(String),
 }
 
 impl From<Error> for std::io::Error {
@@ -178,6 +236,26 @@ impl fmt::Display for ChannelOpenError {
     }
 }
 
+/// Error returned by an SFTP server.
+///
+/// This corresponds to the `SSH_FXP_STATUS` packet with a status code other than `SSH_FX_OK`, as
+/// described in the SFTP protocol (version 3), section 7.
+#[derive(Debug, Clone, thiserror::Error)]
+pub struct SftpError {
+    /// Machine-readable status code (see [`codes::sftp_status`][crate::codes::sftp_status]).
+    pub status_code: u32,
+    /// Human-readable description of the error.
+    pub message: String,
+    /// Language tag of `message` (per RFC 3066).
+    pub message_lang: String,
+}
+
+impl fmt::Display for SftpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_reason(f, sftp_status::to_str(self.status_code), self.status_code, &self.message)
+    }
+}
+
 fn fmt_reason(
     f: &mut fmt::Formatter,
     reason: Option<&'static str>,
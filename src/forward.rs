@@ -0,0 +1,439 @@
+//! Local and remote port forwarding.
+//!
+//! This module implements the usual port forwarding modes of the `ssh` command on top of
+//! [`Client::connect_tunnel()`] and [`Client::bind_tunnel()`]:
+//!
+//! - [`LocalForward`] (`ssh -L`) listens on a local `TcpListener` and forwards every connection
+//!   to an address that is reachable from the SSH server.
+//! - [`RemoteForward`] (`ssh -R`) asks the SSH server to listen on an address and forwards every
+//!   connection to an address that is reachable from us.
+//!
+//! We don't spawn any tasks ourselves: both forwarders produce connection objects
+//! ([`LocalForwardConn`] and [`RemoteForwardConn`]) that you should run, typically in a new task,
+//! so every connection reports its own errors. Each forwarder has a [`ForwardHandle`], which you
+//! can use to shut the forwarder down gracefully and to obtain [`ForwardStats`].
+use std::future::Future as _;
+use std::net::SocketAddr;
+use std::pin::pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::task::Poll;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Notify;
+use crate::client::{Client, ChannelConfig, AcceptTunnel, TunnelStream};
+use crate::codes;
+use crate::error::{Error, Result, ChannelOpenError};
+
+/// Statistics of a forwarder.
+///
+/// You can obtain the statistics from [`ForwardHandle::stats()`].
+///
+/// This struct is `#[non_exhaustive]`, so we may add more fields without breaking backward
+/// compatibility.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ForwardStats {
+    /// Number of connections that were accepted by the forwarder.
+    pub connections: u64,
+    /// Number of connections that are currently running.
+    pub active_connections: u64,
+    /// Number of connections that failed with an error.
+    pub failed_connections: u64,
+    /// Number of bytes sent from the local side to the remote side.
+    ///
+    /// The bytes are counted when the connection is closed.
+    pub bytes_sent: u64,
+    /// Number of bytes sent from the remote side to the local side.
+    ///
+    /// The bytes are counted when the connection is closed.
+    pub bytes_received: u64,
+}
+
+/// Handle to a [`LocalForward`] or [`RemoteForward`].
+///
+/// You can cheaply clone this object and use it from other tasks to shut the forwarder down or
+/// to inspect its statistics.
+#[derive(Clone)]
+pub struct ForwardHandle {
+    shared: Arc<Shared>,
+}
+
+#[derive(Default)]
+struct Shared {
+    shutdown: AtomicBool,
+    shutdown_notify: Notify,
+    idle_notify: Notify,
+    connections: AtomicU64,
+    active_connections: AtomicU64,
+    failed_connections: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+}
+
+impl ForwardHandle {
+    /// Stop accepting new connections.
+    ///
+    /// Connections that are already running are not interrupted, you can wait until they finish
+    /// using [`wait_idle()`][Self::wait_idle()].
+    ///
+    /// This method is idempotent.
+    pub fn shutdown(&self) {
+        self.shared.shutdown.store(true, Ordering::SeqCst);
+        self.shared.shutdown_notify.notify_waiters();
+    }
+
+    /// Returns true if [`shutdown()`][Self::shutdown()] has been called.
+    pub fn is_shutdown(&self) -> bool {
+        self.shared.shutdown.load(Ordering::SeqCst)
+    }
+
+    /// Wait until there are no running connections.
+    pub async fn wait_idle(&self) {
+        loop {
+            let mut notified = pin!(self.shared.idle_notify.notified());
+            notified.as_mut().enable();
+            if self.shared.active_connections.load(Ordering::SeqCst) == 0 {
+                return
+            }
+            notified.await;
+        }
+    }
+
+    /// Get the current statistics of the forwarder.
+    pub fn stats(&self) -> ForwardStats {
+        ForwardStats {
+            connections: self.shared.connections.load(Ordering::Relaxed),
+            active_connections: self.shared.active_connections.load(Ordering::Relaxed),
+            failed_connections: self.shared.failed_connections.load(Ordering::Relaxed),
+            bytes_sent: self.shared.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.shared.bytes_received.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Shared {
+    fn start_conn(self: &Arc<Self>) -> ActiveConn {
+        self.connections.fetch_add(1, Ordering::Relaxed);
+        self.active_connections.fetch_add(1, Ordering::SeqCst);
+        ActiveConn { shared: self.clone() }
+    }
+}
+
+/// Guard that counts a running connection in [`ForwardStats::active_connections`].
+struct ActiveConn {
+    shared: Arc<Shared>,
+}
+
+impl ActiveConn {
+    /// Copy the data between the local and remote side and record the result in the statistics.
+    async fn run<F>(self, fut: F) -> Result<()>
+        where F: std::future::Future<Output = Result<(u64, u64)>>
+    {
+        match fut.await {
+            Ok((bytes_sent, bytes_received)) => {
+                self.shared.bytes_sent.fetch_add(bytes_sent, Ordering::Relaxed);
+                self.shared.bytes_received.fetch_add(bytes_received, Ordering::Relaxed);
+                Ok(())
+            },
+            Err(err) => {
+                self.shared.failed_connections.fetch_add(1, Ordering::Relaxed);
+                Err(err)
+            },
+        }
+    }
+}
+
+impl Drop for ActiveConn {
+    fn drop(&mut self) {
+        if self.shared.active_connections.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.shared.idle_notify.notify_waiters();
+        }
+    }
+}
+
+async fn splice<S>(tunnel_stream: &mut TunnelStream, local_stream: &mut S) -> Result<(u64, u64)>
+    where S: AsyncRead + AsyncWrite + Unpin
+{
+    let (bytes_received, bytes_sent) = tokio::io::copy_bidirectional(tunnel_stream, local_stream).await
+        .map_err(Error::ForwardIo)?;
+    Ok((bytes_sent, bytes_received))
+}
+
+
+
+/// Local port forwarding (`ssh -L`).
+///
+/// This object wraps a `TcpListener` and a [`Client`]. Call [`accept()`][Self::accept()] in a
+/// loop to receive [`LocalForwardConn`]s and run each of them in a new task. For every
+/// connection, we ask the SSH server to connect to the `connect_addr` that you have passed to
+/// [`new()`][Self::new()].
+pub struct LocalForward {
+    client: Client,
+    config: ChannelConfig,
+    listener: TcpListener,
+    connect_addr: (String, u16),
+    shared: Arc<Shared>,
+}
+
+impl LocalForward {
+    /// Create a forwarder that forwards connections from `listener` to `connect_addr`.
+    ///
+    /// The `connect_addr` is resolved by the SSH server. The `config` is used for every tunnel
+    /// that we open.
+    pub fn new(
+        client: Client,
+        config: ChannelConfig,
+        listener: TcpListener,
+        connect_addr: (String, u16),
+    ) -> LocalForward {
+        LocalForward { client, config, listener, connect_addr, shared: Arc::default() }
+    }
+
+    /// Get the local address that we are listening on.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener.local_addr().map_err(Error::ForwardIo)
+    }
+
+    /// Get the address that the SSH server connects to.
+    pub fn connect_addr(&self) -> &(String, u16) {
+        &self.connect_addr
+    }
+
+    /// Get a [`ForwardHandle`] to this forwarder.
+    pub fn handle(&self) -> ForwardHandle {
+        ForwardHandle { shared: self.shared.clone() }
+    }
+
+    /// Accept a new connection.
+    ///
+    /// This only accepts the TCP connection, you must then call [`LocalForwardConn::run()`] to
+    /// open the tunnel and forward the connection.
+    ///
+    /// Returns `None` after the forwarder was shut down using [`ForwardHandle::shutdown()`].
+    pub async fn accept(&self) -> Result<Option<LocalForwardConn>> {
+        let mut shutdown = pin!(self.shared.shutdown_notify.notified());
+        shutdown.as_mut().enable();
+        let mut accept = pin!(self.listener.accept());
+        let accepted = std::future::poll_fn(|cx| {
+            if self.shared.shutdown.load(Ordering::SeqCst) || shutdown.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(Ok(None))
+            }
+            accept.as_mut().poll(cx).map_ok(Some)
+        }).await.map_err(Error::ForwardIo)?;
+
+        Ok(accepted.map(|(stream, peer_addr)| LocalForwardConn {
+            client: self.client.clone(),
+            config: self.config.clone(),
+            stream,
+            peer_addr,
+            connect_addr: self.connect_addr.clone(),
+            active: self.shared.start_conn(),
+        }))
+    }
+}
+
+/// A connection accepted by [`LocalForward`].
+pub struct LocalForwardConn {
+    client: Client,
+    config: ChannelConfig,
+    stream: TcpStream,
+    peer_addr: SocketAddr,
+    connect_addr: (String, u16),
+    active: ActiveConn,
+}
+
+impl LocalForwardConn {
+    /// Get the address of the local peer that connected to us.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    /// Handle the connection.
+    ///
+    /// We open a tunnel using [`Client::connect_tunnel()`] (with the address of the local peer as
+    /// the originator address) and then copy the data in both directions until both sides are
+    /// closed.
+    pub async fn run(self) -> Result<()> {
+        let Self { client, config, mut stream, peer_addr, connect_addr, active } = self;
+        active.run(async move {
+            let originator_addr = (peer_addr.ip().to_string(), peer_addr.port());
+            let (tunnel, tunnel_rx) = client.connect_tunnel(config, connect_addr, originator_addr).await?;
+            let mut tunnel_stream = TunnelStream::new(tunnel, tunnel_rx);
+            splice(&mut tunnel_stream, &mut stream).await
+        }).await
+    }
+}
+
+
+
+/// Remote port forwarding (`ssh -R`).
+///
+/// Create this object using [`bind()`][Self::bind()], which asks the SSH server to listen on an
+/// address using [`Client::bind_tunnel()`]. Then, when you receive
+/// [`ClientEvent::Tunnel`][crate::ClientEvent::Tunnel] from the
+/// [`ClientReceiver`][crate::ClientReceiver], find the forwarder that
+/// [`matches()`][Self::matches()] the [`AcceptTunnel`] and pass the tunnel to
+/// [`accept()`][Self::accept()] to get a [`RemoteForwardConn`] that you should run, typically in
+/// a new task. This way, you can use multiple forwarders on the same client.
+///
+/// When you drop this object, we ask the server to stop listening using
+/// [`Client::unbind_tunnel()`], but we don't wait for the response. Use
+/// [`unbind()`][Self::unbind()] if you want to wait.
+pub struct RemoteForward {
+    client: Client,
+    config: ChannelConfig,
+    bind_addr: (String, u16),
+    connect_addr: (String, u16),
+    shared: Arc<Shared>,
+    bound: bool,
+}
+
+impl RemoteForward {
+    /// Ask the server to listen on `bind_addr` and forward the connections to `connect_addr`.
+    ///
+    /// The `bind_addr` is interpreted as in [`Client::bind_tunnel()`]; if the port is 0, the
+    /// server chooses a free port, which you can obtain from [`bind_addr()`][Self::bind_addr()].
+    /// The `connect_addr` is resolved by us. The `config` is used for every tunnel that we
+    /// accept.
+    pub async fn bind(
+        client: Client,
+        config: ChannelConfig,
+        bind_addr: (String, u16),
+        connect_addr: (String, u16),
+    ) -> Result<RemoteForward> {
+        let bound_port = client.bind_tunnel(bind_addr.clone())?.wait().await?;
+        let bind_addr = (bind_addr.0, bound_port.unwrap_or(bind_addr.1));
+        Ok(RemoteForward { client, config, bind_addr, connect_addr, shared: Arc::default(), bound: true })
+    }
+
+    /// Get the address that the SSH server listens on.
+    pub fn bind_addr(&self) -> &(String, u16) {
+        &self.bind_addr
+    }
+
+    /// Get the address that we connect to.
+    pub fn connect_addr(&self) -> &(String, u16) {
+        &self.connect_addr
+    }
+
+    /// Get a [`ForwardHandle`] to this forwarder.
+    pub fn handle(&self) -> ForwardHandle {
+        ForwardHandle { shared: self.shared.clone() }
+    }
+
+    /// Returns true if the tunnel was opened for the address that this forwarder is bound to.
+    pub fn matches(&self, accept: &AcceptTunnel) -> bool {
+        accept.connected_addr == self.bind_addr
+    }
+
+    /// Accept a tunnel opened by the server.
+    ///
+    /// You should check that the tunnel belongs to this forwarder using
+    /// [`matches()`][Self::matches()] first. Returns the connection that you should run, or
+    /// `None` if the forwarder was shut down using [`ForwardHandle::shutdown()`] (in this case, we
+    /// reject the tunnel).
+    pub fn accept(&self, accept: AcceptTunnel) -> Option<RemoteForwardConn> {
+        if self.shared.shutdown.load(Ordering::SeqCst) {
+            accept.reject_prohibited();
+            return None
+        }
+
+        Some(RemoteForwardConn {
+            accept,
+            config: self.config.clone(),
+            connect_addr: self.connect_addr.clone(),
+            active: self.shared.start_conn(),
+        })
+    }
+
+    /// Ask the server to stop listening and wait for the response.
+    ///
+    /// Connections that are already running are not interrupted.
+    pub async fn unbind(mut self) -> Result<()> {
+        self.bound = false;
+        self.client.unbind_tunnel(self.bind_addr.clone())?.wait().await
+    }
+}
+
+impl Drop for RemoteForward {
+    fn drop(&mut self) {
+        if self.bound {
+            if let Ok(resp) = self.client.unbind_tunnel(self.bind_addr.clone()) {
+                resp.ignore();
+            }
+        }
+    }
+}
+
+/// A connection accepted by [`RemoteForward`].
+pub struct RemoteForwardConn {
+    accept: AcceptTunnel,
+    config: ChannelConfig,
+    connect_addr: (String, u16),
+    active: ActiveConn,
+}
+
+impl RemoteForwardConn {
+    /// Get the address of the remote peer that connected to the SSH server.
+    pub fn originator_addr(&self) -> &(String, u16) {
+        &self.accept.originator_addr
+    }
+
+    /// Handle the connection.
+    ///
+    /// We connect to the target address and then accept the tunnel and copy the data in both
+    /// directions until both sides are closed. If we cannot connect, we reject the tunnel.
+    pub async fn run(self) -> Result<()> {
+        let Self { accept, config, connect_addr, active } = self;
+        active.run(async move {
+            let mut stream = match TcpStream::connect((connect_addr.0.as_str(), connect_addr.1)).await {
+                Ok(stream) => stream,
+                Err(err) => {
+                    accept.reject(ChannelOpenError {
+                        reason_code: codes::open::CONNECT_FAILED,
+                        description: format!("could not connect to {}:{}", connect_addr.0, connect_addr.1),
+                        description_lang: "".into(),
+                    });
+                    return Err(Error::ForwardIo(err))
+                },
+            };
+
+            let (tunnel, tunnel_rx) = accept.accept(config).await?;
+            let mut tunnel_stream = TunnelStream::new(tunnel, tunnel_rx);
+            splice(&mut tunnel_stream, &mut stream).await
+        }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ClientConfig;
+
+    #[tokio::test]
+    async fn test_local_forward_shutdown() {
+        let (client, _client_rx, _client_fut) = Client::open(tokio::io::empty(), ClientConfig::default()).unwrap();
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let forward = LocalForward::new(client, ChannelConfig::default(), listener, ("localhost".into(), 80));
+        let handle = forward.handle();
+
+        let local_addr = forward.local_addr().unwrap();
+        let _stream = TcpStream::connect(local_addr).await.unwrap();
+        let conn = forward.accept().await.unwrap().unwrap();
+        assert_eq!(handle.stats().connections, 1);
+        assert_eq!(handle.stats().active_connections, 1);
+
+        handle.shutdown();
+        assert!(handle.is_shutdown());
+        assert!(forward.accept().await.unwrap().is_none());
+
+        let wait_idle = tokio::spawn({
+            let handle = handle.clone();
+            async move { handle.wait_idle().await }
+        });
+        drop(conn);
+        wait_idle.await.unwrap();
+        assert_eq!(handle.stats().active_connections, 0);
+    }
+}
@@ -1,12 +1,12 @@
 //! Support for OpenSSH-compatible `known_hosts` file.
 
-use base64::Engine as _;
 use bytes::{Bytes, BytesMut};
 use hmac::Mac as _;
 use rand::RngCore as _;
 use std::str;
-use crate::pubkey::Pubkey;
-use crate::util::base64_encode;
+use std::time::SystemTime;
+use crate::pubkey::{Pubkey, Certificate, CertType};
+use crate::util::{base64_encode, base64_decode};
 
 /// Representation of an OpenSSH-compatible `known_hosts` file.
 ///
@@ -19,6 +19,11 @@ use crate::util::base64_encode;
 /// [`match_hostname_key()`][Self::match_hostname_key()]/[`match_host_port_key()`][Self::match_host_port_key()]
 /// to lookup the entries that either accept or revoke a given combination of host and key.
 ///
+/// Entries marked with `@cert-authority` store the keys of certificate authorities (CAs) that are
+/// trusted to sign host certificates. You can use
+/// [`match_hostname_cert()`][Self::match_hostname_cert()]/[`match_host_port_cert()`][Self::match_host_port_cert()]
+/// to validate a host [`Certificate`] against these entries.
+///
 /// You can also append new entries to the file using [`append_entry()`][Self::append_entry()]. In
 /// this way, it is possible to update the `known_hosts` file with new keys, without touching the
 /// previous entries (all previous lines will be preserved verbatim, including comments and invalid
@@ -63,6 +68,7 @@ pub struct Entry {
 #[derive(Debug, Clone)]
 pub struct EntryBuilder {
     is_revoked: bool,
+    is_cert_authority: bool,
     hashed_hostnames: Vec<String>,
     plaintext_hostnames: Vec<String>,
     keys: Vec<Pubkey>,
@@ -119,6 +125,32 @@ pub enum KeyMatch<'e> {
     NotFound,
 }
 
+/// A match returned by [`File::match_hostname_cert()`].
+#[derive(Debug, Clone)]
+pub enum CertMatch<'e> {
+    /// The certificate is valid and it was signed by a CA that is trusted for this hostname.
+    ///
+    /// The `Vec` lists the `@cert-authority` entries that match the hostname and the key of the
+    /// CA, it is always non-empty.
+    Accepted(Vec<&'e Entry>),
+
+    /// The key of the host or the key of the CA was revoked.
+    ///
+    /// The `Entry` is the first revoked entry in the file that matches the hostname and one of
+    /// these keys.
+    Revoked(&'e Entry),
+
+    /// The certificate was signed by a CA that is trusted for this hostname, but the certificate
+    /// is not valid.
+    ///
+    /// The `Vec` lists the `@cert-authority` entries that match the hostname and the key of the
+    /// CA (it is always non-empty), and the `&str` describes why the certificate is not valid.
+    Invalid(Vec<&'e Entry>, &'static str),
+
+    /// No `@cert-authority` entry that matches this hostname and the key of the CA was found.
+    NotFound,
+}
+
 impl File {
     /// Parses a file in OpenSSH `known_hosts` format.
     ///
@@ -155,7 +187,7 @@ impl File {
         let mut other_keys = Vec::new();
 
         for entry in self.entries() {
-            if !entry.matches_hostname(hostname) {
+            if !entry.matches_hostname(hostname) || entry.is_cert_authority() {
                 continue
             }
 
@@ -188,6 +220,58 @@ impl File {
         self.match_hostname_key(&host_port_to_hostname(host, port), pubkey)
     }
 
+    /// Finds the match for the given hostname and host certificate in this file.
+    ///
+    /// See [`host_port_to_hostname()`] for the format of the `hostname`; you can use
+    /// [`match_host_port_cert()`][Self::match_host_port_cert()] to match a `(host, port)` pair.
+    ///
+    /// The certificate is accepted if it was signed by a CA from a `@cert-authority` entry that
+    /// matches the `hostname`, and if it is valid: it must be a host certificate with a valid
+    /// signature, the host must be listed in its principals (or the list of principals must be
+    /// empty), the current time must be in its validity window and it must not contain any
+    /// critical options. The certificate is rejected if the key of the host or the key of the CA
+    /// has been revoked using a `@revoked` entry.
+    pub fn match_hostname_cert(&self, hostname: &str, cert: &Certificate) -> CertMatch<'_> {
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        self.match_hostname_cert_at(hostname, cert, now)
+    }
+
+    /// Finds the match for the given host, port and host certificate in this file.
+    ///
+    /// Same as [`match_hostname_cert()`][Self::match_hostname_cert()], but formats the host and
+    /// port using [`host_port_to_hostname()`].
+    pub fn match_host_port_cert(&self, host: &str, port: u16, cert: &Certificate) -> CertMatch<'_> {
+        self.match_hostname_cert(&host_port_to_hostname(host, port), cert)
+    }
+
+    fn match_hostname_cert_at(&self, hostname: &str, cert: &Certificate, now: u64) -> CertMatch<'_> {
+        let mut authorities = Vec::new();
+        for entry in self.entries() {
+            if !entry.matches_hostname(hostname) {
+                continue
+            }
+
+            if entry.is_revoked() {
+                if entry.pubkey() == &cert.pubkey || entry.pubkey() == &cert.signature_key {
+                    return CertMatch::Revoked(entry)
+                }
+            } else if entry.is_cert_authority() && entry.pubkey() == &cert.signature_key {
+                authorities.push(entry);
+            }
+        }
+
+        if authorities.is_empty() {
+            return CertMatch::NotFound
+        }
+
+        match validate_host_cert(hostname, cert, now) {
+            Ok(()) => CertMatch::Accepted(authorities),
+            Err(reason) => CertMatch::Invalid(authorities, reason),
+        }
+    }
+
     /// Creates an [`EntryBuilder`], which can be used to add an entry (or a set of entries) to the
     /// file (see [`append_entry()`][Self::append_entry()]).
     pub fn entry_builder() -> EntryBuilder {
@@ -246,6 +330,15 @@ impl Entry {
         self.line_i + 1
     }
 
+    /// Is this entry marked with the `@cert-authority` marker?
+    ///
+    /// This signifies that the public key from this entry is a certificate authority (CA), which
+    /// is trusted to sign host certificates for the host (see [`File::match_hostname_cert()`]).
+    /// The key itself should not be accepted as a host key.
+    pub fn is_cert_authority(&self) -> bool {
+        matches!(self.marker, Some(Marker::CertAuthority))
+    }
+
     /// Has this entry been revoked using the `@revoked` marker?
     ///
     /// This signifies that the public key from this entry has been revoked for the host, so
@@ -274,6 +367,7 @@ impl EntryBuilder {
     fn new() -> Self {
         EntryBuilder {
             is_revoked: false,
+            is_cert_authority: false,
             hashed_hostnames: Vec::new(),
             plaintext_hostnames: Vec::new(),
             keys: Vec::new(),
@@ -287,6 +381,14 @@ impl EntryBuilder {
         self.is_revoked = true; self
     }
 
+    /// Marks the entries as certificate authorities (using `@cert-authority`).
+    ///
+    /// This means that the given keys are trusted to sign host certificates for the given
+    /// hostnames.
+    pub fn cert_authority(&mut self) -> &mut Self {
+        self.is_cert_authority = true; self
+    }
+
     /// Adds a given hostname in plaintext.
     ///
     /// See [`host_port_to_hostname()`] for the format of the `hostname`; you can use
@@ -337,7 +439,10 @@ impl EntryBuilder {
     }
 
     fn build(&self, mut line_i: usize) -> Vec<Entry> {
-        let marker = if self.is_revoked { Some(Marker::Revoked) } else { None };
+        let marker =
+            if self.is_revoked { Some(Marker::Revoked) }
+            else if self.is_cert_authority { Some(Marker::CertAuthority) }
+            else { None };
 
         let mut entries = Vec::new();
         for key in self.keys.iter() {
@@ -369,6 +474,38 @@ impl EntryBuilder {
     }
 }
 
+fn validate_host_cert(hostname: &str, cert: &Certificate, now: u64) -> Result<(), &'static str> {
+    if cert.cert_type != CertType::Host {
+        return Err("certificate is not a host certificate")
+    }
+
+    if cert.verify_signature().is_err() {
+        return Err("certificate signature is not valid")
+    }
+
+    // the principals contain the host without the port
+    let host = hostname.strip_prefix('[')
+        .and_then(|hostname| hostname.rsplit_once("]:"))
+        .map(|(host, _port)| host)
+        .unwrap_or(hostname);
+    if !cert.valid_principals.is_empty() && !cert.valid_principals.iter().any(|p| p == host) {
+        return Err("host is not listed as a principal in the certificate")
+    }
+
+    if now < cert.valid_after {
+        return Err("certificate is not yet valid")
+    } else if now >= cert.valid_before {
+        return Err("certificate has expired")
+    }
+
+    // no critical options are defined for host certificates
+    if !cert.critical_options.is_empty() {
+        return Err("certificate contains unsupported critical options")
+    }
+
+    Ok(())
+}
+
 fn pattern_matches(pattern: &Pattern, hostname: &str) -> bool {
     match pattern {
         Pattern::Hashed(pattern) => {
@@ -622,13 +759,6 @@ fn consume_whitespace(bytes: &mut &[u8]) {
     *bytes = &bytes[white_len..];
 }
 
-fn base64_decode(mut data_base64: &[u8]) -> Result<Vec<u8>, base64::DecodeError> {
-    while data_base64.last() == Some(&b'=') {
-        data_base64 = &data_base64[..data_base64.len() - 1];
-    }
-    base64::engine::general_purpose::STANDARD_NO_PAD.decode(data_base64)
-}
-
 #[cfg(test)]
 mod tests {
     use hex_literal::hex;
@@ -1037,6 +1167,84 @@ mod tests {
         assert_eq!(bytes2, bytes4);
     }
 
+    #[test]
+    fn test_cert_authority() {
+        let file = File::decode(concat!(
+            // line 1
+            "@cert-authority *.example.com ssh-ed25519 ",
+                "AAAAC3NzaC1lZDI1NTE5AAAAIAklXWCTvkbJ2y9Ib9CpRvIfVykSdgOBHiDC/dv1hZKz alice\n",
+            // line 2
+            "@revoked revoked.example.com ssh-ed25519 ",
+                "AAAAC3NzaC1lZDI1NTE5AAAAIPJUmxF+H42aRAqDYOHqs9Wh2JDecL51WgYygy1hxswl edward\n",
+            // line 3
+            "@cert-authority [*.example.net]:2222 ssh-ed25519 ",
+                "AAAAC3NzaC1lZDI1NTE5AAAAIAklXWCTvkbJ2y9Ib9CpRvIfVykSdgOBHiDC/dv1hZKz alice\n",
+        ).into());
+
+        let alice = keys::alice_ed25519();
+        let edward = keys::edward_ed25519();
+
+        let make_cert = |principals: &[&str], cert_type, ca_privkey: &crate::Privkey| {
+            let mut cert = Certificate {
+                nonce: Bytes::from_static(b"nonce"),
+                pubkey: edward.pubkey(),
+                serial: 1,
+                cert_type,
+                key_id: "edward host".into(),
+                valid_principals: principals.iter().map(|&p| p.into()).collect(),
+                valid_after: 1000,
+                valid_before: 2000,
+                critical_options: Vec::new(),
+                extensions: Vec::new(),
+                signature_key: ca_privkey.pubkey(),
+                signature: Bytes::new(),
+                raw: None,
+            };
+            cert.sign(ca_privkey, &crate::pubkey::SSH_ED25519).unwrap();
+            cert
+        };
+
+        let check = |hostname: &str, cert: &Certificate, now: u64, expected: &str| {
+            let res = file.match_hostname_cert_at(hostname, cert, now);
+            match (&res, expected) {
+                (CertMatch::Accepted(entries), "accepted") => assert_eq!(entries.len(), 1),
+                (CertMatch::Revoked(entry), "revoked") => assert_eq!(entry.line(), 2),
+                (CertMatch::Invalid(entries, _), "invalid") => assert_eq!(entries.len(), 1),
+                (CertMatch::NotFound, "not found") => {},
+                _ => panic!("expected {}, got {:?}", expected, res),
+            }
+        };
+
+        let cert = make_cert(&["www.example.com"], CertType::Host, &alice);
+        check("www.example.com", &cert, 1500, "accepted");
+        check(&host_port_to_hostname("www.example.com", 2222), &cert, 1500, "not found");
+        check("mail.example.com", &cert, 1500, "invalid");
+        check("www.example.org", &cert, 1500, "not found");
+        check("www.example.com", &cert, 999, "invalid");
+        check("www.example.com", &cert, 2000, "invalid");
+
+        let cert = make_cert(&["www.example.net"], CertType::Host, &alice);
+        check(&host_port_to_hostname("www.example.net", 2222), &cert, 1500, "accepted");
+        check(&host_port_to_hostname("mail.example.net", 2222), &cert, 1500, "invalid");
+
+        let cert = make_cert(&[], CertType::Host, &alice);
+        check("mail.example.com", &cert, 1500, "accepted");
+        check("revoked.example.com", &cert, 1500, "revoked");
+
+        let cert = make_cert(&["www.example.com"], CertType::User, &alice);
+        check("www.example.com", &cert, 1500, "invalid");
+
+        let mut cert = make_cert(&["www.example.com"], CertType::Host, &alice);
+        cert.serial = 2;
+        check("www.example.com", &cert, 1500, "invalid");
+
+        let cert = make_cert(&["www.example.com"], CertType::Host, &edward);
+        check("www.example.com", &cert, 1500, "not found");
+
+        // the key of the CA must not be accepted as a host key
+        check_not_found(&file, "www.example.com", 22, &alice.pubkey());
+    }
+
     #[allow(dead_code)]
     mod keys {
         mod makiko {
@@ -6,12 +6,12 @@ use crate::codec::{PacketDecode, PacketEncode};
 use crate::codes::msg;
 use crate::error::{Error, Result};
 use crate::util::CryptoRngCore;
-use super::{KexAlgo, KexInput, KexOutput, Kex};
+use super::{KexAlgo, KexInput, KexOutput, Kex, SharedSecret};
 
 /// "curve25519-sha256" key exchange from RFC 8731.
 pub static CURVE25519_SHA256: KexAlgo = KexAlgo {
     name: "curve25519-sha256",
-    make_kex: |rng| Ok(Box::new(init_kex(rng)?)),
+    make_kex: |rng, _| Ok(Box::new(init_kex(rng)?)),
 };
 
 /// "curve25519-sha256@libssh.org" key exchange (same as ["curve25519-sha256"][CURVE25519_SHA256]).
@@ -21,7 +21,7 @@ pub static CURVE25519_SHA256: KexAlgo = KexAlgo {
 /// "@" suffix).
 pub static CURVE25519_SHA256_LIBSSH: KexAlgo = KexAlgo {
     name: "curve25519-sha256@libssh.org",
-    make_kex: |rng| Ok(Box::new(init_kex(rng)?)),
+    make_kex: |rng, _| Ok(Box::new(init_kex(rng)?)),
 };
 
 
@@ -129,6 +129,7 @@ fn exchange(kex: &mut Curve25519Kex, input: KexInput) -> Result<KexOutput> {
     exchange_data.put_biguint(&shared_secret);
     let exchange_hash = compute_hash(&exchange_data.finish());
 
+    let shared_secret = SharedSecret::Mpint(shared_secret);
     Ok(KexOutput { shared_secret, exchange_hash, server_pubkey, server_exchange_hash_sign })
 }
 
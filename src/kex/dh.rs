@@ -7,30 +7,30 @@ use crate::codec::{PacketDecode, PacketEncode};
 use crate::codes::msg;
 use crate::error::{Error, Result};
 use crate::util::CryptoRngCore;
-use super::{KexAlgo, KexInput, KexOutput, Kex};
+use super::{KexAlgo, KexInput, KexOutput, Kex, SharedSecret};
 
 /// "diffie-hellman-group14-sha1" key exchange from RFC 4253.
 pub static DIFFIE_HELLMAN_GROUP14_SHA1: KexAlgo = KexAlgo {
     name: "diffie-hellman-group14-sha1",
-    make_kex: |rng| Ok(Box::new(init_kex(Group::group_14(), compute_hash_sha1, rng)?)),
+    make_kex: |rng, _| Ok(Box::new(init_kex(Group::group_14(), compute_hash_sha1, rng)?)),
 };
 
 /// "diffie-hellman-group14-sha256" key exchange from RFC 8268.
 pub static DIFFIE_HELLMAN_GROUP14_SHA256: KexAlgo = KexAlgo {
     name: "diffie-hellman-group14-sha256",
-    make_kex: |rng| Ok(Box::new(init_kex(Group::group_14(), compute_hash_sha256, rng)?)),
+    make_kex: |rng, _| Ok(Box::new(init_kex(Group::group_14(), compute_hash_sha256, rng)?)),
 };
 
 /// "diffie-hellman-group16-sha512" key exchange from RFC 8268.
 pub static DIFFIE_HELLMAN_GROUP16_SHA512: KexAlgo = KexAlgo {
     name: "diffie-hellman-group16-sha512",
-    make_kex: |rng| Ok(Box::new(init_kex(Group::group_16(), compute_hash_sha512, rng)?)),
+    make_kex: |rng, _| Ok(Box::new(init_kex(Group::group_16(), compute_hash_sha512, rng)?)),
 };
 
 /// "diffie-hellman-group18-sha512" key exchange from RFC 8268.
 pub static DIFFIE_HELLMAN_GROUP18_SHA512: KexAlgo = KexAlgo {
     name: "diffie-hellman-group18-sha512",
-    make_kex: |rng| Ok(Box::new(init_kex(Group::group_18(), compute_hash_sha512, rng)?)),
+    make_kex: |rng, _| Ok(Box::new(init_kex(Group::group_18(), compute_hash_sha512, rng)?)),
 };
 
 /// "diffie-hellman-group1-sha1" key exchange from RFC 4253, which SHOULD NOT be implemented
@@ -40,7 +40,7 @@ pub static DIFFIE_HELLMAN_GROUP18_SHA512: KexAlgo = KexAlgo {
 #[cfg(feature = "insecure-crypto")]
 pub static DIFFIE_HELLMAN_GROUP1_SHA1: KexAlgo = KexAlgo {
     name: "diffie-hellman-group1-sha1",
-    make_kex: |rng| Ok(Box::new(init_kex(Group::group_2(), compute_hash_sha1, rng)?)),
+    make_kex: |rng, _| Ok(Box::new(init_kex(Group::group_2(), compute_hash_sha1, rng)?)),
 };
 
 
@@ -162,6 +162,7 @@ fn exchange(kex: &mut DiffieHellmanKex, input: KexInput) -> Result<KexOutput> {
     exchange_data.put_biguint(&shared_secret);
     let exchange_hash = (kex.compute_hash)(&exchange_data.finish());
 
+    let shared_secret = SharedSecret::Mpint(shared_secret);
     Ok(KexOutput { shared_secret, exchange_hash, server_pubkey, server_exchange_hash_sign })
 }
 
@@ -180,6 +181,12 @@ fn compute_hash_sha512(data: &[u8]) -> Vec<u8> {
     sha2::Sha512::digest(data).to_vec()
 }
 
+// the groups from RFC 3526 that OpenSSH servers also use for the group exchange when they don't
+// have a moduli file
+pub(super) fn is_well_known_prime(p: &BigUint) -> bool {
+    [Group::group_14(), Group::group_16(), Group::group_18()].iter().any(|group| &group.p == p)
+}
+
 impl Group {
     #[cfg(feature = "insecure-crypto")]
     fn group_2() -> Group {
@@ -0,0 +1,371 @@
+use bytes::Bytes;
+use derivative::Derivative;
+use num_bigint_dig::{BigUint, RandBigInt as _};
+use rand::SeedableRng as _;
+use std::task::Poll;
+use crate::codec::{PacketDecode, PacketEncode};
+use crate::codes::msg;
+use crate::error::{Error, Result};
+use crate::util::CryptoRngCore;
+use super::{KexAlgo, KexInput, KexOutput, Kex, SharedSecret};
+use super::dh;
+
+/// "diffie-hellman-group-exchange-sha256" key exchange from RFC 4419.
+///
+/// The server chooses the group, based on the sizes in [`DhGexConfig`].
+pub static DIFFIE_HELLMAN_GROUP_EXCHANGE_SHA256: KexAlgo = KexAlgo {
+    name: "diffie-hellman-group-exchange-sha256",
+    make_kex: |rng, dh_gex| Ok(Box::new(init_kex(dh_gex, compute_hash_sha256, rng)?)),
+};
+
+/// "diffie-hellman-group-exchange-sha1" key exchange from RFC 4419.
+///
+/// The server chooses the group, based on the sizes in [`DhGexConfig`].
+pub static DIFFIE_HELLMAN_GROUP_EXCHANGE_SHA1: KexAlgo = KexAlgo {
+    name: "diffie-hellman-group-exchange-sha1",
+    make_kex: |rng, dh_gex| Ok(Box::new(init_kex(dh_gex, compute_hash_sha1, rng)?)),
+};
+
+/// Configuration of the Diffie-Hellman group exchange (RFC 4419).
+///
+/// The client sends the minimal, preferred and maximal size of the group (in bits) to the server,
+/// and the server then chooses a group of an appropriate size. We abort the key exchange if the
+/// server sends a group that is outside of the range between [`min_bits`][Self::min_bits] and
+/// [`max_bits`][Self::max_bits].
+///
+/// You should start from the [default][Default] instance, which uses the sizes recommended by RFC
+/// 8270, and modify it as needed. The sizes must satisfy `min_bits <= preferred_bits <= max_bits`,
+/// otherwise the key exchange fails with [`Error::Config`][crate::Error::Config].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct DhGexConfig {
+    /// Minimal size of the group in bits.
+    ///
+    /// By default, we use 2048 bits, as recommended by RFC 8270.
+    pub min_bits: u32,
+
+    /// Preferred size of the group in bits.
+    ///
+    /// By default, we use 3072 bits.
+    pub preferred_bits: u32,
+
+    /// Maximal size of the group in bits.
+    ///
+    /// By default, we use 8192 bits, which is the maximum supported by RFC 4419.
+    pub max_bits: u32,
+
+    /// Check that the group sent by the server uses a safe prime (RFC 4419, section 6).
+    ///
+    /// The primality test of a large number is expensive (it may take seconds for an 8192-bit
+    /// prime) and it blocks the [`ClientFuture`][crate::ClientFuture] while it runs. We skip the
+    /// test for the well-known groups from RFC 3526.
+    ///
+    /// By default, this is `true`.
+    pub check_safe_prime: bool,
+}
+
+impl Default for DhGexConfig {
+    fn default() -> Self {
+        DhGexConfig {
+            min_bits: 2048,
+            preferred_bits: 3072,
+            max_bits: 8192,
+            check_safe_prime: true,
+        }
+    }
+}
+
+impl DhGexConfig {
+    /// Update the configuration in pseudo-builder pattern style.
+    ///
+    /// This method applies your closure to `self` and returns the mutated configuration.
+    pub fn with<F: FnOnce(&mut Self)>(mut self, f: F) -> Self {
+        f(&mut self);
+        self
+    }
+}
+
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct DhGexKex {
+    config: DhGexConfig,
+    #[derivative(Debug = "ignore")]
+    compute_hash: fn(&[u8]) -> Vec<u8>,
+    #[derivative(Debug = "ignore")]
+    rng: rand_chacha::ChaCha20Rng,
+    request_sent: bool,
+    group: Option<Group>,
+    our_eph: Option<(BigUint, BigUint)>,
+    init_sent: bool,
+    reply: Option<GexReply>,
+}
+
+#[derive(Debug)]
+struct Group {
+    p: BigUint,
+    g: BigUint,
+    p_minus_1: BigUint,
+}
+
+#[derive(Debug)]
+struct GexReply {
+    server_pubkey: Bytes,
+    server_eph_pubkey: BigUint,
+    server_exchange_hash_sign: Bytes,
+}
+
+fn init_kex(
+    config: &DhGexConfig,
+    compute_hash: fn(&[u8]) -> Vec<u8>,
+    rng: &mut dyn CryptoRngCore,
+) -> Result<DhGexKex> {
+    if !(config.min_bits <= config.preferred_bits && config.preferred_bits <= config.max_bits) {
+        return Err(Error::Config("DhGexConfig must satisfy min_bits <= preferred_bits <= max_bits"))
+    }
+
+    // we need to generate the private key only after we receive the group from the server, so we
+    // seed a generator that we will use later
+    let rng = rand_chacha::ChaCha20Rng::from_rng(rng.as_rngcore())
+        .map_err(|_| Error::Crypto("could not seed random number generator"))?;
+    Ok(DhGexKex {
+        config: config.clone(),
+        compute_hash,
+        rng,
+        request_sent: false,
+        group: None,
+        our_eph: None,
+        init_sent: false,
+        reply: None,
+    })
+}
+
+impl Kex for DhGexKex {
+    fn recv_packet(&mut self, msg_id: u8, payload: &mut PacketDecode) -> Result<()> {
+        match msg_id {
+            msg::KEX_DH_GEX_GROUP => recv_gex_group(self, payload),
+            msg::KEX_DH_GEX_REPLY => recv_gex_reply(self, payload),
+            _ => Err(Error::PacketNotImplemented(msg_id)),
+        }
+    }
+
+    fn send_packet(&mut self) -> Result<Option<Bytes>> {
+        if !self.request_sent {
+            let payload = send_gex_request(self)?;
+            self.request_sent = true;
+            return Ok(Some(payload))
+        }
+
+        if self.group.is_some() && !self.init_sent {
+            let payload = send_gex_init(self)?;
+            self.init_sent = true;
+            return Ok(Some(payload))
+        }
+
+        Ok(None)
+    }
+
+    fn poll(&mut self, input: KexInput) -> Poll<Result<KexOutput>> {
+        if self.reply.is_some() {
+            return Poll::Ready(exchange(self, input))
+        }
+        Poll::Pending
+    }
+
+    fn compute_hash(&self, data: &[u8]) -> Vec<u8> {
+        (self.compute_hash)(data)
+    }
+}
+
+fn send_gex_request(kex: &mut DhGexKex) -> Result<Bytes> {
+    // RFC 4419, section 3
+    let mut payload = PacketEncode::new();
+    payload.put_u8(msg::KEX_DH_GEX_REQUEST);
+    payload.put_u32(kex.config.min_bits);
+    payload.put_u32(kex.config.preferred_bits);
+    payload.put_u32(kex.config.max_bits);
+    log::debug!("sending SSH_MSG_KEX_DH_GEX_REQUEST: min {}, n {}, max {}",
+        kex.config.min_bits, kex.config.preferred_bits, kex.config.max_bits);
+    Ok(payload.finish())
+}
+
+fn recv_gex_group(kex: &mut DhGexKex, payload: &mut PacketDecode) -> Result<()> {
+    if !kex.request_sent || kex.group.is_some() {
+        return Err(Error::Protocol("received unexpected SSH_MSG_KEX_DH_GEX_GROUP"))
+    }
+
+    // RFC 4419, section 3
+    let p = payload.get_biguint()?;
+    let g = payload.get_biguint()?;
+    log::debug!("received SSH_MSG_KEX_DH_GEX_GROUP with {}-bit prime", p.bits());
+
+    let group = validate_group(&kex.config, p, g)?;
+    let q = &group.p_minus_1 >> 1;
+    let our_eph_privkey = kex.rng.gen_biguint_range(&BigUint::from(2u32), &q);
+    let our_eph_pubkey = (group.g).modpow(&our_eph_privkey, &group.p);
+    kex.our_eph = Some((our_eph_privkey, our_eph_pubkey));
+    kex.group = Some(group);
+    Ok(())
+}
+
+fn validate_group(config: &DhGexConfig, p: BigUint, g: BigUint) -> Result<Group> {
+    let bits = p.bits() as u32;
+    if bits < config.min_bits || bits > config.max_bits {
+        log::debug!("server sent {}-bit group, but we requested {} to {} bits",
+            bits, config.min_bits, config.max_bits);
+        return Err(Error::Protocol("server sent Diffie-Hellman group of unacceptable size"))
+    }
+
+    let one = BigUint::from(1u32);
+    let p_minus_1 = &p - &one;
+    if g <= one || g >= p_minus_1 {
+        return Err(Error::Protocol("server sent invalid Diffie-Hellman group generator"))
+    }
+
+    // the prime must be a safe prime, p = 2*q + 1 where q is also a prime (RFC 4419, section 6)
+    if config.check_safe_prime && !dh::is_well_known_prime(&p) {
+        let q = &p_minus_1 >> 1;
+        if !num_bigint_dig::prime::probably_prime(&p, SAFE_PRIME_ROUNDS) ||
+            !num_bigint_dig::prime::probably_prime(&q, SAFE_PRIME_ROUNDS)
+        {
+            return Err(Error::Protocol("server sent Diffie-Hellman group that is not a safe prime"))
+        }
+    }
+
+    Ok(Group { p, g, p_minus_1 })
+}
+
+// the primality test also includes a Lucas test, so a few Miller-Rabin rounds are enough
+const SAFE_PRIME_ROUNDS: usize = 4;
+
+fn send_gex_init(kex: &mut DhGexKex) -> Result<Bytes> {
+    // RFC 4419, section 3
+    let (_, our_eph_pubkey) = kex.our_eph.as_ref().unwrap();
+    let mut payload = PacketEncode::new();
+    payload.put_u8(msg::KEX_DH_GEX_INIT);
+    payload.put_biguint(our_eph_pubkey);
+    log::debug!("sending SSH_MSG_KEX_DH_GEX_INIT");
+    Ok(payload.finish())
+}
+
+fn recv_gex_reply(kex: &mut DhGexKex, payload: &mut PacketDecode) -> Result<()> {
+    if !kex.init_sent || kex.reply.is_some() {
+        return Err(Error::Protocol("received unexpected SSH_MSG_KEX_DH_GEX_REPLY"))
+    }
+
+    // RFC 4419, section 3
+    let server_pubkey = payload.get_bytes()?;
+    let server_eph_pubkey = payload.get_biguint()?;
+    let server_exchange_hash_sign = payload.get_bytes()?;
+
+    let group = kex.group.as_ref().unwrap();
+    if server_eph_pubkey <= BigUint::from(1u32) || server_eph_pubkey >= group.p_minus_1 {
+        return Err(Error::Protocol("server sent invalid Diffie-Hellman ephemeral public key"))
+    }
+
+    let server_pubkey = Bytes::copy_from_slice(&server_pubkey);
+    let server_exchange_hash_sign = Bytes::copy_from_slice(&server_exchange_hash_sign);
+    kex.reply = Some(GexReply { server_pubkey, server_eph_pubkey, server_exchange_hash_sign });
+    log::debug!("received SSH_MSG_KEX_DH_GEX_REPLY");
+
+    Ok(())
+}
+
+fn exchange(kex: &mut DhGexKex, input: KexInput) -> Result<KexOutput> {
+    let reply = kex.reply.take().unwrap();
+    let GexReply { server_pubkey, server_eph_pubkey, server_exchange_hash_sign } = reply;
+    let group = kex.group.as_ref().unwrap();
+    let (our_eph_privkey, our_eph_pubkey) = kex.our_eph.as_ref().unwrap();
+
+    let shared_secret = (server_eph_pubkey).modpow(our_eph_privkey, &group.p);
+
+    // RFC 4419, section 3
+    let mut exchange_data = PacketEncode::new();
+    exchange_data.put_bytes(input.client_ident);
+    exchange_data.put_bytes(input.server_ident);
+    exchange_data.put_bytes(input.client_kex_init);
+    exchange_data.put_bytes(input.server_kex_init);
+    exchange_data.put_bytes(&server_pubkey);
+    exchange_data.put_u32(kex.config.min_bits);
+    exchange_data.put_u32(kex.config.preferred_bits);
+    exchange_data.put_u32(kex.config.max_bits);
+    exchange_data.put_biguint(&group.p);
+    exchange_data.put_biguint(&group.g);
+    exchange_data.put_biguint(our_eph_pubkey);
+    exchange_data.put_biguint(&server_eph_pubkey);
+    exchange_data.put_biguint(&shared_secret);
+    let exchange_hash = (kex.compute_hash)(&exchange_data.finish());
+
+    let shared_secret = SharedSecret::Mpint(shared_secret);
+    Ok(KexOutput { shared_secret, exchange_hash, server_pubkey, server_exchange_hash_sign })
+}
+
+fn compute_hash_sha1(data: &[u8]) -> Vec<u8> {
+    use sha1::digest::Digest as _;
+    sha1::Sha1::digest(data).to_vec()
+}
+
+fn compute_hash_sha256(data: &[u8]) -> Vec<u8> {
+    use sha2::digest::Digest as _;
+    sha2::Sha256::digest(data).to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group_14_p() -> BigUint {
+        BigUint::parse_bytes(b"\
+            FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74020BBEA63B139B22514A0879\
+            8E3404DDEF9519B3CD3A431B302B0A6DF25F14374FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B\
+            0BFF5CB6F406B7EDEE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF0598DA4836\
+            1C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB9ED529077096966D670C354E4ABC9804\
+            F1746C08CA18217C32905E462E36CE3BE39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF6\
+            955817183995497CEA956AE515D2261898FA051015728E5A8AACAA68FFFFFFFFFFFFFFFF", 16).unwrap()
+    }
+
+    fn group_2_p() -> BigUint {
+        BigUint::parse_bytes(b"\
+            FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74020BBEA63B139B22514A0879\
+            8E3404DDEF9519B3CD3A431B302B0A6DF25F14374FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B\
+            0BFF5CB6F406B7EDEE386BFB5A899FA5AE9F24117C4B1FE649286651ECE65381FFFFFFFFFFFFFFFF", 16).unwrap()
+    }
+
+    #[test]
+    fn test_validate_group() {
+        let config = DhGexConfig::default();
+        let two = BigUint::from(2u32);
+        assert!(validate_group(&config, group_14_p(), two.clone()).is_ok());
+
+        let not_safe = group_14_p() + BigUint::from(2u32);
+        assert!(validate_group(&config, not_safe.clone(), two.clone()).is_err());
+        let unchecked_config = config.clone().with(|c| c.check_safe_prime = false);
+        assert!(validate_group(&unchecked_config, not_safe, two.clone()).is_ok());
+
+        // group 2 is not one of the well-known groups, so we really test that it is a safe prime
+        let group_2_config = config.clone().with(|c| c.min_bits = 1024);
+        assert!(validate_group(&group_2_config, group_2_p(), two.clone()).is_ok());
+
+        assert!(validate_group(&config, group_14_p(), BigUint::from(1u32)).is_err());
+        assert!(validate_group(&config, group_14_p(), group_14_p() - 1u32).is_err());
+
+        let small_config = config.clone().with(|c| c.min_bits = 3072);
+        assert!(validate_group(&small_config, group_14_p(), two.clone()).is_err());
+        let large_config = config.with(|c| c.max_bits = 1024);
+        assert!(validate_group(&large_config, group_14_p(), two).is_err());
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let mut rng = rand::rngs::OsRng;
+        let hash = |data: &[u8]| data.to_vec();
+        assert!(init_kex(&DhGexConfig::default(), hash, &mut rng).is_ok());
+
+        let config = DhGexConfig::default().with(|c| c.preferred_bits = 1024);
+        assert!(matches!(init_kex(&config, hash, &mut rng), Err(Error::Config(_))));
+        let config = DhGexConfig::default().with(|c| c.preferred_bits = 16384);
+        assert!(matches!(init_kex(&config, hash, &mut rng), Err(Error::Config(_))));
+        let config = DhGexConfig::default().with(|c| c.min_bits = 9000);
+        assert!(matches!(init_kex(&config, hash, &mut rng), Err(Error::Config(_))));
+    }
+}
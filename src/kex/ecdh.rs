@@ -0,0 +1,175 @@
+use bytes::Bytes;
+use ecdsa::elliptic_curve::{self, CurveArithmetic, FieldBytesSize};
+use ecdsa::elliptic_curve::sec1::{self, FromEncodedPoint, ToEncodedPoint};
+use num_bigint_dig::BigUint;
+use sha2::digest::Digest as _;
+use std::task::Poll;
+use crate::codec::{PacketDecode, PacketEncode};
+use crate::codes::msg;
+use crate::error::{Error, Result};
+use crate::util::CryptoRngCore;
+use super::{KexAlgo, KexInput, KexOutput, Kex, SharedSecret};
+
+/// "ecdh-sha2-nistp256" key exchange from RFC 5656.
+pub static ECDH_SHA2_NISTP256: KexAlgo = KexAlgo {
+    name: "ecdh-sha2-nistp256",
+    make_kex: |rng, _| Ok(Box::new(init_kex::<p256::NistP256>(compute_hash_sha256, rng)?)),
+};
+
+/// "ecdh-sha2-nistp384" key exchange from RFC 5656.
+pub static ECDH_SHA2_NISTP384: KexAlgo = KexAlgo {
+    name: "ecdh-sha2-nistp384",
+    make_kex: |rng, _| Ok(Box::new(init_kex::<p384::NistP384>(compute_hash_sha384, rng)?)),
+};
+
+/// "ecdh-sha2-nistp521" key exchange from RFC 5656.
+pub static ECDH_SHA2_NISTP521: KexAlgo = KexAlgo {
+    name: "ecdh-sha2-nistp521",
+    make_kex: |rng, _| Ok(Box::new(init_kex::<p521::NistP521>(compute_hash_sha512, rng)?)),
+};
+
+
+struct EcdhKex<C: CurveArithmetic> {
+    compute_hash: fn(&[u8]) -> Vec<u8>,
+    our_eph_privkey: Option<elliptic_curve::ecdh::EphemeralSecret<C>>,
+    our_eph_pubkey: Bytes,
+    ecdh_init_sent: bool,
+    ecdh_reply: Option<EcdhReply<C>>,
+}
+
+struct EcdhReply<C: CurveArithmetic> {
+    server_pubkey: Bytes,
+    server_eph_pubkey: elliptic_curve::PublicKey<C>,
+    server_eph_pubkey_bytes: Bytes,
+    server_exchange_hash_sign: Bytes,
+}
+
+fn init_kex<C>(
+    compute_hash: fn(&[u8]) -> Vec<u8>,
+    mut rng: &mut dyn CryptoRngCore,
+) -> Result<EcdhKex<C>>
+    where C: CurveArithmetic,
+          FieldBytesSize<C>: sec1::ModulusSize,
+          C::AffinePoint: FromEncodedPoint<C> + ToEncodedPoint<C>,
+{
+    let our_eph_privkey = elliptic_curve::ecdh::EphemeralSecret::<C>::random(&mut rng);
+    let our_eph_pubkey = our_eph_privkey.public_key().to_encoded_point(false);
+    let our_eph_pubkey = Bytes::copy_from_slice(our_eph_pubkey.as_bytes());
+    log::debug!("initialized ecdh kex");
+    Ok(EcdhKex {
+        compute_hash,
+        our_eph_privkey: Some(our_eph_privkey),
+        our_eph_pubkey,
+        ecdh_init_sent: false,
+        ecdh_reply: None,
+    })
+}
+
+impl<C> Kex for EcdhKex<C>
+    where C: CurveArithmetic,
+          FieldBytesSize<C>: sec1::ModulusSize,
+          C::AffinePoint: FromEncodedPoint<C> + ToEncodedPoint<C>,
+{
+    fn recv_packet(&mut self, msg_id: u8, payload: &mut PacketDecode) -> Result<()> {
+        match msg_id {
+            msg::KEX_ECDH_REPLY => recv_ecdh_reply(self, payload),
+            _ => Err(Error::PacketNotImplemented(msg_id)),
+        }
+    }
+
+    fn send_packet(&mut self) -> Result<Option<Bytes>> {
+        if !self.ecdh_init_sent {
+            let payload = send_ecdh_init(self)?;
+            self.ecdh_init_sent = true;
+            return Ok(Some(payload))
+        }
+        Ok(None)
+    }
+
+    fn poll(&mut self, input: KexInput) -> Poll<Result<KexOutput>> {
+        if self.our_eph_privkey.is_some() && self.ecdh_reply.is_some() {
+            return Poll::Ready(exchange(self, input))
+        }
+        Poll::Pending
+    }
+
+    fn compute_hash(&self, data: &[u8]) -> Vec<u8> {
+        (self.compute_hash)(data)
+    }
+}
+
+fn send_ecdh_init<C: CurveArithmetic>(kex: &mut EcdhKex<C>) -> Result<Bytes> {
+    // RFC 5656, section 4
+    let mut payload = PacketEncode::new();
+    payload.put_u8(msg::KEX_ECDH_INIT);
+    payload.put_bytes(&kex.our_eph_pubkey);
+    log::debug!("sending SSH_MSG_KEX_ECDH_INIT");
+    Ok(payload.finish())
+}
+
+fn recv_ecdh_reply<C>(kex: &mut EcdhKex<C>, payload: &mut PacketDecode) -> Result<()>
+    where C: CurveArithmetic,
+          FieldBytesSize<C>: sec1::ModulusSize,
+          C::AffinePoint: FromEncodedPoint<C> + ToEncodedPoint<C>,
+{
+    if kex.ecdh_reply.is_some() {
+        return Err(Error::Protocol("received duplicate SSH_MSG_KEX_ECDH_REPLY"))
+    }
+
+    // RFC 5656, section 4
+    let server_pubkey = payload.get_bytes()?;
+    let server_eph_pubkey_bytes = payload.get_bytes()?;
+    let server_exchange_hash_sign = payload.get_bytes()?;
+
+    // RFC 5656, section 4 requires us to verify that the point is valid (SEC1, section 3.2.2.1),
+    // which is done when decoding the public key
+    let server_eph_pubkey = elliptic_curve::PublicKey::<C>::from_sec1_bytes(&server_eph_pubkey_bytes)
+        .map_err(|_| Error::Protocol("server sent invalid ECDH ephemeral public key"))?;
+
+    let server_pubkey = Bytes::copy_from_slice(&server_pubkey);
+    let server_eph_pubkey_bytes = Bytes::copy_from_slice(&server_eph_pubkey_bytes);
+    let server_exchange_hash_sign = Bytes::copy_from_slice(&server_exchange_hash_sign);
+    kex.ecdh_reply = Some(EcdhReply {
+        server_pubkey, server_eph_pubkey, server_eph_pubkey_bytes, server_exchange_hash_sign,
+    });
+    log::debug!("received SSH_MSG_KEX_ECDH_REPLY");
+
+    Ok(())
+}
+
+fn exchange<C: CurveArithmetic>(kex: &mut EcdhKex<C>, input: KexInput) -> Result<KexOutput> {
+    let our_eph_privkey = kex.our_eph_privkey.take().unwrap();
+    let ecdh_reply = kex.ecdh_reply.take().unwrap();
+    let EcdhReply {
+        server_pubkey, server_eph_pubkey, server_eph_pubkey_bytes, server_exchange_hash_sign,
+    } = ecdh_reply;
+
+    let shared_secret = our_eph_privkey.diffie_hellman(&server_eph_pubkey);
+    let shared_secret = BigUint::from_bytes_be(shared_secret.raw_secret_bytes());
+
+    let mut exchange_data = PacketEncode::new();
+    exchange_data.put_bytes(input.client_ident);
+    exchange_data.put_bytes(input.server_ident);
+    exchange_data.put_bytes(input.client_kex_init);
+    exchange_data.put_bytes(input.server_kex_init);
+    exchange_data.put_bytes(&server_pubkey);
+    exchange_data.put_bytes(&kex.our_eph_pubkey);
+    exchange_data.put_bytes(&server_eph_pubkey_bytes);
+    exchange_data.put_biguint(&shared_secret);
+    let exchange_hash = (kex.compute_hash)(&exchange_data.finish());
+
+    let shared_secret = SharedSecret::Mpint(shared_secret);
+    Ok(KexOutput { shared_secret, exchange_hash, server_pubkey, server_exchange_hash_sign })
+}
+
+fn compute_hash_sha256(data: &[u8]) -> Vec<u8> {
+    sha2::Sha256::digest(data).to_vec()
+}
+
+fn compute_hash_sha384(data: &[u8]) -> Vec<u8> {
+    sha2::Sha384::digest(data).to_vec()
+}
+
+fn compute_hash_sha512(data: &[u8]) -> Vec<u8> {
+    sha2::Sha512::digest(data).to_vec()
+}
@@ -0,0 +1,249 @@
+use bytes::Bytes;
+use ml_kem::{EncodedSizeUser as _, KemCore as _};
+use ml_kem::kem::Decapsulate as _;
+use sha2::digest::Digest as _;
+use std::task::Poll;
+use crate::codec::{PacketDecode, PacketEncode};
+use crate::codes::msg;
+use crate::error::{Error, Result};
+use crate::util::CryptoRngCore;
+use super::{KexAlgo, KexInput, KexOutput, Kex, SharedSecret};
+
+/// "sntrup761x25519-sha512" hybrid post-quantum key exchange from
+/// draft-josefsson-ntruprime-ssh.
+///
+/// Combines the Streamlined NTRU Prime sntrup761 key encapsulation mechanism with X25519 key
+/// exchange, so the key exchange is secure as long as at least one of them is not broken.
+pub static SNTRUP761X25519_SHA512: KexAlgo = KexAlgo {
+    name: "sntrup761x25519-sha512",
+    make_kex: |rng, _| Ok(Box::new(init_kex(&SNTRUP761, rng)?)),
+};
+
+/// "sntrup761x25519-sha512@openssh.com" key exchange (same as
+/// ["sntrup761x25519-sha512"][SNTRUP761X25519_SHA512]).
+///
+/// This is the name that OpenSSH used before the key exchange method was standardized.
+pub static SNTRUP761X25519_SHA512_OPENSSH: KexAlgo = KexAlgo {
+    name: "sntrup761x25519-sha512@openssh.com",
+    make_kex: |rng, _| Ok(Box::new(init_kex(&SNTRUP761, rng)?)),
+};
+
+/// "mlkem768x25519-sha256" hybrid post-quantum key exchange from
+/// draft-ietf-sshm-mlkem-hybrid-kex.
+///
+/// Combines the ML-KEM-768 key encapsulation mechanism (FIPS 203) with X25519 key exchange, so
+/// the key exchange is secure as long as at least one of them is not broken.
+pub static MLKEM768X25519_SHA256: KexAlgo = KexAlgo {
+    name: "mlkem768x25519-sha256",
+    make_kex: |rng, _| Ok(Box::new(init_kex(&MLKEM768, rng)?)),
+};
+
+
+struct Kem {
+    name: &'static str,
+    ciphertext_len: usize,
+    keygen: fn(rng: &mut dyn CryptoRngCore) -> (Vec<u8>, Vec<u8>),
+    decaps: fn(privkey: &[u8], ciphertext: &[u8]) -> Option<[u8; 32]>,
+    compute_hash: fn(data: &[u8]) -> Vec<u8>,
+}
+
+static SNTRUP761: Kem = Kem {
+    name: "sntrup761",
+    ciphertext_len: sntrup761::CIPHERTEXT_SIZE,
+    keygen: |rng| {
+        // the `sntrup761` crate uses a different version of `rand`, so we pass the randomness as
+        // a seed
+        let mut seed = [0; 32];
+        rng.fill_bytes(&mut seed);
+        let (pubkey, privkey) = sntrup761::generate_key_from_seed(seed);
+        (pubkey.as_ref().to_vec(), privkey.as_ref().to_vec())
+    },
+    decaps: |privkey, ciphertext| {
+        let privkey = sntrup761::DecapsulationKey::try_from(privkey).ok()?;
+        let ciphertext = sntrup761::Ciphertext::try_from(ciphertext).ok()?;
+        privkey.decapsulate(&ciphertext).as_ref().try_into().ok()
+    },
+    compute_hash: |data| sha2::Sha512::digest(data).to_vec(),
+};
+
+static MLKEM768: Kem = Kem {
+    name: "mlkem768",
+    ciphertext_len: 1088, // FIPS 203, section 8
+    keygen: |mut rng| {
+        let (privkey, pubkey) = ml_kem::MlKem768::generate(&mut rng);
+        (pubkey.as_bytes().to_vec(), privkey.as_bytes().to_vec())
+    },
+    decaps: |privkey, ciphertext| {
+        type DecapsulationKey = <ml_kem::MlKem768 as ml_kem::KemCore>::DecapsulationKey;
+        let privkey = DecapsulationKey::from_bytes(privkey.try_into().ok()?);
+        let ciphertext = ciphertext.try_into().ok()?;
+        privkey.decapsulate(ciphertext).ok().map(|shared_secret| shared_secret.into())
+    },
+    compute_hash: |data| sha2::Sha256::digest(data).to_vec(),
+};
+
+struct HybridKex {
+    kem: &'static Kem,
+    our_kem_privkey: Vec<u8>,
+    our_eph_privkey: Option<x25519_dalek::EphemeralSecret>,
+    our_eph_pubkey: Bytes,
+    ecdh_init_sent: bool,
+    ecdh_reply: Option<EcdhReply>,
+}
+
+#[derive(Debug)]
+struct EcdhReply {
+    server_pubkey: Bytes,
+    server_eph_pubkey: Bytes,
+    server_exchange_hash_sign: Bytes,
+}
+
+fn init_kex(kem: &'static Kem, rng: &mut dyn CryptoRngCore) -> Result<HybridKex> {
+    let (our_kem_pubkey, our_kem_privkey) = (kem.keygen)(rng);
+
+    // see `curve25519.rs` for the reasons why we use `OsRng` here
+    let our_eph_privkey = x25519_dalek::EphemeralSecret::random_from_rng(rand::rngs::OsRng);
+    let our_x25519_pubkey = x25519_dalek::PublicKey::from(&our_eph_privkey);
+
+    let mut our_eph_pubkey = our_kem_pubkey;
+    our_eph_pubkey.extend_from_slice(our_x25519_pubkey.as_bytes());
+    log::debug!("initialized {}x25519 kex", kem.name);
+
+    Ok(HybridKex {
+        kem,
+        our_kem_privkey,
+        our_eph_privkey: Some(our_eph_privkey),
+        our_eph_pubkey: our_eph_pubkey.into(),
+        ecdh_init_sent: false,
+        ecdh_reply: None,
+    })
+}
+
+impl Kex for HybridKex {
+    fn recv_packet(&mut self, msg_id: u8, payload: &mut PacketDecode) -> Result<()> {
+        match msg_id {
+            msg::KEX_ECDH_REPLY => recv_ecdh_reply(self, payload),
+            _ => Err(Error::PacketNotImplemented(msg_id)),
+        }
+    }
+
+    fn send_packet(&mut self) -> Result<Option<Bytes>> {
+        if !self.ecdh_init_sent {
+            let payload = send_ecdh_init(self)?;
+            self.ecdh_init_sent = true;
+            return Ok(Some(payload))
+        }
+        Ok(None)
+    }
+
+    fn poll(&mut self, input: KexInput) -> Poll<Result<KexOutput>> {
+        if self.our_eph_privkey.is_some() && self.ecdh_reply.is_some() {
+            return Poll::Ready(exchange(self, input))
+        }
+        Poll::Pending
+    }
+
+    fn compute_hash(&self, data: &[u8]) -> Vec<u8> {
+        (self.kem.compute_hash)(data)
+    }
+}
+
+fn send_ecdh_init(kex: &mut HybridKex) -> Result<Bytes> {
+    // draft-josefsson-ntruprime-ssh, section 2 and draft-ietf-sshm-mlkem-hybrid-kex, section 2.1
+    let mut payload = PacketEncode::new();
+    payload.put_u8(msg::KEX_ECDH_INIT);
+    payload.put_bytes(&kex.our_eph_pubkey);
+    log::debug!("sending SSH_MSG_KEX_ECDH_INIT");
+    Ok(payload.finish())
+}
+
+fn recv_ecdh_reply(kex: &mut HybridKex, payload: &mut PacketDecode) -> Result<()> {
+    if kex.ecdh_reply.is_some() {
+        return Err(Error::Protocol("received duplicate SSH_MSG_KEX_ECDH_REPLY"))
+    }
+
+    let server_pubkey = payload.get_bytes()?;
+    let server_eph_pubkey = payload.get_bytes()?;
+    let server_exchange_hash_sign = payload.get_bytes()?;
+
+    if server_eph_pubkey.len() != kex.kem.ciphertext_len + 32 {
+        return Err(Error::Protocol("server sent hybrid ephemeral public key with invalid length"))
+    }
+
+    let server_pubkey = Bytes::copy_from_slice(&server_pubkey);
+    let server_eph_pubkey = Bytes::copy_from_slice(&server_eph_pubkey);
+    let server_exchange_hash_sign = Bytes::copy_from_slice(&server_exchange_hash_sign);
+    kex.ecdh_reply = Some(EcdhReply { server_pubkey, server_eph_pubkey, server_exchange_hash_sign });
+    log::debug!("received SSH_MSG_KEX_ECDH_REPLY");
+
+    Ok(())
+}
+
+fn exchange(kex: &mut HybridKex, input: KexInput) -> Result<KexOutput> {
+    let our_eph_privkey = kex.our_eph_privkey.take().unwrap();
+    let ecdh_reply = kex.ecdh_reply.take().unwrap();
+    let EcdhReply { server_pubkey, server_eph_pubkey, server_exchange_hash_sign } = ecdh_reply;
+
+    let (kem_ciphertext, server_x25519_pubkey) = server_eph_pubkey.split_at(kex.kem.ciphertext_len);
+    let kem_shared_secret = (kex.kem.decaps)(&kex.our_kem_privkey, kem_ciphertext)
+        .ok_or(Error::Protocol("server sent invalid KEM ciphertext"))?;
+
+    let server_x25519_pubkey: [u8; 32] = server_x25519_pubkey.try_into().unwrap();
+    let x25519_shared_secret = our_eph_privkey.diffie_hellman(&server_x25519_pubkey.into());
+
+    let mut shared_secret_data = kem_shared_secret.to_vec();
+    shared_secret_data.extend_from_slice(x25519_shared_secret.as_bytes());
+    let shared_secret = (kex.kem.compute_hash)(&shared_secret_data);
+
+    let mut exchange_data = PacketEncode::new();
+    exchange_data.put_bytes(input.client_ident);
+    exchange_data.put_bytes(input.server_ident);
+    exchange_data.put_bytes(input.client_kex_init);
+    exchange_data.put_bytes(input.server_kex_init);
+    exchange_data.put_bytes(&server_pubkey);
+    exchange_data.put_bytes(&kex.our_eph_pubkey);
+    exchange_data.put_bytes(&server_eph_pubkey);
+    exchange_data.put_bytes(&shared_secret);
+    let exchange_hash = (kex.kem.compute_hash)(&exchange_data.finish());
+
+    let shared_secret = SharedSecret::String(shared_secret);
+    Ok(KexOutput { shared_secret, exchange_hash, server_pubkey, server_exchange_hash_sign })
+}
+
+#[cfg(test)]
+mod tests {
+    use ml_kem::kem::Encapsulate as _;
+    use rand::rngs::OsRng;
+    use super::*;
+
+    fn check_kem(kem: &Kem, encaps: impl Fn(&[u8]) -> (Vec<u8>, [u8; 32])) {
+        let (pubkey, privkey) = (kem.keygen)(&mut OsRng);
+        let (mut ciphertext, shared_secret) = encaps(&pubkey);
+        assert_eq!(ciphertext.len(), kem.ciphertext_len);
+        assert_eq!((kem.decaps)(&privkey, &ciphertext), Some(shared_secret));
+
+        // a modified ciphertext is implicitly rejected
+        ciphertext[0] ^= 1;
+        assert_ne!((kem.decaps)(&privkey, &ciphertext), Some(shared_secret));
+        assert_eq!((kem.decaps)(&privkey, &ciphertext[1..]), None);
+    }
+
+    #[test]
+    fn test_sntrup761() {
+        check_kem(&SNTRUP761, |pubkey| {
+            let pubkey = sntrup761::EncapsulationKey::try_from(pubkey).unwrap();
+            let (ciphertext, shared_secret) = pubkey.encapsulate_deterministic(rand::random());
+            (ciphertext.as_ref().to_vec(), shared_secret.as_ref().try_into().unwrap())
+        });
+    }
+
+    #[test]
+    fn test_mlkem768() {
+        check_kem(&MLKEM768, |pubkey| {
+            type EncapsulationKey = <ml_kem::MlKem768 as ml_kem::KemCore>::EncapsulationKey;
+            let pubkey = EncapsulationKey::from_bytes(pubkey.try_into().unwrap());
+            let (ciphertext, shared_secret) = pubkey.encapsulate(&mut OsRng).unwrap();
+            (ciphertext.to_vec(), shared_secret.into())
+        });
+    }
+}
@@ -9,22 +9,42 @@
 //!
 //! # Supported algorithms
 //!
+//! - "mlkem768x25519-sha256" ([`MLKEM768X25519_SHA256`])
+//! - "sntrup761x25519-sha512" / "sntrup761x25519-sha512@openssh.com" ([`SNTRUP761X25519_SHA512`]
+//!   / [`SNTRUP761X25519_SHA512_OPENSSH`])
 //! - "curve25519-sha256" / "curve25519-sha256@libssh.com" ([`CURVE25519_SHA256`] /
 //! [`CURVE25519_SHA256_LIBSSH`])
+//! - "ecdh-sha2-nistp256" ([`ECDH_SHA2_NISTP256`])
+//! - "ecdh-sha2-nistp384" ([`ECDH_SHA2_NISTP384`])
+//! - "ecdh-sha2-nistp521" ([`ECDH_SHA2_NISTP521`])
+//! - "diffie-hellman-group-exchange-sha256" ([`DIFFIE_HELLMAN_GROUP_EXCHANGE_SHA256`])
+//! - "diffie-hellman-group-exchange-sha1" ([`DIFFIE_HELLMAN_GROUP_EXCHANGE_SHA1`])
 //! - "diffie-hellman-group14-sha1" ([`DIFFIE_HELLMAN_GROUP14_SHA1`])
 //! - "diffie-hellman-group14-sha256" ([`DIFFIE_HELLMAN_GROUP14_SHA256`])
 //! - "diffie-hellman-group16-sha512" ([`DIFFIE_HELLMAN_GROUP16_SHA512`])
 //! - "diffie-hellman-group18-sha512" ([`DIFFIE_HELLMAN_GROUP18_SHA512`])
 //! - "diffie-hellman-group1-sha1" ([`DIFFIE_HELLMAN_GROUP1_SHA1`], SHOULD NOT be used, available
 //! only with feature `insecure-crypto`).
+//!
+//! The group exchange algorithms (RFC 4419) let the server choose the Diffie-Hellman group; the
+//! sizes of the group that we request are configured with [`DhGexConfig`].
+//!
+//! You can also look up the algorithms by name using [`algo_by_name()`].
 use bytes::Bytes;
 use derivative::Derivative;
 use num_bigint_dig::BigUint;
 use std::task::Poll;
 use crate::Result;
-use crate::codec::PacketDecode;
+use crate::codec::{PacketDecode, PacketEncode};
 use crate::util::CryptoRngCore;
 pub use self::curve25519::{CURVE25519_SHA256, CURVE25519_SHA256_LIBSSH};
+pub use self::ecdh::{ECDH_SHA2_NISTP256, ECDH_SHA2_NISTP384, ECDH_SHA2_NISTP521};
+pub use self::hybrid::{
+    MLKEM768X25519_SHA256, SNTRUP761X25519_SHA512, SNTRUP761X25519_SHA512_OPENSSH,
+};
+pub use self::dh_gex::{
+    DhGexConfig, DIFFIE_HELLMAN_GROUP_EXCHANGE_SHA256, DIFFIE_HELLMAN_GROUP_EXCHANGE_SHA1,
+};
 pub use self::dh::{
     DIFFIE_HELLMAN_GROUP14_SHA1, DIFFIE_HELLMAN_GROUP14_SHA256,
     DIFFIE_HELLMAN_GROUP16_SHA512, DIFFIE_HELLMAN_GROUP18_SHA512,
@@ -34,6 +54,31 @@ pub use self::dh::DIFFIE_HELLMAN_GROUP1_SHA1;
 
 mod curve25519;
 mod dh;
+mod dh_gex;
+mod ecdh;
+mod hybrid;
+
+static ALGOS: &[&KexAlgo] = &[
+    &MLKEM768X25519_SHA256,
+    &SNTRUP761X25519_SHA512, &SNTRUP761X25519_SHA512_OPENSSH,
+    &CURVE25519_SHA256, &CURVE25519_SHA256_LIBSSH,
+    &ECDH_SHA2_NISTP256, &ECDH_SHA2_NISTP384, &ECDH_SHA2_NISTP521,
+    &DIFFIE_HELLMAN_GROUP_EXCHANGE_SHA256,
+    &DIFFIE_HELLMAN_GROUP16_SHA512, &DIFFIE_HELLMAN_GROUP18_SHA512,
+    &DIFFIE_HELLMAN_GROUP14_SHA256, &DIFFIE_HELLMAN_GROUP14_SHA1,
+    &DIFFIE_HELLMAN_GROUP_EXCHANGE_SHA1,
+    #[cfg(feature = "insecure-crypto")]
+    &DIFFIE_HELLMAN_GROUP1_SHA1,
+];
+
+/// Find a supported key exchange algorithm by its name.
+pub fn algo_by_name(name: &str) -> Option<&'static KexAlgo> {
+    ALGOS.iter().copied().find(|algo| algo.name == name)
+}
+
+pub(crate) fn algos() -> &'static [&'static KexAlgo] {
+    ALGOS
+}
 
 /// Algorithm for key exchange.
 ///
@@ -44,7 +89,10 @@ pub struct KexAlgo {
     /// Name of the algorithm.
     pub name: &'static str,
     #[derivative(Debug = "ignore")]
-    pub(crate) make_kex: fn(rng: &mut dyn CryptoRngCore) -> Result<Box<dyn Kex + Send>>,
+    pub(crate) make_kex: fn(
+        rng: &mut dyn CryptoRngCore,
+        dh_gex: &DhGexConfig,
+    ) -> Result<Box<dyn Kex + Send>>,
 }
 
 #[derive(Debug)]
@@ -56,12 +104,29 @@ pub(crate) struct KexInput<'a> {
 }
 
 pub(crate) struct KexOutput {
-    pub shared_secret: BigUint,
+    pub shared_secret: SharedSecret,
     pub exchange_hash: Vec<u8>,
     pub server_pubkey: Bytes,
     pub server_exchange_hash_sign: Bytes,
 }
 
+/// The shared secret `K` produced by a key exchange.
+pub(crate) enum SharedSecret {
+    /// Shared secret encoded as `mpint` (Diffie-Hellman and elliptic curve key exchanges).
+    Mpint(BigUint),
+    /// Shared secret encoded as `string` (hybrid key exchanges with a KEM).
+    String(Vec<u8>),
+}
+
+impl SharedSecret {
+    pub fn encode(&self, encode: &mut PacketEncode) {
+        match self {
+            SharedSecret::Mpint(value) => encode.put_biguint(value),
+            SharedSecret::String(value) => encode.put_bytes(value),
+        }
+    }
+}
+
 pub(crate) trait Kex {
     fn recv_packet(&mut self, msg_id: u8, payload: &mut PacketDecode) -> Result<()>;
     fn send_packet(&mut self) -> Result<Option<Bytes>>;
@@ -6,6 +6,7 @@ pub use self::openssh::{
     OpensshKeypair, OpensshKeypairNopass,
     decode_openssh_pem_keypair, decode_openssh_binary_keypair,
     decode_openssh_pem_keypair_nopass, decode_openssh_binary_keypair_nopass,
+    decode_openssh_cert,
 };
 pub use self::pkcs1::{
     decode_pkcs1_pem_privkey_nopass, decode_pkcs1_der_privkey,
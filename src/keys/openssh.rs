@@ -4,7 +4,8 @@ use derivative::Derivative;
 use crate::cipher::{self,  CipherAlgoVariant};
 use crate::codec::PacketDecode;
 use crate::error::{Result, Error};
-use crate::pubkey::{Pubkey, Privkey};
+use crate::pubkey::{Certificate, Pubkey, Privkey};
+use crate::util::base64_decode;
 
 /// Keypair (public and private key) in OpenSSH format.
 ///
@@ -109,6 +110,29 @@ pub fn decode_openssh_binary_keypair_nopass(data: Bytes) -> Result<OpensshKeypai
     Ok(OpensshKeypairNopass { pubkey: raw.pubkey, privkey, comment })
 }
 
+/// Decode a certificate from OpenSSH format.
+///
+/// Files in this format (such as `id_ed25519-cert.pub`) contain a single line with the certificate
+/// type (such as `ssh-ed25519-cert-v01@openssh.com`), the certificate encoded in base64 and an
+/// optional comment.
+pub fn decode_openssh_cert(data: &[u8]) -> Result<Certificate> {
+    let data = std::str::from_utf8(data)
+        .map_err(|_| Error::Decode("OpenSSH certificate is not valid utf-8"))?;
+    let mut fields = data.split_ascii_whitespace();
+    let cert_type = fields.next()
+        .ok_or(Error::Decode("expected certificate type in OpenSSH certificate"))?;
+    let cert_base64 = fields.next()
+        .ok_or(Error::Decode("expected certificate data in OpenSSH certificate"))?;
+    let cert_blob = base64_decode(cert_base64.as_bytes())
+        .map_err(|_| Error::Decode("OpenSSH certificate data is invalid base64"))?;
+
+    let cert = Certificate::decode(cert_blob.into())?;
+    if cert.type_str() != cert_type {
+        return Err(Error::Decode("OpenSSH certificate type is different from the specified type"))
+    }
+    Ok(cert)
+}
+
 #[derive(Debug)]
 struct RawKeypair {
     cipher_name: String,
@@ -123,7 +147,7 @@ fn decode_raw(data: Bytes) -> Result<RawKeypair> {
 
     let auth_magic = b"openssh-key-v1\0";
     let magic = data.get_raw(auth_magic.len())?;
-    if magic.as_ref() != auth_magic.as_ref() {
+    if magic.as_ref() != auth_magic.as_slice() {
         return Err(Error::Decode("this does not seem to be an OpenSSH keypair (bad magic bytes)"))
     }
 
@@ -6,7 +6,14 @@
 //!
 //! - Entry point for making SSH connections is the [`Client`].
 //! - Functions for decoding keys are in the [`keys`] module.
+//! - Client for the SSH agent is in the [`agent`] module.
 //! - Support for the `known_hosts` file is in the [`host_file`] module.
+//! - Support for the `ssh_config` file is in the [`ssh_config`] module.
+//! - Probing the algorithms supported by a server is in the [`probe`] module.
+//! - File transfer using SFTP is in the [`sftp`] module.
+//! - Helpers for X11 forwarding are in the [`x11`] module.
+//! - Local and remote port forwarding is in the [`forward`] module.
+//! - Dynamic port forwarding using SOCKS5 is in the [`socks`] module.
 //!
 #![allow(clippy::box_default)]
 #![allow(clippy::collapsible_if)]
@@ -18,27 +25,31 @@
 
 pub use crate::client::{
     AuthFailure, AuthNoneResult, AuthPasswordResult, AuthPasswordPrompt, AuthPubkeyResult,
+    AuthKeyboardInteractiveResult, AuthInfoRequest, AuthInfoPrompt,
 };
 pub use crate::client::{
     Channel, ChannelReceiver, ChannelEvent, ChannelReq, ChannelReply, ChannelConfig,
     DataType, DATA_STANDARD, DATA_STDERR,
 };
 pub use crate::client::{Client, ClientResp, ClientFuture, ClientConfig, GlobalReq, GlobalReply};
+pub use crate::client::{JumpStream, NegotiatedAlgos};
 pub use crate::client::{
     ClientReceiver, ClientEvent, AcceptPubkey, DebugMsg, AuthBanner, AcceptTunnel, AcceptChannel,
+    AcceptStreamlocalTunnel, AcceptX11, AcceptAgentForward,
 };
 pub use crate::client::{
     Session, SessionReceiver, SessionEvent, SessionResp, ExitSignal,
-    PtyRequest, PtyTerminalModes, WindowChange,
+    PtyRequest, PtyTerminalModes, WindowChange, X11Request,
 };
 pub use crate::client::{Tunnel, TunnelReceiver, TunnelEvent, TunnelReader, TunnelWriter, TunnelStream};
 pub use crate::codec::{PacketEncode, PacketDecode};
-pub use crate::error::{Result, Error, AlgoNegotiateError, DisconnectError, ChannelOpenError};
+pub use crate::error::{Result, Error, AlgoNegotiateError, DisconnectError, ChannelOpenError, SftpError};
 
 pub use self::cipher::CipherAlgo;
+pub use self::compress::CompressAlgo;
 pub use self::kex::KexAlgo;
 pub use self::mac::MacAlgo;
-pub use self::pubkey::{PubkeyAlgo, Pubkey, Privkey};
+pub use self::pubkey::{PubkeyAlgo, Pubkey, Privkey, Certificate, Signer};
 
 pub use bytes;
 pub use ecdsa;
@@ -49,14 +60,22 @@ pub use p384;
 pub use pem;
 pub use rsa;
 
+pub mod agent;
 pub mod cipher;
 mod client;
 mod codec;
 pub mod codes;
+pub mod compress;
 mod error;
+pub mod forward;
 pub mod host_file;
 pub mod kex;
 pub mod keys;
 pub mod mac;
+pub mod probe;
 pub mod pubkey;
+pub mod sftp;
+pub mod socks;
+pub mod ssh_config;
 mod util;
+pub mod x11;
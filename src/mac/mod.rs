@@ -15,6 +15,8 @@
 //! - "hmac-sha2-512-etm@openssh.com" ([`HMAC_SHA2_512_ETM`])
 //! - "hmac-sha1-etm@openssh.com" ([`HMAC_SHA1_ETM`])
 //! - "none" ([`NONE`])
+//!
+//! You can also look up the algorithms by name using [`algo_by_name()`].
 use crate::Result;
 use derivative::Derivative;
 pub use self::hmac::{
@@ -27,6 +29,22 @@ pub(crate) use self::none::{INVALID, Empty};
 mod none;
 mod hmac;
 
+static ALGOS: &[&MacAlgo] = &[
+    &HMAC_SHA2_256_ETM, &HMAC_SHA2_512_ETM,
+    &HMAC_SHA2_256, &HMAC_SHA2_512,
+    &HMAC_SHA1_ETM, &HMAC_SHA1,
+    &NONE,
+];
+
+/// Find a supported MAC algorithm by its name.
+pub fn algo_by_name(name: &str) -> Option<&'static MacAlgo> {
+    ALGOS.iter().copied().find(|algo| algo.name == name)
+}
+
+pub(crate) fn algos() -> &'static [&'static MacAlgo] {
+    ALGOS
+}
+
 /// Algorithm for authenticating messages.
 ///
 /// See the [module documentation][self] for details.
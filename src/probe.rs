@@ -0,0 +1,441 @@
+//! Probing the algorithms supported by an SSH server.
+//!
+//! This module is useful for auditing SSH servers, similar to `ssh-audit`: [`probe()`] sends our
+//! identification string, waits for the identification string and the `SSH_MSG_KEXINIT` packet
+//! from the server, and then disconnects before starting the key exchange. The result is a
+//! [`ProbeReport`] that contains all algorithm name-lists that the server offered, so it is
+//! available even if the server doesn't support any algorithm that we implement.
+//!
+//! Use [`classify()`] or [`ProbeReport::findings()`] to find the algorithms that we consider
+//! insecure or less secure. The classification follows the configurations in
+//! [`ClientConfig::default()`], [`ClientConfig::default_compatible_less_secure()`] and the
+//! algorithms that we don't enable by default at all.
+use bytes::Bytes;
+use rand::rngs::OsRng;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncReadExt as _, AsyncWriteExt as _};
+use crate::client::ClientConfig;
+use crate::codec::{PacketDecode, PacketEncode, RecvPipe, SendPipe};
+use crate::codes::{disconnect, msg};
+use crate::error::{Error, Result, DisconnectError};
+use crate::util::SyncFuture;
+use crate::{cipher, kex, mac, pubkey};
+
+/// Report about the algorithms offered by an SSH server, returned by [`probe()`].
+///
+/// All name-lists are in the order that the server sent them, which is the order of server's
+/// preference.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ProbeReport {
+    /// Identification string of the server, without the terminating CR LF.
+    pub server_ident: Bytes,
+    /// Lines that the server sent before the identification string.
+    pub server_prefix_lines: Vec<Bytes>,
+    /// Key exchange algorithms (`kex_algorithms`).
+    ///
+    /// This list also contains pseudo-algorithms that signal extensions, such as
+    /// `ext-info-s` and `kex-strict-s-v00@openssh.com`.
+    pub kex_algos: Vec<String>,
+    /// Server host key algorithms (`server_host_key_algorithms`).
+    pub server_pubkey_algos: Vec<String>,
+    /// Ciphers client-to-server (`encryption_algorithms_client_to_server`).
+    pub cipher_algos_cts: Vec<String>,
+    /// Ciphers server-to-client (`encryption_algorithms_server_to_client`).
+    pub cipher_algos_stc: Vec<String>,
+    /// MACs client-to-server (`mac_algorithms_client_to_server`).
+    pub mac_algos_cts: Vec<String>,
+    /// MACs server-to-client (`mac_algorithms_server_to_client`).
+    pub mac_algos_stc: Vec<String>,
+    /// Compression algorithms client-to-server (`compression_algorithms_client_to_server`).
+    pub compress_algos_cts: Vec<String>,
+    /// Compression algorithms server-to-client (`compression_algorithms_server_to_client`).
+    pub compress_algos_stc: Vec<String>,
+    /// Languages client-to-server (`languages_client_to_server`).
+    pub languages_cts: Vec<String>,
+    /// Languages server-to-client (`languages_server_to_client`).
+    pub languages_stc: Vec<String>,
+    /// The `first_kex_packet_follows` field.
+    pub first_kex_packet_follows: bool,
+    /// The server supports strict key exchange (`kex-strict-s-v00@openssh.com`), which mitigates
+    /// the Terrapin attack (CVE-2023-48795).
+    pub strict_kex: bool,
+    /// The server supports the extension negotiation from RFC 8308 (`ext-info-s`).
+    pub ext_info: bool,
+}
+
+/// Kind of algorithm, used in [`classify()`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AlgoKind {
+    /// Key exchange algorithm.
+    Kex,
+    /// Server host key algorithm.
+    ServerPubkey,
+    /// Cipher.
+    Cipher,
+    /// Message authentication code.
+    Mac,
+}
+
+/// Security of an algorithm, as determined by [`classify()`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AlgoSecurity {
+    /// The algorithm is enabled in [`ClientConfig::default()`].
+    Secure,
+    /// The algorithm is enabled only in [`ClientConfig::default_compatible_less_secure()`].
+    LessSecure,
+    /// The algorithm is known to be insecure, such as `3des-cbc`, `hmac-md5` or the "none"
+    /// cipher.
+    Insecure,
+    /// We don't know this algorithm.
+    Unknown,
+}
+
+/// Algorithm offered by the server, as reported by [`ProbeReport::findings()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// Kind of the algorithm.
+    pub kind: AlgoKind,
+    /// Name of the algorithm.
+    pub name: String,
+    /// Security of the algorithm.
+    pub security: AlgoSecurity,
+}
+
+impl ProbeReport {
+    /// Classify all algorithms offered by the server.
+    ///
+    /// Returns a [`Finding`] for every key exchange, host key, cipher and MAC algorithm from the
+    /// report (algorithms that appear in both directions are reported only once). The
+    /// pseudo-algorithms that signal extensions are skipped, see [`Self::strict_kex`] and
+    /// [`Self::ext_info`].
+    pub fn findings(&self) -> Vec<Finding> {
+        let lists = [
+            (AlgoKind::Kex, &self.kex_algos),
+            (AlgoKind::ServerPubkey, &self.server_pubkey_algos),
+            (AlgoKind::Cipher, &self.cipher_algos_cts),
+            (AlgoKind::Cipher, &self.cipher_algos_stc),
+            (AlgoKind::Mac, &self.mac_algos_cts),
+            (AlgoKind::Mac, &self.mac_algos_stc),
+        ];
+
+        let mut findings: Vec<Finding> = Vec::new();
+        for (kind, names) in lists {
+            for name in names.iter() {
+                if kind == AlgoKind::Kex && is_kex_pseudo_algo(name) {
+                    continue
+                }
+                if findings.iter().any(|f| f.kind == kind && &f.name == name) {
+                    continue
+                }
+                findings.push(Finding { kind, name: name.clone(), security: classify(kind, name) });
+            }
+        }
+        findings
+    }
+
+    /// Return the worst security of all algorithms offered by the server.
+    ///
+    /// Algorithms that we don't know ([`AlgoSecurity::Unknown`]) are ignored. Returns `None` if
+    /// the server did not offer any known algorithm.
+    pub fn worst_security(&self) -> Option<AlgoSecurity> {
+        self.findings().into_iter()
+            .map(|finding| finding.security)
+            .filter(|&security| security != AlgoSecurity::Unknown)
+            .max()
+    }
+}
+
+/// Probe the algorithms supported by an SSH server.
+///
+/// The `stream` must be connected to the SSH server. We exchange identification strings, receive
+/// the `SSH_MSG_KEXINIT` packet from the server and then send `SSH_MSG_DISCONNECT` and shut down
+/// the stream, without performing the key exchange.
+///
+/// Returns [`Error::PeerDisconnected`] if the server disconnects before sending its
+/// `SSH_MSG_KEXINIT`.
+pub async fn probe<IO>(stream: IO) -> Result<ProbeReport>
+    where IO: AsyncRead + AsyncWrite + Unpin + Send
+{
+    SyncFuture::new(Box::pin(probe_stream(stream))).await
+}
+
+async fn probe_stream<IO>(mut stream: IO) -> Result<ProbeReport>
+    where IO: AsyncRead + AsyncWrite + Unpin
+{
+    let mut send_pipe = SendPipe::new(&mut OsRng)?;
+    let mut recv_pipe = RecvPipe::new();
+
+    send_pipe.feed_ident(b"SSH-2.0-makiko");
+    write_all(&mut stream, &mut send_pipe).await?;
+
+    let server_ident = loop {
+        if let Some(ident) = recv_pipe.consume_ident()? {
+            break Bytes::copy_from_slice(&ident)
+        }
+        read_more(&mut stream, &mut recv_pipe).await?;
+    };
+    let server_prefix_lines = recv_pipe.take_prefix_lines();
+    log::debug!("probe received server identifier: {:?}", server_ident);
+
+    let report = loop {
+        let packet = match recv_pipe.consume_packet()? {
+            Some(packet) => packet,
+            None => {
+                read_more(&mut stream, &mut recv_pipe).await?;
+                continue
+            },
+        };
+
+        let mut payload = PacketDecode::new(packet.payload);
+        match payload.get_u8()? {
+            msg::KEXINIT => break recv_kex_init(&mut payload, server_ident, server_prefix_lines)?,
+            msg::IGNORE | msg::DEBUG => continue,
+            msg::DISCONNECT => return Err(Error::PeerDisconnected(DisconnectError {
+                reason_code: payload.get_u32()?,
+                description: payload.get_string()?,
+                description_lang: payload.get_string()?,
+            })),
+            _ => return Err(Error::Protocol("expected SSH_MSG_KEXINIT from the server")),
+        }
+    };
+
+    let mut payload = PacketEncode::new();
+    payload.put_u8(msg::DISCONNECT);
+    payload.put_u32(disconnect::BY_APPLICATION);
+    payload.put_str("probe finished");
+    payload.put_str("");
+    send_pipe.feed_packet(&payload.finish())?;
+    write_all(&mut stream, &mut send_pipe).await?;
+    stream.shutdown().await.map_err(Error::WriteIo)?;
+
+    Ok(report)
+}
+
+fn recv_kex_init(
+    payload: &mut PacketDecode,
+    server_ident: Bytes,
+    server_prefix_lines: Vec<Bytes>,
+) -> Result<ProbeReport> {
+    // RFC 4253, section 7.1
+    payload.skip(16)?; // cookie
+    let kex_algos = payload.get_name_list()?;
+    let server_pubkey_algos = payload.get_name_list()?;
+    let cipher_algos_cts = payload.get_name_list()?;
+    let cipher_algos_stc = payload.get_name_list()?;
+    let mac_algos_cts = payload.get_name_list()?;
+    let mac_algos_stc = payload.get_name_list()?;
+    let compress_algos_cts = payload.get_name_list()?;
+    let compress_algos_stc = payload.get_name_list()?;
+    let languages_cts = payload.get_name_list()?;
+    let languages_stc = payload.get_name_list()?;
+    let first_kex_packet_follows = payload.get_bool()?;
+    payload.get_u32()?; // reserved
+
+    let strict_kex = kex_algos.iter().any(|name| name == "kex-strict-s-v00@openssh.com");
+    let ext_info = kex_algos.iter().any(|name| name == "ext-info-s");
+
+    Ok(ProbeReport {
+        server_ident,
+        server_prefix_lines,
+        kex_algos,
+        server_pubkey_algos,
+        cipher_algos_cts,
+        cipher_algos_stc,
+        mac_algos_cts,
+        mac_algos_stc,
+        compress_algos_cts,
+        compress_algos_stc,
+        languages_cts,
+        languages_stc,
+        first_kex_packet_follows,
+        strict_kex,
+        ext_info,
+    })
+}
+
+async fn write_all<IO: AsyncWrite + Unpin>(stream: &mut IO, send_pipe: &mut SendPipe) -> Result<()> {
+    stream.write_all(send_pipe.peek_bytes()).await.map_err(Error::WriteIo)?;
+    send_pipe.consume_bytes(send_pipe.peek_bytes().len());
+    stream.flush().await.map_err(Error::WriteIo)
+}
+
+async fn read_more<IO: AsyncRead + Unpin>(stream: &mut IO, recv_pipe: &mut RecvPipe) -> Result<()> {
+    match stream.read_buf(recv_pipe.feed_buf()).await {
+        Ok(0) => Err(Error::PeerClosed),
+        Ok(_) => Ok(()),
+        Err(err) => Err(Error::ReadIo(err)),
+    }
+}
+
+/// Classify the security of an algorithm.
+///
+/// Algorithms enabled in [`ClientConfig::default()`] are [`AlgoSecurity::Secure`], algorithms
+/// that are added in [`ClientConfig::default_compatible_less_secure()`] are
+/// [`AlgoSecurity::LessSecure`]. Other algorithms that we implement (such as the "none" cipher)
+/// and a list of well-known broken algorithms that we don't implement (such as `arcfour` or
+/// `hmac-md5`) are [`AlgoSecurity::Insecure`], and everything else is [`AlgoSecurity::Unknown`].
+///
+/// Host certificate algorithms (`*-cert-v01@openssh.com`) are classified by the underlying key
+/// algorithm.
+pub fn classify(kind: AlgoKind, name: &str) -> AlgoSecurity {
+    let name = match kind {
+        AlgoKind::ServerPubkey => name.strip_suffix("-cert-v01@openssh.com").unwrap_or(name),
+        _ => name,
+    };
+
+    let (known, config_names, insecure_names): (bool, fn(&ClientConfig) -> Vec<&'static str>, _) =
+        match kind {
+            AlgoKind::Kex => (
+                kex::algo_by_name(name).is_some(),
+                |c| c.kex_algos.iter().map(|a| a.name).collect(),
+                INSECURE_KEX_ALGOS,
+            ),
+            AlgoKind::ServerPubkey => (
+                pubkey::algo_by_name(name).is_some(),
+                |c| c.server_pubkey_algos.iter().map(|a| a.name).collect(),
+                INSECURE_PUBKEY_ALGOS,
+            ),
+            AlgoKind::Cipher => (
+                cipher::algo_by_name(name).is_some(),
+                |c| c.cipher_algos.iter().map(|a| a.name).collect(),
+                INSECURE_CIPHER_ALGOS,
+            ),
+            AlgoKind::Mac => (
+                mac::algo_by_name(name).is_some(),
+                |c| c.mac_algos.iter().map(|a| a.name).collect(),
+                INSECURE_MAC_ALGOS,
+            ),
+        };
+
+    if config_names(&ClientConfig::default()).contains(&name) {
+        AlgoSecurity::Secure
+    } else if config_names(&ClientConfig::default_compatible_less_secure()).contains(&name) {
+        AlgoSecurity::LessSecure
+    } else if known || insecure_names.contains(&name) {
+        AlgoSecurity::Insecure
+    } else {
+        AlgoSecurity::Unknown
+    }
+}
+
+fn is_kex_pseudo_algo(name: &str) -> bool {
+    name.starts_with("ext-info-") || name.starts_with("kex-strict-")
+}
+
+// well-known broken algorithms that we don't implement (some of them only without the
+// `insecure-crypto` feature)
+static INSECURE_KEX_ALGOS: &[&str] = &[
+    "diffie-hellman-group1-sha1",
+    "gss-group1-sha1-toWM5Slw5Ew8Mqkay+al2g==",
+    "rsa1024-sha1",
+];
+
+static INSECURE_PUBKEY_ALGOS: &[&str] = &[
+    "ssh-dss",
+    "ssh-rsa-sha224@ssh.com",
+    "x509v3-sign-dss",
+];
+
+static INSECURE_CIPHER_ALGOS: &[&str] = &[
+    "3des-cbc", "3des-ctr",
+    "arcfour", "arcfour128", "arcfour256",
+    "blowfish-cbc", "blowfish-ctr",
+    "cast128-cbc", "cast128-ctr",
+    "des-cbc", "des-cbc@ssh.com",
+    "idea-cbc", "idea-ctr",
+    "rijndael-cbc@lysator.liu.se",
+    "seed-cbc@ssh.com",
+    "twofish-cbc", "twofish128-cbc", "twofish192-cbc", "twofish256-cbc",
+];
+
+static INSECURE_MAC_ALGOS: &[&str] = &[
+    "hmac-md5", "hmac-md5-96", "hmac-md5-etm@openssh.com", "hmac-md5-96-etm@openssh.com",
+    "hmac-sha1-96", "hmac-sha1-96-etm@openssh.com",
+    "hmac-ripemd160", "hmac-ripemd160@openssh.com", "hmac-ripemd160-etm@openssh.com",
+    "umac-32@openssh.com",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kex_init_payload() -> Bytes {
+        let mut payload = PacketEncode::new();
+        payload.put_u8(msg::KEXINIT);
+        payload.put_raw(&[0; 16]);
+        payload.put_name_list(&["curve25519-sha256", "diffie-hellman-group1-sha1",
+            "ext-info-s", "kex-strict-s-v00@openssh.com"]);
+        payload.put_name_list(&["ssh-ed25519", "ssh-ed25519-cert-v01@openssh.com", "ssh-dss"]);
+        payload.put_name_list(&["aes128-ctr", "aes128-cbc"]);
+        payload.put_name_list(&["aes128-ctr", "arcfour"]);
+        payload.put_name_list(&["hmac-sha2-256", "hmac-sha1"]);
+        payload.put_name_list(&["hmac-sha2-256", "umac-64@openssh.com"]);
+        payload.put_name_list(&["none"]);
+        payload.put_name_list(&["none"]);
+        payload.put_name_list(&[]);
+        payload.put_name_list(&[]);
+        payload.put_bool(false);
+        payload.put_u32(0);
+        payload.finish()
+    }
+
+    #[tokio::test]
+    async fn test_probe() {
+        let (client, mut server) = tokio::io::duplex(4096);
+        let probe_task = probe(client);
+
+        let server_task = async move {
+            let mut send_pipe = SendPipe::new(&mut OsRng).unwrap();
+            send_pipe.feed_ident(b"hello\r\nSSH-2.0-dummy");
+            send_pipe.feed_packet(&kex_init_payload()).unwrap();
+            server.write_all(send_pipe.peek_bytes()).await.unwrap();
+
+            let mut data = Vec::new();
+            server.read_to_end(&mut data).await.unwrap();
+            let mut recv_pipe = RecvPipe::new();
+            recv_pipe.feed_buf().extend_from_slice(&data);
+            assert_eq!(recv_pipe.consume_ident().unwrap(), Some(Bytes::from("SSH-2.0-makiko")));
+            let packet = recv_pipe.consume_packet().unwrap().unwrap();
+            assert_eq!(packet.payload[0], msg::DISCONNECT);
+        };
+
+        let (report, ()) = tokio::join!(probe_task, server_task);
+        let report = report.unwrap();
+        assert_eq!(report.server_ident, "SSH-2.0-dummy");
+        assert_eq!(report.server_prefix_lines, vec![Bytes::from("hello")]);
+        assert_eq!(report.cipher_algos_stc, vec!["aes128-ctr", "arcfour"]);
+        assert!(report.strict_kex);
+        assert!(report.ext_info);
+        assert_eq!(report.worst_security(), Some(AlgoSecurity::Insecure));
+
+        let findings = report.findings();
+        let security = |kind, name: &str| findings.iter()
+            .find(|f| f.kind == kind && f.name == name)
+            .map(|f| f.security);
+        assert_eq!(findings.len(), 11);
+        assert_eq!(security(AlgoKind::Kex, "curve25519-sha256"), Some(AlgoSecurity::Secure));
+        assert_eq!(security(AlgoKind::Kex, "ext-info-s"), None);
+        assert_eq!(security(AlgoKind::ServerPubkey, "ssh-ed25519-cert-v01@openssh.com"),
+            Some(AlgoSecurity::Secure));
+        assert_eq!(security(AlgoKind::ServerPubkey, "ssh-dss"), Some(AlgoSecurity::Insecure));
+        assert_eq!(security(AlgoKind::Cipher, "aes128-cbc"), Some(AlgoSecurity::LessSecure));
+        assert_eq!(security(AlgoKind::Cipher, "arcfour"), Some(AlgoSecurity::Insecure));
+        assert_eq!(security(AlgoKind::Mac, "hmac-sha1"), Some(AlgoSecurity::LessSecure));
+        assert_eq!(security(AlgoKind::Mac, "umac-64@openssh.com"), Some(AlgoSecurity::Unknown));
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(AlgoKind::Kex, "mlkem768x25519-sha256"), AlgoSecurity::Secure);
+        assert_eq!(classify(AlgoKind::Kex, "diffie-hellman-group14-sha1"), AlgoSecurity::LessSecure);
+        assert_eq!(classify(AlgoKind::Kex, "diffie-hellman-group1-sha1"), AlgoSecurity::Insecure);
+        assert_eq!(classify(AlgoKind::ServerPubkey, "ssh-rsa"), AlgoSecurity::LessSecure);
+        assert_eq!(classify(AlgoKind::ServerPubkey, "rsa-sha2-512-cert-v01@openssh.com"),
+            AlgoSecurity::Secure);
+        assert_eq!(classify(AlgoKind::Cipher, "chacha20-poly1305@openssh.com"), AlgoSecurity::Secure);
+        assert_eq!(classify(AlgoKind::Cipher, "none"), AlgoSecurity::Insecure);
+        assert_eq!(classify(AlgoKind::Cipher, "3des-cbc"), AlgoSecurity::Insecure);
+        assert_eq!(classify(AlgoKind::Mac, "hmac-md5"), AlgoSecurity::Insecure);
+        assert_eq!(classify(AlgoKind::Mac, "foo"), AlgoSecurity::Unknown);
+    }
+}
@@ -0,0 +1,504 @@
+use bytes::Bytes;
+use crate::codec::{PacketDecode, PacketEncode};
+use crate::error::{Result, Error};
+use super::{PubkeyAlgo, Pubkey, Privkey};
+use super::{SSH_ED25519, ECDSA_SHA2_NISTP256, ECDSA_SHA2_NISTP384};
+use super::{SSH_RSA_SHA1, RSA_SHA2_256, RSA_SHA2_512};
+
+/// "ssh-ed25519-cert-v01@openssh.com" public key algorithm from OpenSSH.
+///
+/// This algorithm is compatible with [`Certificate`]s of [`Ed25519Pubkey`][super::Ed25519Pubkey]
+/// and with [`Ed25519Privkey`][super::Ed25519Privkey]. The signatures are the same as with
+/// [`SSH_ED25519`].
+pub static SSH_ED25519_CERT_V01: PubkeyAlgo = PubkeyAlgo {
+    name: "ssh-ed25519-cert-v01@openssh.com",
+    verify: |pubkey, message, signature| (SSH_ED25519.verify)(pubkey, message, signature),
+    sign: |privkey, message| (SSH_ED25519.sign)(privkey, message),
+};
+
+/// "ecdsa-sha2-nistp256-cert-v01@openssh.com" public key algorithm from OpenSSH.
+///
+/// This algorithm is compatible with [`Certificate`]s of
+/// [`EcdsaPubkey<p256::NistP256>`][super::EcdsaPubkey] and with
+/// [`EcdsaPrivkey<p256::NistP256>`][super::EcdsaPrivkey]. The signatures are the same as with
+/// [`ECDSA_SHA2_NISTP256`].
+pub static ECDSA_SHA2_NISTP256_CERT_V01: PubkeyAlgo = PubkeyAlgo {
+    name: "ecdsa-sha2-nistp256-cert-v01@openssh.com",
+    verify: |pubkey, message, signature| (ECDSA_SHA2_NISTP256.verify)(pubkey, message, signature),
+    sign: |privkey, message| (ECDSA_SHA2_NISTP256.sign)(privkey, message),
+};
+
+/// "ecdsa-sha2-nistp384-cert-v01@openssh.com" public key algorithm from OpenSSH.
+///
+/// This algorithm is compatible with [`Certificate`]s of
+/// [`EcdsaPubkey<p384::NistP384>`][super::EcdsaPubkey] and with
+/// [`EcdsaPrivkey<p384::NistP384>`][super::EcdsaPrivkey]. The signatures are the same as with
+/// [`ECDSA_SHA2_NISTP384`].
+pub static ECDSA_SHA2_NISTP384_CERT_V01: PubkeyAlgo = PubkeyAlgo {
+    name: "ecdsa-sha2-nistp384-cert-v01@openssh.com",
+    verify: |pubkey, message, signature| (ECDSA_SHA2_NISTP384.verify)(pubkey, message, signature),
+    sign: |privkey, message| (ECDSA_SHA2_NISTP384.sign)(privkey, message),
+};
+
+/// "ssh-rsa-cert-v01@openssh.com" public key algorithm from OpenSSH.
+///
+/// This algorithm is compatible with [`Certificate`]s of [`RsaPubkey`][super::RsaPubkey] and with
+/// [`RsaPrivkey`][super::RsaPrivkey]. The signatures are the same as with [`SSH_RSA_SHA1`].
+pub static SSH_RSA_SHA1_CERT_V01: PubkeyAlgo = PubkeyAlgo {
+    name: "ssh-rsa-cert-v01@openssh.com",
+    verify: |pubkey, message, signature| (SSH_RSA_SHA1.verify)(pubkey, message, signature),
+    sign: |privkey, message| (SSH_RSA_SHA1.sign)(privkey, message),
+};
+
+/// "rsa-sha2-256-cert-v01@openssh.com" public key algorithm from OpenSSH.
+///
+/// This algorithm is compatible with [`Certificate`]s of [`RsaPubkey`][super::RsaPubkey] and with
+/// [`RsaPrivkey`][super::RsaPrivkey]. The signatures are the same as with [`RSA_SHA2_256`].
+pub static RSA_SHA2_256_CERT_V01: PubkeyAlgo = PubkeyAlgo {
+    name: "rsa-sha2-256-cert-v01@openssh.com",
+    verify: |pubkey, message, signature| (RSA_SHA2_256.verify)(pubkey, message, signature),
+    sign: |privkey, message| (RSA_SHA2_256.sign)(privkey, message),
+};
+
+/// "rsa-sha2-512-cert-v01@openssh.com" public key algorithm from OpenSSH.
+///
+/// This algorithm is compatible with [`Certificate`]s of [`RsaPubkey`][super::RsaPubkey] and with
+/// [`RsaPrivkey`][super::RsaPrivkey]. The signatures are the same as with [`RSA_SHA2_512`].
+pub static RSA_SHA2_512_CERT_V01: PubkeyAlgo = PubkeyAlgo {
+    name: "rsa-sha2-512-cert-v01@openssh.com",
+    verify: |pubkey, message, signature| (RSA_SHA2_512.verify)(pubkey, message, signature),
+    sign: |privkey, message| (RSA_SHA2_512.sign)(privkey, message),
+};
+
+/// OpenSSH certificate.
+///
+/// A certificate binds a public key ([`pubkey`][Self::pubkey]) to a set of identities
+/// ([`valid_principals`][Self::valid_principals]) for a limited time, and it is signed by a
+/// certificate authority (CA) with the key [`signature_key`][Self::signature_key]. The format is
+/// described in the file `PROTOCOL.certkeys` in the OpenSSH sources.
+///
+/// Certificates are typically stored in files such as `id_ed25519-cert.pub`, which you can decode
+/// using [`keys::decode_openssh_cert()`][crate::keys::decode_openssh_cert()]. To authenticate
+/// using a user certificate, use [`Client::auth_pubkey_cert()`][crate::Client::auth_pubkey_cert()].
+///
+/// A decoded certificate remembers its original encoding, which is used by
+/// [`encode()`][Self::encode()] and [`verify_signature()`][Self::verify_signature()], so that
+/// certificates with a non-empty reserved field or with non-canonical encoding of the fields are
+/// preserved exactly. Note that if you modify any of the fields, the original encoding is no longer
+/// used and the [`signature`][Self::signature] will no longer be valid; use
+/// [`sign()`][Self::sign()] to sign the certificate again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Certificate {
+    /// Random nonce supplied by the CA, which makes collision attacks on the signature infeasible.
+    pub nonce: Bytes,
+    /// The public key that is certified.
+    pub pubkey: Pubkey,
+    /// Serial number chosen by the CA (may be zero).
+    pub serial: u64,
+    /// Whether this is a user or a host certificate.
+    pub cert_type: CertType,
+    /// Free-form identifier chosen by the CA, typically logged by the server.
+    pub key_id: String,
+    /// Usernames (for user certificates) or hostnames (for host certificates) for which the
+    /// certificate is valid. Empty list means that the certificate is valid for any principal.
+    pub valid_principals: Vec<String>,
+    /// Start of the validity period (seconds since the UNIX epoch).
+    pub valid_after: u64,
+    /// End of the validity period (seconds since the UNIX epoch), exclusive.
+    pub valid_before: u64,
+    /// Critical options; the server must refuse the certificate if it does not recognize any of
+    /// them.
+    pub critical_options: Vec<CertOption>,
+    /// Extensions; the server should ignore extensions that it does not recognize.
+    pub extensions: Vec<CertOption>,
+    /// Public key of the CA that signed this certificate.
+    pub signature_key: Pubkey,
+    /// Signature of the certificate by the CA (in SSH encoding).
+    pub signature: Bytes,
+    /// Original encoding of the certificate, if it was decoded or signed by us.
+    pub(crate) raw: Option<RawCert>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RawCert {
+    /// The whole certificate blob, including the signature.
+    blob: Bytes,
+    /// Length of the prefix of `blob` that is signed by the CA.
+    signed_len: usize,
+}
+
+/// Type of an OpenSSH [`Certificate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CertType {
+    /// User certificate (`SSH_CERT_TYPE_USER`), used to authenticate users.
+    User,
+    /// Host certificate (`SSH_CERT_TYPE_HOST`), used to authenticate hosts.
+    Host,
+}
+
+/// Critical option or extension in an OpenSSH [`Certificate`].
+///
+/// OpenSSH defines critical options such as `force-command` or `source-address`, which carry a
+/// string value, and extensions such as `permit-pty`, which are just flags with empty
+/// [`data`][Self::data].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertOption {
+    /// Name of the option or extension.
+    pub name: String,
+    /// Raw data of the option or extension (usually empty or a single string in SSH encoding).
+    pub data: Bytes,
+}
+
+static CERT_ALGOS: &[&PubkeyAlgo] = &[
+    &SSH_ED25519_CERT_V01, &ECDSA_SHA2_NISTP256_CERT_V01, &ECDSA_SHA2_NISTP384_CERT_V01,
+    &SSH_RSA_SHA1_CERT_V01, &RSA_SHA2_256_CERT_V01, &RSA_SHA2_512_CERT_V01,
+];
+
+pub(super) fn is_cert_algo(algo: &PubkeyAlgo) -> bool {
+    CERT_ALGOS.iter().any(|cert_algo| cert_algo.name == algo.name)
+}
+
+impl Certificate {
+    /// Decode a certificate from SSH wire encoding.
+    pub fn decode(blob: Bytes) -> Result<Self> {
+        decode_cert(blob)
+    }
+
+    /// Encode the certificate into SSH wire encoding.
+    ///
+    /// This is the encoding that is used in certificate files (encoded with base64) and in the SSH
+    /// protocol.
+    pub fn encode(&self) -> Bytes {
+        if let Some(raw) = self.valid_raw() {
+            return raw.blob.clone()
+        }
+        let mut blob = encode_cert_to_sign(self);
+        blob.put_bytes(&self.signature);
+        blob.finish()
+    }
+
+    /// Get the type of the certificate as a string.
+    ///
+    /// Returns a string like `"ssh-ed25519-cert-v01@openssh.com"`, which is used in OpenSSH files
+    /// as a human-readable description of the certificate type.
+    pub fn type_str(&self) -> String {
+        cert_type_str(&self.pubkey).into()
+    }
+
+    /// Get all public key algorithms that work with this certificate.
+    ///
+    /// These are the `*-cert-v01@openssh.com` variants of the algorithms returned by
+    /// [`Pubkey::algos()`] for the certified [`pubkey`][Self::pubkey].
+    pub fn algos(&self) -> &'static [&'static PubkeyAlgo] {
+        static ED25519: &[&PubkeyAlgo] = &[&SSH_ED25519_CERT_V01];
+        static RSA: &[&PubkeyAlgo] = &[
+            &RSA_SHA2_256_CERT_V01, &RSA_SHA2_512_CERT_V01, &SSH_RSA_SHA1_CERT_V01,
+        ];
+        static ECDSA_P256: &[&PubkeyAlgo] = &[&ECDSA_SHA2_NISTP256_CERT_V01];
+        static ECDSA_P384: &[&PubkeyAlgo] = &[&ECDSA_SHA2_NISTP384_CERT_V01];
+        match self.pubkey {
+            Pubkey::Ed25519(_) => ED25519,
+            Pubkey::Rsa(_) => RSA,
+            Pubkey::EcdsaP256(_) => ECDSA_P256,
+            Pubkey::EcdsaP384(_) => ECDSA_P384,
+        }
+    }
+
+    /// Find a critical option by name.
+    pub fn critical_option(&self, name: &str) -> Option<&CertOption> {
+        self.critical_options.iter().find(|opt| opt.name == name)
+    }
+
+    /// Find an extension by name.
+    pub fn extension(&self, name: &str) -> Option<&CertOption> {
+        self.extensions.iter().find(|ext| ext.name == name)
+    }
+
+    /// Sign the certificate using the private key of the CA.
+    ///
+    /// Sets the [`signature_key`][Self::signature_key] to the public key of `ca_privkey` and
+    /// updates the [`signature`][Self::signature] using `ca_pubkey_algo`. If the algorithm is not
+    /// compatible with the private key, returns [`Error::PrivkeyFormat`].
+    pub fn sign(&mut self, ca_privkey: &Privkey, ca_pubkey_algo: &'static PubkeyAlgo) -> Result<()> {
+        self.signature_key = ca_privkey.pubkey();
+        let signed = encode_cert_to_sign(self).finish();
+        self.signature = (ca_pubkey_algo.sign)(ca_privkey, &signed)?;
+        let mut blob = PacketEncode::new();
+        blob.put_raw(&signed);
+        blob.put_bytes(&self.signature);
+        self.raw = Some(RawCert { blob: blob.finish(), signed_len: signed.len() });
+        Ok(())
+    }
+
+    /// Verify the signature of the certificate by the CA.
+    ///
+    /// Checks that the [`signature`][Self::signature] was made by the
+    /// [`signature_key`][Self::signature_key], using one of the algorithms in
+    /// [`Pubkey::algos()`]. Note that this method does not check whether you trust the CA, nor
+    /// does it check the validity period or the principals.
+    pub fn verify_signature(&self) -> Result<()> {
+        let signature_format = PacketDecode::new(self.signature.clone()).get_string()?;
+        let algo = self.signature_key.algos().iter()
+            .find(|algo| algo.name == signature_format)
+            .ok_or(Error::Decode("unknown signature format in certificate"))?;
+        let signed = match self.valid_raw() {
+            Some(raw) => raw.blob.slice(..raw.signed_len),
+            None => encode_cert_to_sign(self).finish(),
+        };
+        (algo.verify)(&self.signature_key, &signed, self.signature.clone())?;
+        Ok(())
+    }
+
+    /// Get the original encoding, unless the certificate has been modified since it was decoded.
+    fn valid_raw(&self) -> Option<&RawCert> {
+        let raw = self.raw.as_ref()?;
+        let original = decode_cert(raw.blob.clone()).ok()?;
+        (original == *self).then_some(raw)
+    }
+}
+
+impl CertOption {
+    /// Decode the [`data`][Self::data] as a single string.
+    ///
+    /// This is the format used by the critical options defined by OpenSSH, such as
+    /// `force-command` or `source-address`.
+    pub fn data_str(&self) -> Result<String> {
+        let mut data = PacketDecode::new(self.data.clone());
+        let value = data.get_string()?;
+        if data.remaining_len() != 0 {
+            return Err(Error::Decode("unexpected data after string in certificate option"))
+        }
+        Ok(value)
+    }
+}
+
+impl CertType {
+    fn from_code(code: u32) -> Option<Self> {
+        match code {
+            1 => Some(Self::User),
+            2 => Some(Self::Host),
+            _ => None,
+        }
+    }
+
+    fn code(self) -> u32 {
+        match self {
+            Self::User => 1,
+            Self::Host => 2,
+        }
+    }
+}
+
+fn cert_base_type_str(format: &str) -> Option<&'static str> {
+    Some(match format {
+        "ssh-ed25519-cert-v01@openssh.com" => "ssh-ed25519",
+        "ssh-rsa-cert-v01@openssh.com" => "ssh-rsa",
+        "ecdsa-sha2-nistp256-cert-v01@openssh.com" => "ecdsa-sha2-nistp256",
+        "ecdsa-sha2-nistp384-cert-v01@openssh.com" => "ecdsa-sha2-nistp384",
+        _ => return None,
+    })
+}
+
+fn cert_type_str(pubkey: &Pubkey) -> &'static str {
+    match pubkey {
+        Pubkey::Ed25519(_) => "ssh-ed25519-cert-v01@openssh.com",
+        Pubkey::Rsa(_) => "ssh-rsa-cert-v01@openssh.com",
+        Pubkey::EcdsaP256(_) => "ecdsa-sha2-nistp256-cert-v01@openssh.com",
+        Pubkey::EcdsaP384(_) => "ecdsa-sha2-nistp384-cert-v01@openssh.com",
+    }
+}
+
+fn decode_cert(raw_blob: Bytes) -> Result<Certificate> {
+    // OpenSSH PROTOCOL.certkeys
+    let mut blob = PacketDecode::new(raw_blob.clone());
+    let format = blob.get_string()?;
+    let Some(base_format) = cert_base_type_str(&format) else {
+        log::debug!("unknown certificate format {:?}", format);
+        return Err(Error::Decode("unknown certificate format"))
+    };
+
+    let nonce = blob.get_bytes()?;
+    let pubkey = super::decode_pubkey_fields(base_format, &mut blob)?;
+    let serial = blob.get_u64()?;
+    let cert_type = CertType::from_code(blob.get_u32()?)
+        .ok_or(Error::Decode("unknown certificate type"))?;
+    let key_id = blob.get_string()?;
+    let valid_principals = decode_principals(blob.get_bytes()?)?;
+    let valid_after = blob.get_u64()?;
+    let valid_before = blob.get_u64()?;
+    let critical_options = decode_options(blob.get_bytes()?)?;
+    let extensions = decode_options(blob.get_bytes()?)?;
+    let _reserved = blob.get_bytes()?;
+    let signature_key = Pubkey::decode(blob.get_bytes()?)?;
+    let signed_len = raw_blob.len() - blob.remaining_len();
+    let signature = blob.get_bytes()?;
+
+    if blob.remaining_len() != 0 {
+        return Err(Error::Decode("unexpected data after certificate"))
+    }
+
+    Ok(Certificate {
+        nonce, pubkey, serial, cert_type, key_id, valid_principals, valid_after, valid_before,
+        critical_options, extensions, signature_key, signature,
+        raw: Some(RawCert { blob: raw_blob, signed_len }),
+    })
+}
+
+fn decode_principals(data: Bytes) -> Result<Vec<String>> {
+    let mut data = PacketDecode::new(data);
+    let mut principals = Vec::new();
+    while data.remaining_len() > 0 {
+        principals.push(data.get_string()?);
+    }
+    Ok(principals)
+}
+
+fn decode_options(data: Bytes) -> Result<Vec<CertOption>> {
+    let mut data = PacketDecode::new(data);
+    let mut options = Vec::new();
+    while data.remaining_len() > 0 {
+        let name = data.get_string()?;
+        let data = data.get_bytes()?;
+        options.push(CertOption { name, data });
+    }
+    Ok(options)
+}
+
+fn encode_cert_to_sign(cert: &Certificate) -> PacketEncode {
+    let mut blob = PacketEncode::new();
+    blob.put_str(cert_type_str(&cert.pubkey));
+    blob.put_bytes(&cert.nonce);
+    super::encode_pubkey_fields(&mut blob, &cert.pubkey);
+    blob.put_u64(cert.serial);
+    blob.put_u32(cert.cert_type.code());
+    blob.put_str(&cert.key_id);
+
+    let mut principals = PacketEncode::new();
+    for principal in cert.valid_principals.iter() {
+        principals.put_str(principal);
+    }
+    blob.put_bytes(&principals.finish());
+
+    blob.put_u64(cert.valid_after);
+    blob.put_u64(cert.valid_before);
+    blob.put_bytes(&encode_options(&cert.critical_options));
+    blob.put_bytes(&encode_options(&cert.extensions));
+    blob.put_bytes(&[]);
+    blob.put_bytes(&cert.signature_key.encode());
+    blob
+}
+
+fn encode_options(options: &[CertOption]) -> Bytes {
+    let mut data = PacketEncode::new();
+    for option in options.iter() {
+        data.put_str(&option.name);
+        data.put_bytes(&option.data);
+    }
+    data.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // generated with `ssh-keygen -s user_ca -I "edward cert" -n edward,alice -V always:forever -z 42
+    // -O force-command=/bin/true -O source-address=10.0.0.0/8 edward_ed25519.pub`
+    static EDWARD_CERT: &str = concat!(
+        "AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAICpP/zXdHe7UQ+iyZI7KzxBjbZOxDIrAanswe+",
+        "xnDLlcAAAAIPJUmxF+H42aRAqDYOHqs9Wh2JDecL51WgYygy1hxswlAAAAAAAAACoAAAABAAAAC2Vkd2FyZCBjZXJ0",
+        "AAAAEwAAAAZlZHdhcmQAAAAFYWxpY2UAAAAAAAAAAP//////////AAAARgAAAA1mb3JjZS1jb21tYW5kAAAADQAAAA",
+        "kvYmluL3RydWUAAAAOc291cmNlLWFkZHJlc3MAAAAOAAAACjEwLjAuMC4wLzgAAACCAAAAFXBlcm1pdC1YMTEtZm9y",
+        "d2FyZGluZwAAAAAAAAAXcGVybWl0LWFnZW50LWZvcndhcmRpbmcAAAAAAAAAFnBlcm1pdC1wb3J0LWZvcndhcmRpbm",
+        "cAAAAAAAAACnBlcm1pdC1wdHkAAAAAAAAADnBlcm1pdC11c2VyLXJjAAAAAAAAAAAAAAAzAAAAC3NzaC1lZDI1NTE5",
+        "AAAAIFn3hhpn8hWALtJKrs2TxEnhgo7yqpv/ojDDBQ1Jp/BVAAAAUwAAAAtzc2gtZWQyNTUxOQAAAEAXyKjuEUeTQP",
+        "zVAxWRPWY0A3ofwN4xeJ8S8l9tv7b/TRRVpmvzETLDL0LUvOrq1Ixp0KLon1LKd5/JqqMgYhcK",
+    );
+
+    fn decode_base64(data: &str) -> Bytes {
+        use base64::Engine as _;
+        base64::engine::general_purpose::STANDARD.decode(data).unwrap().into()
+    }
+
+    #[test]
+    fn test_decode_encode() {
+        let blob = decode_base64(EDWARD_CERT);
+        let cert = Certificate::decode(blob.clone()).unwrap();
+
+        assert_eq!(cert.type_str(), "ssh-ed25519-cert-v01@openssh.com");
+        assert_eq!(cert.pubkey.fingerprint(), "SHA256:m/ji0qLSX52XXfUDHr1uBbwb3RTZE3uao1wn8M9kpa4");
+        assert_eq!(cert.signature_key.fingerprint(), "SHA256:P1nxcckCH8/XeI4jdGzFjW70LHSt4Pkns83HSdYzdjU");
+        assert_eq!(cert.serial, 42);
+        assert_eq!(cert.cert_type, CertType::User);
+        assert_eq!(cert.key_id, "edward cert");
+        assert_eq!(cert.valid_principals, vec!["edward".to_string(), "alice".to_string()]);
+        assert_eq!(cert.valid_after, 0);
+        assert_eq!(cert.valid_before, u64::MAX);
+
+        let option_names = cert.critical_options.iter().map(|opt| opt.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(option_names, vec!["force-command", "source-address"]);
+        assert_eq!(cert.critical_option("force-command").unwrap().data_str().unwrap(), "/bin/true");
+        assert_eq!(cert.critical_option("source-address").unwrap().data_str().unwrap(), "10.0.0.0/8");
+
+        let extension_names = cert.extensions.iter().map(|ext| ext.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(extension_names, vec![
+            "permit-X11-forwarding", "permit-agent-forwarding", "permit-port-forwarding",
+            "permit-pty", "permit-user-rc",
+        ]);
+        assert!(cert.extension("permit-pty").unwrap().data.is_empty());
+
+        cert.verify_signature().unwrap();
+        assert_eq!(cert.encode(), blob);
+    }
+
+    #[test]
+    fn test_sign_verify() {
+        let mut cert = Certificate::decode(decode_base64(EDWARD_CERT)).unwrap();
+        cert.valid_principals = vec!["eve".into()];
+        assert!(cert.verify_signature().is_err());
+
+        let ca_privkey = Privkey::Ed25519(ed25519_dalek::SigningKey::from_bytes(&[42; 32]).into());
+        cert.sign(&ca_privkey, &SSH_ED25519).unwrap();
+        assert_eq!(cert.signature_key, ca_privkey.pubkey());
+        cert.verify_signature().unwrap();
+
+        let decoded = Certificate::decode(cert.encode()).unwrap();
+        assert_eq!(decoded, cert);
+    }
+
+    #[test]
+    fn test_reserved_field() {
+        // replace the empty reserved field with a non-empty one and sign the certificate again
+        let orig = Certificate::decode(decode_base64(EDWARD_CERT)).unwrap();
+        let ca_privkey = Privkey::Ed25519(ed25519_dalek::SigningKey::from_bytes(&[42; 32]).into());
+        let signature_key = ca_privkey.pubkey().encode();
+        let orig_signed = encode_cert_to_sign(&orig).finish();
+        let prefix_len = orig_signed.len() - 4 - 4 - orig.signature_key.encode().len();
+
+        let mut signed = PacketEncode::new();
+        signed.put_raw(&orig_signed[..prefix_len]);
+        signed.put_bytes(b"reserved");
+        signed.put_bytes(&signature_key);
+        let signed = signed.finish();
+        let signature = (SSH_ED25519.sign)(&ca_privkey, &signed).unwrap();
+        let mut blob = PacketEncode::new();
+        blob.put_raw(&signed);
+        blob.put_bytes(&signature);
+        let blob = blob.finish();
+
+        let mut cert = Certificate::decode(blob.clone()).unwrap();
+        assert_eq!(cert.signature_key, ca_privkey.pubkey());
+        cert.verify_signature().unwrap();
+        assert_eq!(cert.encode(), blob);
+
+        cert.serial += 1;
+        assert!(cert.verify_signature().is_err());
+        assert_ne!(cert.encode(), blob);
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        let blob = decode_base64(EDWARD_CERT);
+        assert!(Certificate::decode(blob.slice(..blob.len() - 1)).is_err());
+        assert!(Certificate::decode(SSH_ED25519_CERT_V01.name.as_bytes().to_vec().into()).is_err());
+    }
+}
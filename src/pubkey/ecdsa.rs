@@ -185,7 +185,6 @@ pub(super) fn encode_pubkey<C: Curve>(blob: &mut PacketEncode, pubkey: &EcdsaPub
     let public_key: elliptic_curve::PublicKey<C> = pubkey.verifying.into();
     let encoded_point = public_key.to_encoded_point(false);
 
-    blob.put_str(C::ALGO_NAME);
     blob.put_str(C::CURVE_NAME);
     blob.put_bytes(encoded_point.as_bytes());
 }
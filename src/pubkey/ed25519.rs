@@ -69,7 +69,6 @@ fn sign(privkey: &Privkey, message: &[u8]) -> Result<Bytes> {
 }
 
 pub(super) fn encode_pubkey(blob: &mut PacketEncode, pubkey: &Ed25519Pubkey) {
-    blob.put_str("ssh-ed25519");
     blob.put_bytes(pubkey.verifying.as_bytes());
 }
 
@@ -13,19 +13,70 @@
 //! - "ssh-rsa" ([`SSH_RSA_SHA1`], uses [`RsaPubkey`] and [`RsaPrivkey`])
 //! - "rsa-sha2-256" ([`RSA_SHA2_256`], uses [`RsaPubkey`] and [`RsaPrivkey`])
 //! - "rsa-sha2-512" ([`RSA_SHA2_512`], uses [`RsaPubkey`] and [`RsaPrivkey`])
+//!
+//! # OpenSSH certificates
+//!
+//! With OpenSSH certificates ([`Certificate`]), the public key is signed by a certificate
+//! authority, so the server does not need to know the public key of the client in advance (and
+//! the client does not need to know the host key of the server). These algorithms use
+//! a [`Certificate`] instead of a [`Pubkey`] and produce the same signatures as the corresponding
+//! algorithms for plain keys:
+//!
+//! - "ssh-ed25519-cert-v01@openssh.com" ([`SSH_ED25519_CERT_V01`])
+//! - "ecdsa-sha2-nistp256-cert-v01@openssh.com" ([`ECDSA_SHA2_NISTP256_CERT_V01`])
+//! - "ecdsa-sha2-nistp384-cert-v01@openssh.com" ([`ECDSA_SHA2_NISTP384_CERT_V01`])
+//! - "ssh-rsa-cert-v01@openssh.com" ([`SSH_RSA_SHA1_CERT_V01`])
+//! - "rsa-sha2-256-cert-v01@openssh.com" ([`RSA_SHA2_256_CERT_V01`])
+//! - "rsa-sha2-512-cert-v01@openssh.com" ([`RSA_SHA2_512_CERT_V01`])
+//!
+//! # Signers
+//!
+//! To authenticate the client, we need to sign data using the private key. This is abstracted by
+//! the [`Signer`] trait, which is implemented by [`Privkey`], but you can also implement it for
+//! keys that are stored elsewhere.
+//!
+//! # Lookup by name
+//!
+//! You can look up all of these algorithms by name using [`algo_by_name()`].
 use bytes::Bytes;
 use derivative::Derivative;
 use std::fmt;
 use crate::codec::{PacketDecode, PacketEncode};
 use crate::error::{Result, Error};
 use crate::util::base64_encode;
+pub use self::cert::{
+    SSH_ED25519_CERT_V01, ECDSA_SHA2_NISTP256_CERT_V01, ECDSA_SHA2_NISTP384_CERT_V01,
+    SSH_RSA_SHA1_CERT_V01, RSA_SHA2_256_CERT_V01, RSA_SHA2_512_CERT_V01,
+    Certificate, CertType, CertOption,
+};
 pub use self::ecdsa::{ECDSA_SHA2_NISTP256, ECDSA_SHA2_NISTP384, EcdsaPubkey, EcdsaPrivkey};
 pub use self::ed25519::{SSH_ED25519, Ed25519Pubkey, Ed25519Privkey};
 pub use self::rsa::{SSH_RSA_SHA1, RSA_SHA2_256, RSA_SHA2_512, RsaPubkey, RsaPrivkey};
+pub use self::signer::Signer;
 
+mod cert;
 mod ecdsa;
 mod ed25519;
 mod rsa;
+mod signer;
+
+static ALGOS: &[&PubkeyAlgo] = &[
+    &SSH_ED25519,
+    &ECDSA_SHA2_NISTP256, &ECDSA_SHA2_NISTP384,
+    &RSA_SHA2_512, &RSA_SHA2_256, &SSH_RSA_SHA1,
+    &SSH_ED25519_CERT_V01,
+    &ECDSA_SHA2_NISTP256_CERT_V01, &ECDSA_SHA2_NISTP384_CERT_V01,
+    &RSA_SHA2_512_CERT_V01, &RSA_SHA2_256_CERT_V01, &SSH_RSA_SHA1_CERT_V01,
+];
+
+/// Find a supported public key algorithm by its name.
+pub fn algo_by_name(name: &str) -> Option<&'static PubkeyAlgo> {
+    ALGOS.iter().copied().find(|algo| algo.name == name)
+}
+
+pub(crate) fn algos() -> &'static [&'static PubkeyAlgo] {
+    ALGOS
+}
 
 /// Algorithm for public key cryptography.
 ///
@@ -203,14 +254,28 @@ impl Privkey {
 
 
 
+/// Decode the server host key, which is a certificate if `algo` is a certificate algorithm.
+pub(crate) fn decode_host_key(algo: &PubkeyAlgo, blob: Bytes) -> Result<(Pubkey, Option<Certificate>)> {
+    if cert::is_cert_algo(algo) {
+        let cert = Certificate::decode(blob)?;
+        Ok((cert.pubkey.clone(), Some(cert)))
+    } else {
+        Ok((Pubkey::decode(blob)?, None))
+    }
+}
+
 fn decode_pubkey(blob: Bytes) -> Result<Pubkey> {
     let mut blob = PacketDecode::new(blob);
     let format = blob.get_string()?;
-    match format.as_str() {
-        "ssh-ed25519" => ed25519::decode_pubkey(&mut blob).map(Pubkey::Ed25519),
-        "ssh-rsa" => rsa::decode_pubkey(&mut blob).map(Pubkey::Rsa),
-        "ecdsa-sha2-nistp256" => ecdsa::decode_pubkey::<p256::NistP256>(&mut blob).map(Pubkey::EcdsaP256),
-        "ecdsa-sha2-nistp384" => ecdsa::decode_pubkey::<p384::NistP384>(&mut blob).map(Pubkey::EcdsaP384),
+    decode_pubkey_fields(&format, &mut blob)
+}
+
+fn decode_pubkey_fields(format: &str, blob: &mut PacketDecode) -> Result<Pubkey> {
+    match format {
+        "ssh-ed25519" => ed25519::decode_pubkey(blob).map(Pubkey::Ed25519),
+        "ssh-rsa" => rsa::decode_pubkey(blob).map(Pubkey::Rsa),
+        "ecdsa-sha2-nistp256" => ecdsa::decode_pubkey::<p256::NistP256>(blob).map(Pubkey::EcdsaP256),
+        "ecdsa-sha2-nistp384" => ecdsa::decode_pubkey::<p384::NistP384>(blob).map(Pubkey::EcdsaP384),
         _ => {
             log::debug!("unknown pubkey format {:?}", format);
             Err(Error::Decode("unknown public key format"))
@@ -229,13 +294,18 @@ fn pubkey_type_str(pubkey: &Pubkey) -> &'static str {
 
 fn encode_pubkey(pubkey: &Pubkey) -> Bytes {
     let mut blob = PacketEncode::new();
+    blob.put_str(pubkey_type_str(pubkey));
+    encode_pubkey_fields(&mut blob, pubkey);
+    blob.finish()
+}
+
+fn encode_pubkey_fields(blob: &mut PacketEncode, pubkey: &Pubkey) {
     match pubkey {
-        Pubkey::Ed25519(pubkey) => ed25519::encode_pubkey(&mut blob, pubkey),
-        Pubkey::Rsa(pubkey) => rsa::encode_pubkey(&mut blob, pubkey),
-        Pubkey::EcdsaP256(pubkey) => ecdsa::encode_pubkey(&mut blob, pubkey),
-        Pubkey::EcdsaP384(pubkey) => ecdsa::encode_pubkey(&mut blob, pubkey),
+        Pubkey::Ed25519(pubkey) => ed25519::encode_pubkey(blob, pubkey),
+        Pubkey::Rsa(pubkey) => rsa::encode_pubkey(blob, pubkey),
+        Pubkey::EcdsaP256(pubkey) => ecdsa::encode_pubkey(blob, pubkey),
+        Pubkey::EcdsaP384(pubkey) => ecdsa::encode_pubkey(blob, pubkey),
     }
-    blob.finish()
 }
 
 fn decode_privkey(blob: &mut PacketDecode) -> Result<Privkey> {
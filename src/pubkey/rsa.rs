@@ -100,7 +100,6 @@ fn sign<H: RsaHash>(privkey: &Privkey, message: &[u8]) -> Result<Bytes> {
 
 pub(super) fn encode_pubkey(blob: &mut PacketEncode, pubkey: &RsaPubkey) {
     use rsa::traits::PublicKeyParts as _;
-    blob.put_str("ssh-rsa");
     blob.put_biguint(pubkey.pubkey.e());
     blob.put_biguint(pubkey.pubkey.n());
 }
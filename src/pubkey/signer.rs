@@ -0,0 +1,70 @@
+use bytes::Bytes;
+use futures_core::future::BoxFuture;
+use std::sync::Arc;
+use crate::error::Result;
+use super::{Privkey, Pubkey, PubkeyAlgo};
+
+/// Private key that can produce signatures.
+///
+/// This trait abstracts over the place where the private key is stored, so that you can
+/// authenticate using [`Client::auth_pubkey()`][crate::Client::auth_pubkey()] with a key that is
+/// not loaded into your process. For example, the key may be held in a hardware token, in a cloud
+/// KMS or in an SSH agent (see [`AgentSigner`][crate::agent::AgentSigner]). A local [`Privkey`] is
+/// just one implementation of this trait.
+///
+/// The signing is asynchronous, so you may perform I/O in [`sign()`][Self::sign()]. The client
+/// keeps handling the SSH connection while you are signing.
+pub trait Signer: Send + Sync {
+    /// Return the public key that corresponds to the private key.
+    fn pubkey(&self) -> Pubkey;
+
+    /// Sign `data` using the given algorithm.
+    ///
+    /// The returned signature must be encoded in the SSH format (RFC 4253, section 6.6), i.e. as
+    /// the algorithm name followed by the signature blob, which you can produce using
+    /// [`PacketEncode`][crate::PacketEncode]. If the algorithm is not compatible with the key, you
+    /// should return [`Error::PrivkeyFormat`][crate::Error::PrivkeyFormat]; other errors can be
+    /// returned as [`Error::Sign`][crate::Error::Sign].
+    fn sign<'a>(&'a self, algo: &'static PubkeyAlgo, data: &'a [u8]) -> BoxFuture<'a, Result<Bytes>>;
+}
+
+impl Signer for Privkey {
+    fn pubkey(&self) -> Pubkey {
+        Privkey::pubkey(self)
+    }
+
+    fn sign<'a>(&'a self, algo: &'static PubkeyAlgo, data: &'a [u8]) -> BoxFuture<'a, Result<Bytes>> {
+        let signature = (algo.sign)(self, data);
+        Box::pin(async move { signature })
+    }
+}
+
+impl<S: Signer + ?Sized> Signer for &S {
+    fn pubkey(&self) -> Pubkey {
+        (**self).pubkey()
+    }
+
+    fn sign<'a>(&'a self, algo: &'static PubkeyAlgo, data: &'a [u8]) -> BoxFuture<'a, Result<Bytes>> {
+        (**self).sign(algo, data)
+    }
+}
+
+impl<S: Signer + ?Sized> Signer for Box<S> {
+    fn pubkey(&self) -> Pubkey {
+        (**self).pubkey()
+    }
+
+    fn sign<'a>(&'a self, algo: &'static PubkeyAlgo, data: &'a [u8]) -> BoxFuture<'a, Result<Bytes>> {
+        (**self).sign(algo, data)
+    }
+}
+
+impl<S: Signer + ?Sized> Signer for Arc<S> {
+    fn pubkey(&self) -> Pubkey {
+        (**self).pubkey()
+    }
+
+    fn sign<'a>(&'a self, algo: &'static PubkeyAlgo, data: &'a [u8]) -> BoxFuture<'a, Result<Bytes>> {
+        (**self).sign(algo, data)
+    }
+}
@@ -0,0 +1,182 @@
+use bytes::Bytes;
+use crate::codec::{PacketDecode, PacketEncode};
+use crate::codes::sftp_attr;
+use crate::error::Result;
+
+/// Attributes of a file on the SFTP server.
+///
+/// This corresponds to the `ATTRS` structure from the SFTP protocol (version 3), section 5. All
+/// attributes are optional: the server may omit attributes that it does not support, and when you
+/// send attributes to the server (e.g. using [`Sftp::setstat()`][super::Sftp::setstat()]), only
+/// the attributes that are `Some` will be changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileAttrs {
+    /// Size of the file in bytes.
+    pub size: Option<u64>,
+    /// User and group id of the owner of the file.
+    pub uid_gid: Option<(u32, u32)>,
+    /// Permissions of the file, including the file type bits (as in the `st_mode` field of POSIX
+    /// `stat`).
+    pub permissions: Option<u32>,
+    /// Access and modification time of the file (in seconds since the Unix epoch).
+    pub atime_mtime: Option<(u32, u32)>,
+    /// Extended attributes as pairs of name and data.
+    pub extended: Vec<(String, Bytes)>,
+}
+
+/// Type of a file, derived from [`FileAttrs::permissions`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FileType {
+    /// Regular file.
+    File,
+    /// Directory.
+    Dir,
+    /// Symbolic link.
+    Symlink,
+    /// Other type of file (such as a socket or a device).
+    Other,
+}
+
+const S_IFMT: u32 = 0o170000;
+const S_IFREG: u32 = 0o100000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFLNK: u32 = 0o120000;
+
+impl FileAttrs {
+    /// Create an empty instance (with no attributes).
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Determine the type of the file from the permissions.
+    ///
+    /// Returns `None` if the server did not send the permissions.
+    pub fn file_type(&self) -> Option<FileType> {
+        self.permissions.map(|perms| match perms & S_IFMT {
+            S_IFREG => FileType::File,
+            S_IFDIR => FileType::Dir,
+            S_IFLNK => FileType::Symlink,
+            _ => FileType::Other,
+        })
+    }
+
+    /// Returns true if we know that this is a regular file.
+    pub fn is_file(&self) -> bool {
+        self.file_type() == Some(FileType::File)
+    }
+
+    /// Returns true if we know that this is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.file_type() == Some(FileType::Dir)
+    }
+
+    /// Returns true if we know that this is a symbolic link.
+    pub fn is_symlink(&self) -> bool {
+        self.file_type() == Some(FileType::Symlink)
+    }
+}
+
+pub(super) fn encode_attrs(payload: &mut PacketEncode, attrs: &FileAttrs) {
+    let mut flags = 0;
+    if attrs.size.is_some() { flags |= sftp_attr::SIZE }
+    if attrs.uid_gid.is_some() { flags |= sftp_attr::UIDGID }
+    if attrs.permissions.is_some() { flags |= sftp_attr::PERMISSIONS }
+    if attrs.atime_mtime.is_some() { flags |= sftp_attr::ACMODTIME }
+    if !attrs.extended.is_empty() { flags |= sftp_attr::EXTENDED }
+    payload.put_u32(flags);
+
+    if let Some(size) = attrs.size {
+        payload.put_u64(size);
+    }
+    if let Some((uid, gid)) = attrs.uid_gid {
+        payload.put_u32(uid);
+        payload.put_u32(gid);
+    }
+    if let Some(permissions) = attrs.permissions {
+        payload.put_u32(permissions);
+    }
+    if let Some((atime, mtime)) = attrs.atime_mtime {
+        payload.put_u32(atime);
+        payload.put_u32(mtime);
+    }
+    if !attrs.extended.is_empty() {
+        payload.put_u32(attrs.extended.len() as u32);
+        for (name, data) in attrs.extended.iter() {
+            payload.put_str(name);
+            payload.put_bytes(data);
+        }
+    }
+}
+
+pub(super) fn decode_attrs(payload: &mut PacketDecode) -> Result<FileAttrs> {
+    let flags = payload.get_u32()?;
+    let mut attrs = FileAttrs::default();
+    if flags & sftp_attr::SIZE != 0 {
+        attrs.size = Some(payload.get_u64()?);
+    }
+    if flags & sftp_attr::UIDGID != 0 {
+        attrs.uid_gid = Some((payload.get_u32()?, payload.get_u32()?));
+    }
+    if flags & sftp_attr::PERMISSIONS != 0 {
+        attrs.permissions = Some(payload.get_u32()?);
+    }
+    if flags & sftp_attr::ACMODTIME != 0 {
+        attrs.atime_mtime = Some((payload.get_u32()?, payload.get_u32()?));
+    }
+    if flags & sftp_attr::EXTENDED != 0 {
+        let count = payload.get_u32()?;
+        for _ in 0..count {
+            let name = payload.get_string()?;
+            let data = payload.get_bytes()?;
+            attrs.extended.push((name, data));
+        }
+    }
+    Ok(attrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(attrs: &FileAttrs) -> FileAttrs {
+        let mut payload = PacketEncode::new();
+        encode_attrs(&mut payload, attrs);
+        let mut payload = PacketDecode::new(payload.finish());
+        let decoded = decode_attrs(&mut payload).unwrap();
+        assert_eq!(payload.remaining_len(), 0);
+        decoded
+    }
+
+    #[test]
+    fn test_encode_decode_attrs() {
+        let attrs = FileAttrs::default();
+        assert_eq!(roundtrip(&attrs), attrs);
+
+        let attrs = FileAttrs {
+            size: Some(0x1_0000_0001),
+            uid_gid: Some((1000, 100)),
+            permissions: Some(0o100644),
+            atime_mtime: Some((1_600_000_000, 1_700_000_000)),
+            extended: vec![("foo@example.com".into(), Bytes::from_static(b"bar"))],
+        };
+        assert_eq!(roundtrip(&attrs), attrs);
+        assert!(attrs.is_file());
+
+        let attrs = FileAttrs { permissions: Some(0o040755), .. FileAttrs::default() };
+        assert_eq!(roundtrip(&attrs), attrs);
+        assert!(attrs.is_dir());
+    }
+
+    #[test]
+    fn test_decode_attrs() {
+        let mut payload = PacketDecode::new(Bytes::from_static(&[
+            0,0,0,0x05, 0,0,0,0,0,0,0x10,0, 0,0,0xa1,0xff,
+        ]));
+        let attrs = decode_attrs(&mut payload).unwrap();
+        assert_eq!(attrs.size, Some(0x1000));
+        assert_eq!(attrs.permissions, Some(0o120777));
+        assert_eq!(attrs.file_type(), Some(FileType::Symlink));
+        assert_eq!(attrs.uid_gid, None);
+        assert_eq!(attrs.atime_mtime, None);
+    }
+}
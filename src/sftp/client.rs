@@ -0,0 +1,556 @@
+use bytes::{Buf as _, Bytes, BytesMut};
+use futures_core::ready;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Weak};
+use std::task::{Context, Poll};
+use tokio::sync::oneshot;
+use crate::client::{Client, ChannelConfig, Session, SessionReceiver, SessionEvent};
+use crate::codec::{PacketDecode, PacketEncode};
+use crate::codes::{sftp_msg, sftp_status};
+use crate::error::{Error, Result, SftpError};
+use super::attrs::{FileAttrs, encode_attrs, decode_attrs};
+use super::dir::{Dir, DirEntry};
+use super::file::{File, OpenOptions};
+
+/// The version of the SFTP protocol that we implement.
+const SFTP_VERSION: u32 = 3;
+
+/// Maximum length of a packet that we are willing to receive from the server.
+const MAX_PACKET_LEN: usize = 1 << 20;
+
+/// Handle to an SFTP session.
+///
+/// Use this object to send requests to the SFTP server. The replies are received by the matching
+/// [`SftpFuture`], which you must poll (usually by spawning a task for it). To obtain an instance
+/// of [`Sftp`], use [`Sftp::open()`] or [`Sftp::start()`].
+///
+/// All methods can be called concurrently: every request gets a unique id and the requests are
+/// pipelined, so you don't need to wait for a reply before you send the next request.
+///
+/// You can cheaply clone this object and safely share the clones between tasks.
+#[derive(Clone)]
+pub struct Sftp {
+    sftp_st: Weak<Mutex<SftpState>>,
+}
+
+struct SftpState {
+    session: Session,
+    config: SftpConfig,
+    version: u32,
+    extensions: Vec<(String, Bytes)>,
+    next_req_id: u32,
+    replies: HashMap<u32, oneshot::Sender<SftpReply>>,
+}
+
+#[derive(Debug)]
+pub(super) struct SftpReply {
+    msg_id: u8,
+    payload: PacketDecode,
+}
+
+impl Sftp {
+    /// Open an SFTP session on the given client.
+    ///
+    /// This opens an SSH session, starts the `"sftp"` subsystem (RFC 4254, section 6.5) and
+    /// performs the SFTP version negotiation. You should use the returned objects as follows:
+    ///
+    /// - [`Sftp`] allows you to send requests to the server.
+    /// - [`SftpFuture`] is a future that you must poll to receive replies from the server. You will
+    ///   usually spawn a task for this future.
+    ///
+    /// This method will wait until you are authenticated before doing anything.
+    pub async fn open(client: &Client, config: SftpConfig) -> Result<(Sftp, SftpFuture)> {
+        let (session, session_rx) = client.open_session(config.channel.clone()).await?;
+        session.subsystem("sftp")?.wait().await?;
+        Self::start(session, session_rx, config).await
+    }
+
+    /// Start SFTP on a session where the `"sftp"` subsystem has already been started.
+    ///
+    /// Use this method if you need to prepare the session yourself (for example, if you need to
+    /// set environment variables). We send `SSH_FXP_INIT` and wait for `SSH_FXP_VERSION` from the
+    /// server; the `session_rx` must not have produced any data before this method is called.
+    pub async fn start(
+        session: Session,
+        mut session_rx: SessionReceiver,
+        config: SftpConfig,
+    ) -> Result<(Sftp, SftpFuture)> {
+        let mut payload = PacketEncode::new();
+        payload.put_u32(SFTP_VERSION);
+        session.send_stdin(encode_packet(sftp_msg::INIT, None, &payload.finish())).await?;
+        log::debug!("sending SSH_FXP_INIT");
+
+        let mut recv_buf = BytesMut::new();
+        let mut packet = loop {
+            if let Some(packet) = decode_packet(&mut recv_buf)? {
+                break packet
+            }
+            match session_rx.recv().await? {
+                Some(SessionEvent::StdoutData(data)) => recv_buf.extend_from_slice(&data),
+                Some(SessionEvent::StderrData(data)) => log_stderr(&data),
+                Some(SessionEvent::Eof) | None => return Err(Error::SftpClosed),
+                Some(_) => {},
+            }
+        };
+
+        if packet.get_u8()? != sftp_msg::VERSION {
+            return Err(Error::SftpProtocol("expected SSH_FXP_VERSION"))
+        }
+        let version = packet.get_u32()?;
+        let mut extensions = Vec::new();
+        while packet.remaining_len() > 0 {
+            let name = packet.get_string()?;
+            let data = packet.get_bytes()?;
+            extensions.push((name, data));
+        }
+        log::debug!("received SSH_FXP_VERSION {}, extensions {:?}",
+            version, extensions.iter().map(|x| &x.0).collect::<Vec<_>>());
+
+        if version < SFTP_VERSION {
+            return Err(Error::SftpProtocol("server does not support SFTP version 3"))
+        }
+
+        let sftp_st = Arc::new(Mutex::new(SftpState {
+            session,
+            config,
+            version,
+            extensions,
+            next_req_id: 0,
+            replies: HashMap::new(),
+        }));
+        let sftp = Sftp { sftp_st: Arc::downgrade(&sftp_st) };
+        let sftp_fut = SftpFuture { sftp_st, session_rx, recv_buf };
+        Ok((sftp, sftp_fut))
+    }
+
+    fn upgrade(&self) -> Result<Arc<Mutex<SftpState>>> {
+        self.sftp_st.upgrade().ok_or(Error::SftpClosed)
+    }
+
+    /// Get the version of the protocol sent by the server in `SSH_FXP_VERSION`.
+    pub fn version(&self) -> Result<u32> {
+        Ok(self.upgrade()?.lock().version)
+    }
+
+    /// Get the extensions (pairs of name and data) sent by the server in `SSH_FXP_VERSION`.
+    pub fn extensions(&self) -> Result<Vec<(String, Bytes)>> {
+        Ok(self.upgrade()?.lock().extensions.clone())
+    }
+
+    /// Get the configuration of this SFTP session.
+    pub fn config(&self) -> Result<SftpConfig> {
+        Ok(self.upgrade()?.lock().config.clone())
+    }
+
+    /// Close the SFTP session.
+    ///
+    /// This closes the underlying SSH session. Requests that are still waiting for a reply will
+    /// fail with [`Error::SftpClosed`] once the session is closed.
+    pub fn close(&self) -> Result<()> {
+        self.upgrade()?.lock().session.close()
+    }
+
+    /// Send a request to the server and wait for the reply (low level API).
+    ///
+    /// The request is enqueued to the session when the returned future is first polled.
+    pub(super) async fn request(&self, msg_id: u8, payload: Bytes) -> Result<SftpReply> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let (req_id, session, packet) = {
+            let sftp_st = self.upgrade()?;
+            let mut sftp_st = sftp_st.lock();
+            let req_id = sftp_st.next_req_id;
+            sftp_st.next_req_id = req_id.wrapping_add(1);
+            sftp_st.replies.insert(req_id, reply_tx);
+            (req_id, sftp_st.session.clone(), encode_packet(msg_id, Some(req_id), &payload))
+        };
+        if let Err(err) = session.send_stdin(packet).await {
+            // the request was not sent, so we will never receive a reply
+            if let Ok(sftp_st) = self.upgrade() {
+                sftp_st.lock().replies.remove(&req_id);
+            }
+            return Err(err)
+        }
+        reply_rx.await.map_err(|_| Error::SftpClosed)
+    }
+}
+
+/// # Files
+impl Sftp {
+    /// Open a file.
+    ///
+    /// Sends `SSH_FXP_OPEN` with flags and attributes derived from `options` and returns the
+    /// [`File`] handle. You should explicitly [`close()`][File::close()] the file when you are done
+    /// with it.
+    pub async fn open_file(&self, path: &str, options: &OpenOptions) -> Result<File> {
+        let mut payload = PacketEncode::new();
+        payload.put_str(path);
+        payload.put_u32(options.pflags());
+        encode_attrs(&mut payload, &options.attrs());
+        let handle = self.request(sftp_msg::OPEN, payload.finish()).await?.into_handle()?;
+        Ok(File::new(self.clone(), handle, self.config()?))
+    }
+
+    /// Get the attributes of a file, following symbolic links (`SSH_FXP_STAT`).
+    pub async fn stat(&self, path: &str) -> Result<FileAttrs> {
+        let mut payload = PacketEncode::new();
+        payload.put_str(path);
+        self.request(sftp_msg::STAT, payload.finish()).await?.into_attrs()
+    }
+
+    /// Get the attributes of a file, without following symbolic links (`SSH_FXP_LSTAT`).
+    pub async fn lstat(&self, path: &str) -> Result<FileAttrs> {
+        let mut payload = PacketEncode::new();
+        payload.put_str(path);
+        self.request(sftp_msg::LSTAT, payload.finish()).await?.into_attrs()
+    }
+
+    /// Change the attributes of a file (`SSH_FXP_SETSTAT`).
+    ///
+    /// Only the attributes that are `Some` will be changed.
+    pub async fn setstat(&self, path: &str, attrs: &FileAttrs) -> Result<()> {
+        let mut payload = PacketEncode::new();
+        payload.put_str(path);
+        encode_attrs(&mut payload, attrs);
+        self.request(sftp_msg::SETSTAT, payload.finish()).await?.into_status()
+    }
+
+    /// Remove a file (`SSH_FXP_REMOVE`).
+    pub async fn remove(&self, path: &str) -> Result<()> {
+        let mut payload = PacketEncode::new();
+        payload.put_str(path);
+        self.request(sftp_msg::REMOVE, payload.finish()).await?.into_status()
+    }
+
+    /// Rename a file or a directory (`SSH_FXP_RENAME`).
+    ///
+    /// Note that SFTP version 3 servers usually fail if `new_path` already exists.
+    pub async fn rename(&self, old_path: &str, new_path: &str) -> Result<()> {
+        let mut payload = PacketEncode::new();
+        payload.put_str(old_path);
+        payload.put_str(new_path);
+        self.request(sftp_msg::RENAME, payload.finish()).await?.into_status()
+    }
+
+    /// Create a symbolic link at `link_path` that points to `target_path` (`SSH_FXP_SYMLINK`).
+    ///
+    /// The SFTP specification says that the link path should be sent first, but OpenSSH (and most
+    /// other servers that followed it) expects the target path first. We use the OpenSSH order.
+    pub async fn symlink(&self, target_path: &str, link_path: &str) -> Result<()> {
+        let mut payload = PacketEncode::new();
+        payload.put_str(target_path);
+        payload.put_str(link_path);
+        self.request(sftp_msg::SYMLINK, payload.finish()).await?.into_status()
+    }
+
+    /// Read the target of a symbolic link (`SSH_FXP_READLINK`).
+    pub async fn readlink(&self, path: &str) -> Result<String> {
+        let mut payload = PacketEncode::new();
+        payload.put_str(path);
+        self.request(sftp_msg::READLINK, payload.finish()).await?.into_single_name()
+    }
+
+    /// Canonicalize a path on the server (`SSH_FXP_REALPATH`).
+    ///
+    /// This is useful to convert relative paths (such as `"."`) to absolute paths.
+    pub async fn realpath(&self, path: &str) -> Result<String> {
+        let mut payload = PacketEncode::new();
+        payload.put_str(path);
+        self.request(sftp_msg::REALPATH, payload.finish()).await?.into_single_name()
+    }
+}
+
+/// # Directories
+impl Sftp {
+    /// Create a directory with the given attributes (`SSH_FXP_MKDIR`).
+    pub async fn mkdir(&self, path: &str, attrs: &FileAttrs) -> Result<()> {
+        let mut payload = PacketEncode::new();
+        payload.put_str(path);
+        encode_attrs(&mut payload, attrs);
+        self.request(sftp_msg::MKDIR, payload.finish()).await?.into_status()
+    }
+
+    /// Remove an empty directory (`SSH_FXP_RMDIR`).
+    pub async fn rmdir(&self, path: &str) -> Result<()> {
+        let mut payload = PacketEncode::new();
+        payload.put_str(path);
+        self.request(sftp_msg::RMDIR, payload.finish()).await?.into_status()
+    }
+
+    /// Open a directory for reading (`SSH_FXP_OPENDIR`).
+    ///
+    /// You should explicitly [`close()`][Dir::close()] the directory when you are done with it.
+    pub async fn open_dir(&self, path: &str) -> Result<Dir> {
+        let mut payload = PacketEncode::new();
+        payload.put_str(path);
+        let handle = self.request(sftp_msg::OPENDIR, payload.finish()).await?.into_handle()?;
+        Ok(Dir::new(self.clone(), handle))
+    }
+
+    /// Read all entries in a directory.
+    ///
+    /// This is a shorthand for [`open_dir()`][Self::open_dir()], followed by
+    /// [`Dir::read()`] until the end of the directory and [`Dir::close()`].
+    pub async fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>> {
+        let dir = self.open_dir(path).await?;
+        let mut entries = Vec::new();
+        let res = async {
+            while let Some(chunk) = dir.read().await? {
+                entries.extend(chunk);
+            }
+            Ok(())
+        }.await;
+        let close_res = dir.close().await;
+        res.and(close_res).map(|_| entries)
+    }
+}
+
+impl SftpReply {
+    fn unexpected(&self) -> Error {
+        log::debug!("received unexpected SFTP reply {}", self.msg_id);
+        Error::SftpProtocol("received unexpected reply type")
+    }
+
+    pub(super) fn into_status(mut self) -> Result<()> {
+        match self.msg_id {
+            sftp_msg::STATUS => decode_status(&mut self.payload),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    pub(super) fn into_handle(mut self) -> Result<Bytes> {
+        match self.msg_id {
+            sftp_msg::HANDLE => self.payload.get_bytes(),
+            sftp_msg::STATUS => decode_status(&mut self.payload)
+                .and(Err(Error::SftpProtocol("expected SSH_FXP_HANDLE"))),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    pub(super) fn into_data(mut self) -> Result<Option<Bytes>> {
+        match self.msg_id {
+            sftp_msg::DATA => self.payload.get_bytes().map(Some),
+            sftp_msg::STATUS => decode_status_eof(&mut self.payload).map(|_| None),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    pub(super) fn into_attrs(mut self) -> Result<FileAttrs> {
+        match self.msg_id {
+            sftp_msg::ATTRS => decode_attrs(&mut self.payload),
+            sftp_msg::STATUS => decode_status(&mut self.payload)
+                .and(Err(Error::SftpProtocol("expected SSH_FXP_ATTRS"))),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    pub(super) fn into_names(mut self) -> Result<Option<Vec<DirEntry>>> {
+        match self.msg_id {
+            sftp_msg::NAME => {
+                let count = self.payload.get_u32()?;
+                let mut entries = Vec::new();
+                for _ in 0..count {
+                    let filename = decode_lossy_string(&mut self.payload)?;
+                    let longname = decode_lossy_string(&mut self.payload)?;
+                    let attrs = decode_attrs(&mut self.payload)?;
+                    entries.push(DirEntry { filename, longname, attrs });
+                }
+                Ok(Some(entries))
+            },
+            sftp_msg::STATUS => decode_status_eof(&mut self.payload).map(|_| None),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn into_single_name(self) -> Result<String> {
+        match self.into_names()? {
+            Some(mut entries) if entries.len() == 1 => Ok(entries.remove(0).filename),
+            _ => Err(Error::SftpProtocol("expected SSH_FXP_NAME with a single name")),
+        }
+    }
+}
+
+/// Decodes `SSH_FXP_STATUS` and returns an error if the status is not `SSH_FX_OK`.
+fn decode_status(payload: &mut PacketDecode) -> Result<()> {
+    let status_code = payload.get_u32()?;
+    if status_code == sftp_status::OK {
+        return Ok(())
+    }
+
+    // some old servers do not send the message and the language tag
+    let (message, message_lang) =
+        if payload.remaining_len() > 0 {
+            (decode_lossy_string(payload)?, decode_lossy_string(payload)?)
+        } else {
+            (String::new(), String::new())
+        };
+    Err(Error::Sftp(SftpError { status_code, message, message_lang }))
+}
+
+/// Like [`decode_status()`], but expects the status `SSH_FX_EOF` instead of `SSH_FX_OK`.
+fn decode_status_eof(payload: &mut PacketDecode) -> Result<()> {
+    match decode_status(payload) {
+        Ok(()) => Err(Error::SftpProtocol("received unexpected SSH_FX_OK")),
+        Err(Error::Sftp(err)) if err.status_code == sftp_status::EOF => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+fn decode_lossy_string(payload: &mut PacketDecode) -> Result<String> {
+    Ok(String::from_utf8_lossy(&payload.get_bytes()?).into_owned())
+}
+
+fn encode_packet(msg_id: u8, req_id: Option<u32>, payload: &[u8]) -> Bytes {
+    let req_id_len = if req_id.is_some() { 4 } else { 0 };
+    let mut packet = PacketEncode::new();
+    packet.put_u32((1 + req_id_len + payload.len()).try_into().expect("SFTP packet too long"));
+    packet.put_u8(msg_id);
+    if let Some(req_id) = req_id {
+        packet.put_u32(req_id);
+    }
+    packet.put_raw(payload);
+    packet.finish()
+}
+
+fn decode_packet(recv_buf: &mut BytesMut) -> Result<Option<PacketDecode>> {
+    if recv_buf.len() < 4 {
+        return Ok(None)
+    }
+
+    let packet_len = u32::from_be_bytes(recv_buf[..4].try_into().unwrap()) as usize;
+    if packet_len > MAX_PACKET_LEN {
+        return Err(Error::SftpProtocol("received packet is too long"))
+    } else if packet_len == 0 {
+        return Err(Error::SftpProtocol("received empty packet"))
+    }
+
+    if recv_buf.len() < 4 + packet_len {
+        recv_buf.reserve(4 + packet_len - recv_buf.len());
+        return Ok(None)
+    }
+
+    recv_buf.advance(4);
+    Ok(Some(PacketDecode::new(recv_buf.split_to(packet_len).freeze())))
+}
+
+fn log_stderr(data: &[u8]) {
+    log::debug!("received SFTP stderr: {:?}", String::from_utf8_lossy(data));
+}
+
+
+/// Future that receives replies for an SFTP session.
+///
+/// This future reads the data produced by the server and dispatches the replies to the pending
+/// requests. You must poll this future, usually by spawning a task for it. The future completes
+/// when the underlying session is closed or when an error happens.
+pub struct SftpFuture {
+    sftp_st: Arc<Mutex<SftpState>>,
+    session_rx: SessionReceiver,
+    recv_buf: BytesMut,
+}
+
+impl SftpFuture {
+    fn poll_recv(&mut self, cx: &mut Context) -> Poll<Result<()>> {
+        loop {
+            while let Some(mut packet) = decode_packet(&mut self.recv_buf)? {
+                let msg_id = packet.get_u8()?;
+                if msg_id == sftp_msg::VERSION {
+                    return Poll::Ready(Err(Error::SftpProtocol("received unexpected SSH_FXP_VERSION")))
+                }
+
+                let req_id = packet.get_u32()?;
+                let reply_tx = self.sftp_st.lock().replies.remove(&req_id)
+                    .ok_or(Error::SftpProtocol("received reply to unknown request"))?;
+                let _: Result<_, _> = reply_tx.send(SftpReply { msg_id, payload: packet });
+            }
+
+            match ready!(self.session_rx.poll_recv(cx))? {
+                Some(SessionEvent::StdoutData(data)) => self.recv_buf.extend_from_slice(&data),
+                Some(SessionEvent::StderrData(data)) => log_stderr(&data),
+                Some(SessionEvent::Eof) | None => return Poll::Ready(Ok(())),
+                Some(_) => {},
+            }
+        }
+    }
+}
+
+impl Future for SftpFuture {
+    type Output = Result<()>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        let res = ready!(this.poll_recv(cx));
+        if let Err(ref err) = res {
+            log::debug!("SFTP future returned error: {:#}", err);
+        }
+
+        // the pending requests will never receive a reply, so we wake them up with an error
+        this.sftp_st.lock().replies.clear();
+        Poll::Ready(res)
+    }
+}
+
+
+/// Configuration of an SFTP session.
+///
+/// You should start from the [default][Default] instance, which has reasonable default
+/// configuration, and modify it according to your needs. You may also find the method
+/// [`SftpConfig::with()`] syntactically convenient.
+///
+/// This struct is `#[non_exhaustive]`, so we may add more fields without breaking backward
+/// compatibility.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SftpConfig {
+    /// Configuration of the underlying SSH session, used by [`Sftp::open()`].
+    pub channel: ChannelConfig,
+
+    /// Number of bytes that [`FileReader`][super::FileReader] asks for in a single read request.
+    ///
+    /// Most servers limit the size of read requests, OpenSSH allows up to 255 kB.
+    pub read_chunk_len: u32,
+
+    /// Number of bytes that [`FileWriter`][super::FileWriter] sends in a single write request.
+    pub write_chunk_len: u32,
+
+    /// Maximum number of read or write requests that [`FileReader`][super::FileReader] and
+    /// [`FileWriter`][super::FileWriter] keep in flight.
+    ///
+    /// Pipelining multiple requests allows us to fully utilize the connection even if the latency
+    /// is high.
+    pub max_pending_reqs: usize,
+}
+
+impl Default for SftpConfig {
+    fn default() -> Self {
+        SftpConfig {
+            channel: ChannelConfig::default(),
+            read_chunk_len: 32 * 1024,
+            write_chunk_len: 32 * 1024,
+            max_pending_reqs: 64,
+        }
+    }
+}
+
+impl SftpConfig {
+    /// Update the configuration in pseudo-builder pattern style.
+    ///
+    /// This method applies your closure to `self` and returns the mutated configuration.
+    pub fn with<F: FnOnce(&mut Self)>(mut self, f: F) -> Self {
+        f(&mut self);
+        self
+    }
+
+    pub(super) fn read_chunk_len(&self) -> u32 {
+        self.read_chunk_len.clamp(1, MAX_PACKET_LEN as u32 / 2)
+    }
+
+    pub(super) fn write_chunk_len(&self) -> usize {
+        self.write_chunk_len.clamp(1, MAX_PACKET_LEN as u32 / 2) as usize
+    }
+
+    pub(super) fn max_pending_reqs(&self) -> usize {
+        self.max_pending_reqs.max(1)
+    }
+}
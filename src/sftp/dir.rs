@@ -0,0 +1,56 @@
+use bytes::Bytes;
+use crate::codec::PacketEncode;
+use crate::codes::sftp_msg;
+use crate::error::Result;
+use super::attrs::FileAttrs;
+use super::client::Sftp;
+
+/// Handle to a directory opened on the SFTP server.
+///
+/// Use [`Sftp::open_dir()`] to open a directory and then call [`read()`][Self::read()] to read
+/// the entries. You should [`close()`][Self::close()] the directory when you are done with it.
+///
+/// You can cheaply clone this object, but all clones refer to the same handle on the server.
+#[derive(Clone)]
+pub struct Dir {
+    sftp: Sftp,
+    handle: Bytes,
+}
+
+/// An entry in a directory on the SFTP server.
+///
+/// This corresponds to one name in the `SSH_FXP_NAME` reply from the SFTP protocol (version 3),
+/// section 7.
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    /// Name of the file (without the directory path).
+    pub filename: String,
+    /// Human-readable description of the file, in the format produced by `ls -l`.
+    ///
+    /// The format is not specified and you should not attempt to parse it.
+    pub longname: String,
+    /// Attributes of the file.
+    pub attrs: FileAttrs,
+}
+
+impl Dir {
+    pub(super) fn new(sftp: Sftp, handle: Bytes) -> Self {
+        Self { sftp, handle }
+    }
+
+    /// Read a batch of entries from the directory (`SSH_FXP_READDIR`).
+    ///
+    /// Returns `None` when there are no more entries. The entries include `"."` and `".."`.
+    pub async fn read(&self) -> Result<Option<Vec<DirEntry>>> {
+        let mut payload = PacketEncode::new();
+        payload.put_bytes(&self.handle);
+        self.sftp.request(sftp_msg::READDIR, payload.finish()).await?.into_names()
+    }
+
+    /// Close the directory (`SSH_FXP_CLOSE`).
+    pub async fn close(&self) -> Result<()> {
+        let mut payload = PacketEncode::new();
+        payload.put_bytes(&self.handle);
+        self.sftp.request(sftp_msg::CLOSE, payload.finish()).await?.into_status()
+    }
+}
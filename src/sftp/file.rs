@@ -0,0 +1,407 @@
+use bytes::{Buf as _, Bytes};
+use futures_core::ready;
+use futures_core::future::BoxFuture;
+use std::cmp::min;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncBufRead, AsyncWrite};
+use crate::codec::PacketEncode;
+use crate::codes::{sftp_msg, sftp_open};
+use crate::error::Result;
+use super::attrs::{FileAttrs, encode_attrs};
+use super::client::{Sftp, SftpConfig};
+
+/// Handle to a file opened on the SFTP server.
+///
+/// Use [`Sftp::open_file()`] to open a file. You can read and write data at explicit offsets
+/// using [`read_at()`][Self::read_at()] and [`write_at()`][Self::write_at()], or you can use
+/// [`FileReader`] and [`FileWriter`] for `AsyncRead` and `AsyncWrite`.
+///
+/// The file is not closed automatically, you should [`close()`][Self::close()] the file when you
+/// are done with it.
+///
+/// You can cheaply clone this object, but all clones refer to the same handle on the server.
+#[derive(Clone)]
+pub struct File {
+    sftp: Sftp,
+    handle: Bytes,
+    config: SftpConfig,
+}
+
+impl File {
+    pub(super) fn new(sftp: Sftp, handle: Bytes, config: SftpConfig) -> Self {
+        Self { sftp, handle, config }
+    }
+
+    /// Get the [`Sftp`] that this file belongs to.
+    pub fn sftp(&self) -> Sftp {
+        self.sftp.clone()
+    }
+
+    /// Read at most `len` bytes from the given offset (`SSH_FXP_READ`).
+    ///
+    /// Returns `None` at the end of the file. The server may return fewer bytes than requested
+    /// even if the end of file was not reached.
+    pub async fn read_at(&self, offset: u64, len: u32) -> Result<Option<Bytes>> {
+        let mut payload = PacketEncode::new();
+        payload.put_bytes(&self.handle);
+        payload.put_u64(offset);
+        payload.put_u32(len);
+        self.sftp.request(sftp_msg::READ, payload.finish()).await?.into_data()
+    }
+
+    /// Write `data` at the given offset (`SSH_FXP_WRITE`).
+    ///
+    /// The data is sent in a single request, so you should keep the size of `data` reasonably
+    /// small (most servers refuse packets larger than 256 kB).
+    pub async fn write_at(&self, offset: u64, data: Bytes) -> Result<()> {
+        let mut payload = PacketEncode::new();
+        payload.put_bytes(&self.handle);
+        payload.put_u64(offset);
+        payload.put_bytes(&data);
+        self.sftp.request(sftp_msg::WRITE, payload.finish()).await?.into_status()
+    }
+
+    /// Get the attributes of the open file (`SSH_FXP_FSTAT`).
+    pub async fn stat(&self) -> Result<FileAttrs> {
+        let mut payload = PacketEncode::new();
+        payload.put_bytes(&self.handle);
+        self.sftp.request(sftp_msg::FSTAT, payload.finish()).await?.into_attrs()
+    }
+
+    /// Change the attributes of the open file (`SSH_FXP_FSETSTAT`).
+    ///
+    /// Only the attributes that are `Some` will be changed.
+    pub async fn setstat(&self, attrs: &FileAttrs) -> Result<()> {
+        let mut payload = PacketEncode::new();
+        payload.put_bytes(&self.handle);
+        encode_attrs(&mut payload, attrs);
+        self.sftp.request(sftp_msg::FSETSTAT, payload.finish()).await?.into_status()
+    }
+
+    /// Close the file (`SSH_FXP_CLOSE`).
+    ///
+    /// After the file is closed, all requests on this handle will fail.
+    pub async fn close(&self) -> Result<()> {
+        let mut payload = PacketEncode::new();
+        payload.put_bytes(&self.handle);
+        self.sftp.request(sftp_msg::CLOSE, payload.finish()).await?.into_status()
+    }
+
+    /// Create a [`FileReader`] that reads the file from the given offset.
+    pub fn reader(&self, offset: u64) -> FileReader {
+        FileReader::new(self.clone(), offset)
+    }
+
+    /// Create a [`FileWriter`] that writes to the file from the given offset.
+    pub fn writer(&self, offset: u64) -> FileWriter {
+        FileWriter::new(self.clone(), offset)
+    }
+}
+
+
+/// Options for opening a file with [`Sftp::open_file()`].
+///
+/// This mirrors `std::fs::OpenOptions`; the options are translated to the `pflags` field of the
+/// `SSH_FXP_OPEN` request.
+#[derive(Debug, Clone, Default)]
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    create: bool,
+    create_new: bool,
+    truncate: bool,
+    mode: Option<u32>,
+}
+
+impl OpenOptions {
+    /// Create a blank set of options (you must set at least `read` or `write`).
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Open the file for reading.
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    /// Open the file for writing.
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    /// Force all writes to append data at the end of the file.
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    /// Create the file if it does not exist.
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    /// Create the file, failing if it already exists.
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    /// Truncate the file to zero length if it exists.
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Set the permissions of a newly created file (e.g. `0o644`).
+    pub fn mode(&mut self, mode: u32) -> &mut Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    pub(super) fn pflags(&self) -> u32 {
+        let mut pflags = 0;
+        if self.read { pflags |= sftp_open::READ }
+        if self.write { pflags |= sftp_open::WRITE }
+        if self.append { pflags |= sftp_open::APPEND }
+        if self.create { pflags |= sftp_open::CREAT }
+        if self.create_new { pflags |= sftp_open::CREAT | sftp_open::EXCL }
+        if self.truncate { pflags |= sftp_open::TRUNC }
+        pflags
+    }
+
+    pub(super) fn attrs(&self) -> FileAttrs {
+        FileAttrs { permissions: self.mode, .. FileAttrs::default() }
+    }
+}
+
+
+/// Request that is in flight.
+///
+/// We poll the future as soon as we create it, so that the request is sent to the server
+/// immediately, and then we keep polling it until it completes, even if we are not yet interested
+/// in the result.
+struct Pending<T> {
+    fut: Option<BoxFuture<'static, Result<T>>>,
+    result: Option<Result<T>>,
+}
+
+impl<T> Pending<T> {
+    fn start(fut: BoxFuture<'static, Result<T>>, cx: &mut Context) -> Self {
+        let mut pending = Pending { fut: Some(fut), result: None };
+        pending.poll(cx);
+        pending
+    }
+
+    fn poll(&mut self, cx: &mut Context) {
+        if let Some(fut) = self.fut.as_mut() {
+            if let Poll::Ready(res) = fut.as_mut().poll(cx) {
+                self.result = Some(res);
+                self.fut = None;
+            }
+        }
+    }
+
+    fn take_result(&mut self) -> Option<Result<T>> {
+        self.result.take()
+    }
+}
+
+/// `AsyncRead` and `AsyncBufRead` for a file on the SFTP server.
+///
+/// The reader keeps up to `max_pending_reqs` read requests in flight, each for `read_chunk_len`
+/// bytes (see [`SftpConfig`]), so it can make use of the full bandwidth of the connection even if
+/// the latency is high. Create the reader with [`File::reader()`].
+pub struct FileReader {
+    file: File,
+    /// Offset of the next read request that we will send.
+    next_offset: u64,
+    /// Read requests that are in flight, ordered by offset.
+    pending: VecDeque<(u64, u32, Pending<Option<Bytes>>)>,
+    /// Data that we received from the server but which the caller hasn't read yet.
+    read_buf: Bytes,
+    /// Have we reached the end of file?
+    read_eof: bool,
+}
+
+impl FileReader {
+    /// Create [`FileReader`] that reads `file` starting at `offset`.
+    pub fn new(file: File, offset: u64) -> Self {
+        Self {
+            file,
+            next_offset: offset,
+            pending: VecDeque::new(),
+            read_buf: Bytes::new(),
+            read_eof: false,
+        }
+    }
+
+    /// Get a reference to the [`File`].
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    fn start_reads(&mut self, cx: &mut Context) {
+        let chunk_len = self.file.config.read_chunk_len();
+        while self.pending.len() < self.file.config.max_pending_reqs() {
+            let offset = self.next_offset;
+            let file = self.file.clone();
+            let fut = Box::pin(async move { file.read_at(offset, chunk_len).await });
+            self.pending.push_back((offset, chunk_len, Pending::start(fut, cx)));
+            self.next_offset += chunk_len as u64;
+        }
+    }
+}
+
+impl AsyncRead for FileReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let filled_buf = ready!(self.as_mut().poll_fill_buf(cx))?;
+        let fill_len = min(filled_buf.len(), buf.remaining());
+        buf.put_slice(&filled_buf[..fill_len]);
+        self.as_mut().consume(fill_len);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncBufRead for FileReader {
+    fn poll_fill_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::io::Result<&[u8]>> {
+        let this = self.get_mut();
+        loop {
+            if !this.read_buf.is_empty() || this.read_eof {
+                return Poll::Ready(Ok(&this.read_buf))
+            }
+
+            this.start_reads(cx);
+            for (_, _, pending) in this.pending.iter_mut() {
+                pending.poll(cx);
+            }
+
+            let (offset, len, mut pending) = match this.pending.front_mut() {
+                Some((_, _, pending)) if pending.result.is_some() => this.pending.pop_front().unwrap(),
+                _ => return Poll::Pending,
+            };
+
+            match pending.take_result().unwrap()? {
+                Some(data) if !data.is_empty() => {
+                    if data.len() < len as usize {
+                        // the server returned a short read, so the following requests are reading
+                        // from wrong offsets and we must send them again
+                        this.pending.clear();
+                        this.next_offset = offset + data.len() as u64;
+                    }
+                    this.read_buf = data;
+                },
+                Some(_) | None => {
+                    this.pending.clear();
+                    this.read_eof = true;
+                },
+            }
+        }
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.get_mut().read_buf.advance(amt);
+    }
+}
+
+/// `AsyncWrite` for a file on the SFTP server.
+///
+/// The writer splits the data into chunks of at most `write_chunk_len` bytes and keeps up to
+/// `max_pending_reqs` write requests in flight (see [`SftpConfig`]). Use `flush()` to wait
+/// until the server has confirmed all writes. Create the writer with [`File::writer()`].
+///
+/// Note that shutting down the writer does not close the file, please use [`File::close()`].
+pub struct FileWriter {
+    file: File,
+    /// Offset of the next write request that we will send.
+    next_offset: u64,
+    /// Write requests that are in flight.
+    pending: VecDeque<Pending<()>>,
+}
+
+impl FileWriter {
+    /// Create [`FileWriter`] that writes to `file` starting at `offset`.
+    pub fn new(file: File, offset: u64) -> Self {
+        Self { file, next_offset: offset, pending: VecDeque::new() }
+    }
+
+    /// Get a reference to the [`File`].
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    /// Polls the pending writes and removes the completed ones.
+    fn poll_pending(&mut self, cx: &mut Context) -> Result<()> {
+        let mut res = Ok(());
+        self.pending.retain_mut(|pending| {
+            pending.poll(cx);
+            match pending.take_result() {
+                Some(pending_res) => {
+                    if res.is_ok() {
+                        res = pending_res;
+                    }
+                    false
+                },
+                None => true,
+            }
+        });
+        res
+    }
+}
+
+impl AsyncWrite for FileWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        let this = self.get_mut();
+        this.poll_pending(cx)?;
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0))
+        } else if this.pending.len() >= this.file.config.max_pending_reqs() {
+            return Poll::Pending
+        }
+
+        let write_len = min(buf.len(), this.file.config.write_chunk_len());
+        let data = Bytes::copy_from_slice(&buf[..write_len]);
+        let offset = this.next_offset;
+        let file = this.file.clone();
+        let fut = Box::pin(async move { file.write_at(offset, data).await });
+        this.pending.push_back(Pending::start(fut, cx));
+        this.next_offset += write_len as u64;
+        Poll::Ready(Ok(write_len))
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        let this = self.get_mut();
+        this.poll_pending(cx)?;
+        if this.pending.is_empty() {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        self.poll_flush(cx)
+    }
+}
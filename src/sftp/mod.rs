@@ -0,0 +1,30 @@
+//! SFTP client.
+//!
+//! SFTP is the file transfer protocol that runs over an SSH session as the `"sftp"` subsystem. We
+//! implement version 3 of the protocol (described in `draft-ietf-secsh-filexfer-02`), which is the
+//! version supported by OpenSSH and most other servers.
+//!
+//! To start, use [`Sftp::open()`] to open a new SFTP session on an authenticated
+//! [`Client`][crate::Client], or [`Sftp::start()`] if you have already started the subsystem on a
+//! [`Session`][crate::Session] yourself. You will receive two objects:
+//!
+//! - [`Sftp`] is the handle that you use to send requests to the server. You can cheaply clone
+//!   it and send requests from multiple tasks in parallel; the requests are pipelined over the
+//!   session.
+//! - [`SftpFuture`] is a future that receives the replies from the server. You must poll this
+//!   future, usually by spawning a task for it.
+//!
+//! Files opened with [`Sftp::open_file()`] are represented by [`File`]. You can read and write
+//! data at explicit offsets, or you can use [`FileReader`] and [`FileWriter`], which implement
+//! `AsyncRead` and `AsyncWrite` and keep multiple requests in flight to make efficient use of the
+//! connection.
+pub use self::attrs::{FileAttrs, FileType};
+pub use self::client::{Sftp, SftpFuture, SftpConfig};
+pub use self::dir::{Dir, DirEntry};
+pub use self::file::{File, FileReader, FileWriter, OpenOptions};
+pub use crate::error::SftpError;
+
+mod attrs;
+mod client;
+mod dir;
+mod file;
@@ -0,0 +1,264 @@
+//! Dynamic port forwarding using SOCKS5.
+//!
+//! This module implements the local side of dynamic port forwarding (`ssh -D`): we run a small
+//! SOCKS5 server (RFC 1928) on a local socket, and for every `CONNECT` request that we receive, we
+//! ask the SSH server to connect to the requested address using
+//! [`Client::connect_tunnel()`][crate::Client::connect_tunnel()]. Then we copy the data between
+//! the local connection and the tunnel. This allows programs such as web browsers to reach hosts
+//! that are only reachable from the SSH server.
+//!
+//! We support only the `CONNECT` command with no authentication, with IPv4, IPv6 and domain name
+//! addresses. Domain names are resolved by the SSH server.
+//!
+//! Use [`SocksListener`] to accept connections on a `TcpListener` and then run each
+//! [`SocksConnection`] (typically in a new task), or use [`serve()`] to handle a single SOCKS5
+//! connection on any stream. We don't spawn any tasks ourselves, so every connection reports its
+//! own errors.
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncReadExt as _, AsyncWriteExt as _};
+use tokio::net::{TcpListener, TcpStream};
+use crate::client::{Client, ChannelConfig, TunnelStream};
+use crate::codes;
+use crate::error::{Error, Result};
+
+const VERSION: u8 = 5;
+
+const METHOD_NO_AUTH: u8 = 0x00;
+const METHOD_NO_ACCEPTABLE: u8 = 0xff;
+
+const CMD_CONNECT: u8 = 1;
+
+const ATYP_IPV4: u8 = 1;
+const ATYP_DOMAIN: u8 = 3;
+const ATYP_IPV6: u8 = 4;
+
+const REP_SUCCEEDED: u8 = 0;
+const REP_GENERAL_FAILURE: u8 = 1;
+const REP_NOT_ALLOWED: u8 = 2;
+const REP_CONNECTION_REFUSED: u8 = 5;
+const REP_COMMAND_NOT_SUPPORTED: u8 = 7;
+const REP_ADDRESS_NOT_SUPPORTED: u8 = 8;
+
+/// Listener for SOCKS5 connections that are forwarded over SSH.
+///
+/// This object wraps a `TcpListener` and a [`Client`]. Call [`accept()`][Self::accept()] in a
+/// loop to receive [`SocksConnection`]s and run each of them in a new task.
+pub struct SocksListener {
+    client: Client,
+    config: ChannelConfig,
+    listener: TcpListener,
+}
+
+impl SocksListener {
+    /// Create a listener that forwards connections from `listener` over `client`.
+    ///
+    /// The `config` is used for every tunnel that we open.
+    pub fn new(client: Client, config: ChannelConfig, listener: TcpListener) -> SocksListener {
+        SocksListener { client, config, listener }
+    }
+
+    /// Get the local address that we are listening on.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener.local_addr().map_err(Error::SocksIo)
+    }
+
+    /// Accept a new SOCKS5 connection.
+    ///
+    /// This only accepts the TCP connection, you must then call [`SocksConnection::run()`] to
+    /// perform the SOCKS5 handshake and forward the connection.
+    pub async fn accept(&self) -> Result<SocksConnection> {
+        let (stream, peer_addr) = self.listener.accept().await.map_err(Error::SocksIo)?;
+        Ok(SocksConnection {
+            client: self.client.clone(),
+            config: self.config.clone(),
+            stream,
+            peer_addr,
+        })
+    }
+}
+
+/// A SOCKS5 connection accepted by [`SocksListener`].
+pub struct SocksConnection {
+    client: Client,
+    config: ChannelConfig,
+    stream: TcpStream,
+    peer_addr: SocketAddr,
+}
+
+impl SocksConnection {
+    /// Get the address of the local peer that connected to us.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    /// Handle the connection.
+    ///
+    /// This calls [`serve()`] with the address of the local peer as the originator address of the
+    /// tunnel, so it returns after the connection is closed.
+    pub async fn run(self) -> Result<()> {
+        let originator_addr = (self.peer_addr.ip().to_string(), self.peer_addr.port());
+        serve(&self.client, self.config, self.stream, originator_addr).await
+    }
+}
+
+/// Handle a single SOCKS5 connection on `stream` and forward it over `client`.
+///
+/// We perform the SOCKS5 handshake, open a tunnel to the requested address using
+/// [`Client::connect_tunnel()`] with the given `config` and `originator_addr`, and then copy the
+/// data in both directions until both sides are closed.
+///
+/// If the local peer sends a request that we don't support, we return [`Error::SocksProtocol`].
+/// If the SSH server refuses to open the tunnel, we report the failure to the local peer and
+/// return the error from [`Client::connect_tunnel()`].
+pub async fn serve<S>(
+    client: &Client,
+    config: ChannelConfig,
+    mut stream: S,
+    originator_addr: (String, u16),
+) -> Result<()>
+    where S: AsyncRead + AsyncWrite + Unpin
+{
+    let connect_addr = read_request(&mut stream).await?;
+    log::debug!("SOCKS5 request to connect to {:?}", connect_addr);
+
+    let (tunnel, tunnel_rx) = match client.connect_tunnel(config, connect_addr, originator_addr).await {
+        Ok(tunnel) => tunnel,
+        Err(err) => {
+            let rep = match &err {
+                Error::ChannelOpen(open_err) => match open_err.reason_code {
+                    codes::open::ADMINISTRATIVELY_PROHIBITED => REP_NOT_ALLOWED,
+                    codes::open::CONNECT_FAILED => REP_CONNECTION_REFUSED,
+                    _ => REP_GENERAL_FAILURE,
+                },
+                _ => REP_GENERAL_FAILURE,
+            };
+            let _: Result<_> = write_reply(&mut stream, rep).await;
+            return Err(err)
+        },
+    };
+
+    write_reply(&mut stream, REP_SUCCEEDED).await?;
+    let mut tunnel_stream = TunnelStream::new(tunnel, tunnel_rx);
+    tokio::io::copy_bidirectional(&mut tunnel_stream, &mut stream).await
+        .map_err(Error::SocksIo)?;
+    Ok(())
+}
+
+async fn read_request<S>(stream: &mut S) -> Result<(String, u16)>
+    where S: AsyncRead + AsyncWrite + Unpin
+{
+    // RFC 1928, section 3 (Procedure for TCP-based clients)
+    let mut header = [0; 2];
+    stream.read_exact(&mut header).await.map_err(Error::SocksIo)?;
+    if header[0] != VERSION {
+        return Err(Error::SocksProtocol("unsupported SOCKS version"))
+    }
+    let mut methods = vec![0; header[1] as usize];
+    stream.read_exact(&mut methods).await.map_err(Error::SocksIo)?;
+    if !methods.contains(&METHOD_NO_AUTH) {
+        stream.write_all(&[VERSION, METHOD_NO_ACCEPTABLE]).await.map_err(Error::SocksIo)?;
+        return Err(Error::SocksProtocol("client does not support the \"no authentication\" method"))
+    }
+    stream.write_all(&[VERSION, METHOD_NO_AUTH]).await.map_err(Error::SocksIo)?;
+
+    // RFC 1928, section 4 (Requests)
+    let mut request = [0; 4];
+    stream.read_exact(&mut request).await.map_err(Error::SocksIo)?;
+    if request[0] != VERSION {
+        return Err(Error::SocksProtocol("unsupported SOCKS version in request"))
+    }
+
+    let host = match request[3] {
+        ATYP_IPV4 => {
+            let mut addr = [0; 4];
+            stream.read_exact(&mut addr).await.map_err(Error::SocksIo)?;
+            Ipv4Addr::from(addr).to_string()
+        },
+        ATYP_IPV6 => {
+            let mut addr = [0; 16];
+            stream.read_exact(&mut addr).await.map_err(Error::SocksIo)?;
+            Ipv6Addr::from(addr).to_string()
+        },
+        ATYP_DOMAIN => {
+            let len = stream.read_u8().await.map_err(Error::SocksIo)?;
+            let mut domain = vec![0; len as usize];
+            stream.read_exact(&mut domain).await.map_err(Error::SocksIo)?;
+            String::from_utf8(domain).map_err(|_| Error::SocksProtocol("domain name is not valid UTF-8"))?
+        },
+        _ => {
+            write_reply(stream, REP_ADDRESS_NOT_SUPPORTED).await?;
+            return Err(Error::SocksProtocol("unsupported address type"))
+        },
+    };
+    let port = stream.read_u16().await.map_err(Error::SocksIo)?;
+
+    if request[1] != CMD_CONNECT {
+        write_reply(stream, REP_COMMAND_NOT_SUPPORTED).await?;
+        return Err(Error::SocksProtocol("unsupported command"))
+    }
+
+    Ok((host, port))
+}
+
+async fn write_reply<S>(stream: &mut S, rep: u8) -> Result<()>
+    where S: AsyncWrite + Unpin
+{
+    // RFC 1928, section 6 (Replies); we don't know the address that the server bound, so we
+    // always send 0.0.0.0:0, like OpenSSH
+    let reply = [VERSION, rep, 0, ATYP_IPV4, 0, 0, 0, 0, 0, 0];
+    stream.write_all(&reply).await.map_err(Error::SocksIo)?;
+    stream.flush().await.map_err(Error::SocksIo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn request(msg: &[u8]) -> (Result<(String, u16)>, Vec<u8>) {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        client.write_all(msg).await.unwrap();
+        drop(client.shutdown().await);
+        let res = read_request(&mut server).await;
+        drop(server);
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).await.unwrap();
+        (res, response)
+    }
+
+    #[tokio::test]
+    async fn test_read_request() {
+        let (res, response) = request(&[5, 1, 0, 5, 1, 0, 1, 10, 0, 0, 1, 0x1f, 0x90]).await;
+        assert_eq!(res.unwrap(), ("10.0.0.1".into(), 8080));
+        assert_eq!(response, [5, 0]);
+
+        let mut msg = vec![5, 2, 2, 0, 5, 1, 0, 3, 11];
+        msg.extend_from_slice(b"example.com");
+        msg.extend_from_slice(&[0, 80]);
+        let (res, _) = request(&msg).await;
+        assert_eq!(res.unwrap(), ("example.com".into(), 80));
+
+        let mut msg = vec![5, 1, 0, 5, 1, 0, 4];
+        msg.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        msg.extend_from_slice(&[0, 22]);
+        let (res, _) = request(&msg).await;
+        assert_eq!(res.unwrap(), ("::1".into(), 22));
+    }
+
+    #[tokio::test]
+    async fn test_read_request_unsupported() {
+        let (res, response) = request(&[5, 1, 2]).await;
+        assert!(matches!(res, Err(Error::SocksProtocol(_))));
+        assert_eq!(response, [5, 0xff]);
+
+        let (res, response) = request(&[5, 1, 0, 5, 2, 0, 1, 10, 0, 0, 1, 0, 80]).await;
+        assert!(matches!(res, Err(Error::SocksProtocol(_))));
+        assert_eq!(response, [5, 0, 5, 7, 0, 1, 0, 0, 0, 0, 0, 0]);
+
+        let (res, response) = request(&[5, 1, 0, 5, 1, 0, 9]).await;
+        assert!(matches!(res, Err(Error::SocksProtocol(_))));
+        assert_eq!(response, [5, 0, 5, 8, 0, 1, 0, 0, 0, 0, 0, 0]);
+
+        let (res, _) = request(&[4, 1, 0, 80, 10, 0, 0, 1, 0]).await;
+        assert!(matches!(res, Err(Error::SocksProtocol(_))));
+    }
+}
@@ -0,0 +1,941 @@
+//! Support for OpenSSH-compatible `ssh_config` file.
+//!
+//! The `ssh_config` file (usually `~/.ssh/config`) assigns options such as the real host name,
+//! port, user name or identity files to the hosts that you connect to. Use [`Config`] to parse
+//! the file and [`Config::resolve()`] to obtain the [`HostConfig`] for a particular host.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::error::{Error, Result};
+use crate::util::wildcard_matches;
+
+/// Maximal depth of nested `Include` directives.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Representation of an OpenSSH-compatible `ssh_config` file.
+///
+/// The file is a list of options, which are grouped into blocks that start with `Host` or `Match`
+/// directives. The options in a block apply only to hosts that match the block, options before
+/// the first block apply to all hosts. When we [resolve][Self::resolve()] a host, the first
+/// obtained value of each option is used, so more specific blocks should come first in the file.
+///
+/// We support these features of OpenSSH:
+///
+/// - `Host` blocks with patterns that may contain wildcards `*` and `?` and may be negated with
+///   `!`.
+/// - `Match` blocks with criteria `all`, `host`, `originalhost`, `user`, `localuser`, `canonical`
+///   and `final`, which may be negated with `!`. Criteria `exec` and `localnetwork` are parsed, but
+///   never match.
+/// - `Include` directives with wildcards in file names. Relative paths are resolved against the
+///   directory passed to [`decode()`][Self::decode()] (usually `~/.ssh`).
+/// - Expansion of tokens `%%`, `%d`, `%h`, `%n`, `%p`, `%r` and `%u` and of the leading `~` in the
+///   options of [`HostConfig`] that accept them.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    items: Vec<Item>,
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    Option(Directive),
+    Host(Vec<Pattern>),
+    Match(Vec<Criterion>),
+    Include(Vec<Item>),
+}
+
+#[derive(Debug, Clone)]
+struct Directive {
+    keyword: String,
+    args: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct Pattern {
+    is_negated: bool,
+    pattern: String,
+}
+
+#[derive(Debug, Clone)]
+struct Criterion {
+    is_negated: bool,
+    kind: CriterionKind,
+}
+
+#[derive(Debug, Clone)]
+enum CriterionKind {
+    All,
+    Canonical,
+    Final,
+    Host(Vec<Pattern>),
+    OriginalHost(Vec<Pattern>),
+    User(Vec<Pattern>),
+    LocalUser(Vec<Pattern>),
+    Unsupported,
+}
+
+/// Options for a host resolved from [`Config`].
+///
+/// You obtain this object from [`Config::resolve()`]. Options that were not specified in the
+/// file have their default value (such as port 22) or are empty (`None` or empty `Vec`). Options
+/// that don't have a field in this struct are available in [`options`][Self::options].
+///
+/// This struct is `#[non_exhaustive]`, so we may add more fields without breaking backward
+/// compatibility.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct HostConfig {
+    /// The real host name to connect to (`HostName`).
+    ///
+    /// If the option is not specified, this is the host that you passed to
+    /// [`Config::resolve()`].
+    pub host_name: String,
+
+    /// The port to connect to (`Port`), 22 by default.
+    pub port: u16,
+
+    /// The user name to log in with (`User`).
+    ///
+    /// If this is `None`, you should use the name of the local user.
+    pub user: Option<String>,
+
+    /// Files with private keys (`IdentityFile`), with expanded tokens.
+    pub identity_files: Vec<PathBuf>,
+
+    /// Files with user certificates (`CertificateFile`), with expanded tokens.
+    pub certificate_files: Vec<PathBuf>,
+
+    /// Use only the configured identity files, not the keys from the agent (`IdentitiesOnly`).
+    pub identities_only: Option<bool>,
+
+    /// Socket of the SSH agent (`IdentityAgent`), with expanded tokens.
+    pub identity_agent: Option<PathBuf>,
+
+    /// Jump hosts to connect through (`ProxyJump`), in the order in which they should be used.
+    ///
+    /// Each jump host has the form `[user@]host[:port]` or `ssh://[user@]host[:port]`. If the
+    /// option is set to `none`, this is empty.
+    pub proxy_jump: Vec<String>,
+
+    /// Command to connect to the server (`ProxyCommand`), with expanded tokens.
+    pub proxy_command: Option<String>,
+
+    /// Ciphers in the OpenSSH format (`Ciphers`), such as `"aes128-ctr,aes256-ctr"` or
+    /// `"-*-cbc"`.
+    pub ciphers: Option<String>,
+
+    /// MACs in the OpenSSH format (`MACs`).
+    pub macs: Option<String>,
+
+    /// Key exchange algorithms in the OpenSSH format (`KexAlgorithms`).
+    pub kex_algorithms: Option<String>,
+
+    /// Host key algorithms in the OpenSSH format (`HostKeyAlgorithms`).
+    pub host_key_algorithms: Option<String>,
+
+    /// Public key algorithms for authentication in the OpenSSH format
+    /// (`PubkeyAcceptedAlgorithms`).
+    pub pubkey_accepted_algorithms: Option<String>,
+
+    /// Enable compression (`Compression`).
+    pub compression: Option<bool>,
+
+    /// Files with known host keys (`UserKnownHostsFile`), with expanded tokens.
+    pub user_known_hosts_files: Vec<PathBuf>,
+
+    /// How to check the host keys (`StrictHostKeyChecking`), such as `"yes"` or `"accept-new"`.
+    pub strict_host_key_checking: Option<String>,
+
+    /// Timeout for connecting to the server (`ConnectTimeout`).
+    pub connect_timeout: Option<Duration>,
+
+    /// Interval of keepalive messages (`ServerAliveInterval`).
+    pub server_alive_interval: Option<Duration>,
+
+    /// Number of keepalive messages without response before we disconnect
+    /// (`ServerAliveCountMax`).
+    pub server_alive_count_max: Option<u32>,
+
+    /// Local port forwardings (`LocalForward`), each with the arguments joined by a space.
+    pub local_forwards: Vec<String>,
+
+    /// Remote port forwardings (`RemoteForward`), each with the arguments joined by a space.
+    pub remote_forwards: Vec<String>,
+
+    /// Dynamic port forwardings (`DynamicForward`).
+    pub dynamic_forwards: Vec<String>,
+
+    /// All other options, indexed by the lowercase keyword.
+    ///
+    /// The values are not processed in any way, so tokens are not expanded.
+    pub options: BTreeMap<String, Vec<String>>,
+}
+
+/// Local environment used when resolving a host in [`Config::resolve_in()`].
+///
+/// This struct is `#[non_exhaustive]`, so we may add more fields without breaking backward
+/// compatibility.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct Environment {
+    /// The name of the local user (token `%u`).
+    pub local_user: String,
+
+    /// The home directory of the local user (token `%d` and `~`).
+    pub home_dir: Option<PathBuf>,
+}
+
+impl Environment {
+    /// Obtain the environment from the environment variables `USER` (or `LOGNAME`) and `HOME`.
+    pub fn current() -> Environment {
+        let local_user = std::env::var("USER")
+            .or_else(|_| std::env::var("LOGNAME"))
+            .unwrap_or_default();
+        let home_dir = std::env::var_os("HOME").map(PathBuf::from);
+        Environment { local_user, home_dir }
+    }
+}
+
+impl Config {
+    /// Creates a new empty [`Config`].
+    pub fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    /// Parses a file in OpenSSH `ssh_config` format.
+    ///
+    /// Relative paths in `Include` directives are resolved against `include_dir`, which should be
+    /// `~/.ssh` for the user configuration file and `/etc/ssh` for the system configuration file.
+    /// A leading `~` in these paths is expanded to the [`home_dir`][Environment::home_dir] of
+    /// `env`. The included files are read immediately; files that don't exist are ignored.
+    pub fn decode(data: &str, include_dir: &Path, env: &Environment) -> Result<Self> {
+        let items = decode_file(data, "config", include_dir, env, 0)?;
+        Ok(Self { items })
+    }
+
+    /// Reads and parses the file at `path`.
+    ///
+    /// See [`decode()`][Self::decode()] for the meaning of `include_dir` and `env`.
+    pub fn read_file(path: &Path, include_dir: &Path, env: &Environment) -> Result<Self> {
+        let items = read_file(path, include_dir, env, 0)?;
+        Ok(Self { items })
+    }
+
+    /// Reads and parses the user configuration file `~/.ssh/config`.
+    ///
+    /// The home directory is obtained from the [current environment][Environment::current()]. If
+    /// the file does not exist, returns an empty [`Config`].
+    pub fn read_user() -> Result<Self> {
+        let env = Environment::current();
+        let Some(home_dir) = env.home_dir.as_ref() else {
+            return Ok(Self::new())
+        };
+        let include_dir = home_dir.join(".ssh");
+        let path = include_dir.join("config");
+        if !path.exists() {
+            return Ok(Self::new())
+        }
+        Self::read_file(&path, &include_dir, &env)
+    }
+
+    /// Resolves the options for `host`, using the [current environment][Environment::current()].
+    pub fn resolve(&self, host: &str) -> Result<HostConfig> {
+        self.resolve_in(host, &Environment::current())
+    }
+
+    /// Resolves the options for `host` in the given local environment.
+    ///
+    /// The `host` is the name that the user wants to connect to (the `host` in `ssh host`). It is
+    /// matched against the `Host` blocks, and it is also used as the default
+    /// [`host_name`][HostConfig::host_name].
+    ///
+    /// This method fails if an option has an invalid value or uses an unsupported token.
+    pub fn resolve_in(&self, host: &str, env: &Environment) -> Result<HostConfig> {
+        let host = host.to_lowercase();
+        let mut resolver = Resolver { host: &host, env, options: Vec::new() };
+        resolver.eval_items(&self.items, true);
+        resolver.build()
+    }
+}
+
+fn read_file(
+    path: &Path,
+    include_dir: &Path,
+    env: &Environment,
+    depth: usize,
+) -> Result<Vec<Item>> {
+    let data = std::fs::read_to_string(path).map_err(|err| {
+        Error::SshConfig(format!("could not read {}: {}", path.display(), err))
+    })?;
+    decode_file(&data, &path.display().to_string(), include_dir, env, depth)
+}
+
+fn decode_file(
+    data: &str,
+    name: &str,
+    include_dir: &Path,
+    env: &Environment,
+    depth: usize,
+) -> Result<Vec<Item>> {
+    let mut items = Vec::new();
+    for (line_i, line) in data.lines().enumerate() {
+        let item = decode_line(line, include_dir, env, depth)
+            .map_err(|msg| Error::SshConfig(format!("{}:{}: {}", name, line_i + 1, msg)))?;
+        items.extend(item);
+    }
+    Ok(items)
+}
+
+fn decode_line(
+    line: &str,
+    include_dir: &Path,
+    env: &Environment,
+    depth: usize,
+) -> Result<Option<Item>, String> {
+    let Some((keyword, args)) = split_line(line)? else {
+        return Ok(None)
+    };
+
+    let item = match keyword.as_str() {
+        "host" => {
+            if args.is_empty() {
+                return Err("missing patterns in Host".into())
+            }
+            Item::Host(args.iter().map(|arg| decode_pattern(&arg.to_lowercase())).collect())
+        },
+        "match" => Item::Match(decode_criteria(&args)?),
+        "include" => {
+            if args.is_empty() {
+                return Err("missing path in Include".into())
+            }
+            if depth >= MAX_INCLUDE_DEPTH {
+                return Err("too many nested Include directives".into())
+            }
+
+            let mut items = Vec::new();
+            for arg in args.iter() {
+                let path = expand_tilde(arg, env.home_dir.as_deref());
+                let path = include_dir.join(path);
+                for path in expand_glob(&path) {
+                    if path.is_file() {
+                        items.extend(read_file(&path, include_dir, env, depth + 1)
+                            .map_err(|err| err.to_string())?);
+                    }
+                }
+            }
+            Item::Include(items)
+        },
+        _ => {
+            if args.is_empty() {
+                return Err(format!("missing argument for {:?}", keyword))
+            }
+            Item::Option(Directive { keyword, args })
+        },
+    };
+    Ok(Some(item))
+}
+
+/// Splits the line into a lowercase keyword and arguments.
+fn split_line(line: &str) -> Result<Option<(String, Vec<String>)>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None)
+    }
+
+    // the keyword is separated from the arguments by whitespace and/or a single '='
+    let keyword_len = line.find(|c: char| c.is_ascii_whitespace() || c == '=').unwrap_or(line.len());
+    let keyword = line[..keyword_len].to_ascii_lowercase();
+    let mut rest = line[keyword_len..].trim_start();
+    if let Some(r) = rest.strip_prefix('=') {
+        rest = r.trim_start();
+    }
+
+    let mut args = Vec::new();
+    let mut chars = rest.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+        let Some(&c) = chars.peek() else { break };
+        if c == '#' {
+            break
+        }
+
+        let mut arg = String::new();
+        if c == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => arg.push(c),
+                    None => return Err("unterminated quoted argument".into()),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_ascii_whitespace()) {
+                arg.push(c);
+            }
+        }
+        args.push(arg);
+    }
+
+    Ok(Some((keyword, args)))
+}
+
+fn decode_pattern(pattern: &str) -> Pattern {
+    match pattern.strip_prefix('!') {
+        Some(p) => Pattern { is_negated: true, pattern: p.into() },
+        None => Pattern { is_negated: false, pattern: pattern.into() },
+    }
+}
+
+fn decode_pattern_list(list: &str) -> Vec<Pattern> {
+    list.split(',').filter(|p| !p.is_empty()).map(decode_pattern).collect()
+}
+
+fn decode_criteria(args: &[String]) -> Result<Vec<Criterion>, String> {
+    let mut criteria = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (is_negated, name) = match arg.strip_prefix('!') {
+            Some(name) => (true, name),
+            None => (false, arg.as_str()),
+        };
+        let name = name.to_ascii_lowercase();
+
+        let mut next_arg = || args.next().ok_or_else(|| format!("missing argument for Match {:?}", name));
+        let kind = match name.as_str() {
+            "all" => CriterionKind::All,
+            "canonical" => CriterionKind::Canonical,
+            "final" => CriterionKind::Final,
+            "host" => CriterionKind::Host(decode_pattern_list(&next_arg()?.to_lowercase())),
+            "originalhost" => CriterionKind::OriginalHost(decode_pattern_list(&next_arg()?.to_lowercase())),
+            "user" => CriterionKind::User(decode_pattern_list(next_arg()?)),
+            "localuser" => CriterionKind::LocalUser(decode_pattern_list(next_arg()?)),
+            "exec" | "localnetwork" | "tagged" => {
+                next_arg()?;
+                CriterionKind::Unsupported
+            },
+            _ => return Err(format!("unknown Match criterion {:?}", name)),
+        };
+        criteria.push(Criterion { is_negated, kind });
+    }
+
+    if criteria.is_empty() {
+        return Err("missing criteria in Match".into())
+    }
+    Ok(criteria)
+}
+
+struct Resolver<'a> {
+    host: &'a str,
+    env: &'a Environment,
+    /// Options that apply to the host, in the order of appearance.
+    options: Vec<&'a Directive>,
+}
+
+impl<'a> Resolver<'a> {
+    fn eval_items(&mut self, items: &'a [Item], mut active: bool) {
+        for item in items.iter() {
+            match item {
+                Item::Option(directive) => if active {
+                    self.options.push(directive);
+                },
+                Item::Host(patterns) => active = patterns_match(patterns, self.host),
+                Item::Match(criteria) => active = criteria.iter().all(|c| self.criterion_matches(c)),
+                Item::Include(items) => if active {
+                    // the included files may contain blocks, but these don't affect the rest of
+                    // the including file
+                    self.eval_items(items, active);
+                },
+            }
+        }
+    }
+
+    fn criterion_matches(&self, criterion: &Criterion) -> bool {
+        let matches = match &criterion.kind {
+            CriterionKind::All | CriterionKind::Final => true,
+            CriterionKind::Canonical | CriterionKind::Unsupported => false,
+            CriterionKind::Host(patterns) => {
+                let host_name = self.first("hostname")
+                    .map(|args| expand_host_name(&args[0], self.host).to_lowercase())
+                    .unwrap_or_else(|| self.host.into());
+                patterns_match(patterns, &host_name)
+            },
+            CriterionKind::OriginalHost(patterns) => patterns_match(patterns, self.host),
+            CriterionKind::User(patterns) => {
+                let user = self.first("user").map(|args| args[0].as_str())
+                    .unwrap_or(&self.env.local_user);
+                patterns_match(patterns, user)
+            },
+            CriterionKind::LocalUser(patterns) => patterns_match(patterns, &self.env.local_user),
+        };
+        matches != criterion.is_negated
+    }
+
+    fn first(&self, keyword: &str) -> Option<&'a [String]> {
+        self.options.iter().find(|d| d.keyword == keyword).map(|d| d.args.as_slice())
+    }
+
+    fn all(&self, keyword: &'a str) -> impl Iterator<Item = &'a [String]> + '_ {
+        self.options.iter().filter(move |d| d.keyword == keyword).map(|d| d.args.as_slice())
+    }
+
+    fn build(self) -> Result<HostConfig> {
+        let mut cfg = HostConfig {
+            host_name: self.host.into(),
+            port: 22,
+            ..HostConfig::default()
+        };
+
+        if let Some(args) = self.first("hostname") {
+            cfg.host_name = expand_host_name(&args[0], self.host);
+        }
+        if let Some(args) = self.first("port") {
+            cfg.port = args[0].parse().map_err(|_| invalid_value("Port", &args[0]))?;
+        }
+        cfg.user = self.first("user").map(|args| args[0].clone());
+
+        let tokens = Tokens {
+            host_name: &cfg.host_name,
+            original_host: self.host,
+            port: cfg.port,
+            user: cfg.user.as_deref().unwrap_or(&self.env.local_user),
+            env: self.env,
+        };
+
+        for args in self.all("identityfile") {
+            cfg.identity_files.push(tokens.expand_path(&args[0])?);
+        }
+        for args in self.all("certificatefile") {
+            cfg.certificate_files.push(tokens.expand_path(&args[0])?);
+        }
+        if let Some(args) = self.first("identitiesonly") {
+            cfg.identities_only = Some(decode_bool("IdentitiesOnly", &args[0])?);
+        }
+        if let Some(args) = self.first("identityagent") {
+            cfg.identity_agent = Some(tokens.expand_path(&args[0])?);
+        }
+        if let Some(args) = self.first("proxyjump") {
+            if !args[0].eq_ignore_ascii_case("none") {
+                cfg.proxy_jump = args[0].split(',').map(|s| s.into()).collect();
+            }
+        }
+        if let Some(args) = self.first("proxycommand") {
+            let command = args.join(" ");
+            if !command.eq_ignore_ascii_case("none") {
+                cfg.proxy_command = Some(tokens.expand(&command)?);
+            }
+        }
+
+        cfg.ciphers = self.first("ciphers").map(|args| args[0].clone());
+        cfg.macs = self.first("macs").map(|args| args[0].clone());
+        cfg.kex_algorithms = self.first("kexalgorithms").map(|args| args[0].clone());
+        cfg.host_key_algorithms = self.first("hostkeyalgorithms").map(|args| args[0].clone());
+        cfg.pubkey_accepted_algorithms = self.first("pubkeyacceptedalgorithms")
+            .or_else(|| self.first("pubkeyacceptedkeytypes"))
+            .map(|args| args[0].clone());
+
+        if let Some(args) = self.first("compression") {
+            cfg.compression = Some(decode_bool("Compression", &args[0])?);
+        }
+        if let Some(args) = self.first("userknownhostsfile") {
+            if !args[0].eq_ignore_ascii_case("none") {
+                cfg.user_known_hosts_files = args.iter()
+                    .map(|arg| tokens.expand_path(arg))
+                    .collect::<Result<_>>()?;
+            }
+        }
+        cfg.strict_host_key_checking = self.first("stricthostkeychecking").map(|args| args[0].clone());
+
+        if let Some(args) = self.first("connecttimeout") {
+            cfg.connect_timeout = Some(decode_time("ConnectTimeout", &args[0])?);
+        }
+        if let Some(args) = self.first("serveraliveinterval") {
+            cfg.server_alive_interval = Some(decode_time("ServerAliveInterval", &args[0])?);
+        }
+        if let Some(args) = self.first("serveralivecountmax") {
+            cfg.server_alive_count_max = Some(args[0].parse()
+                .map_err(|_| invalid_value("ServerAliveCountMax", &args[0]))?);
+        }
+
+        cfg.local_forwards = self.all("localforward").map(|args| args.join(" ")).collect();
+        cfg.remote_forwards = self.all("remoteforward").map(|args| args.join(" ")).collect();
+        cfg.dynamic_forwards = self.all("dynamicforward").map(|args| args.join(" ")).collect();
+
+        for directive in self.options.iter() {
+            if !TYPED_KEYWORDS.contains(&directive.keyword.as_str()) {
+                cfg.options.entry(directive.keyword.clone()).or_insert_with(|| directive.args.clone());
+            }
+        }
+
+        Ok(cfg)
+    }
+}
+
+/// Keywords that are represented by fields of [`HostConfig`].
+static TYPED_KEYWORDS: &[&str] = &[
+    "hostname", "port", "user", "identityfile", "certificatefile", "identitiesonly",
+    "identityagent", "proxyjump", "proxycommand", "ciphers", "macs", "kexalgorithms",
+    "hostkeyalgorithms", "pubkeyacceptedalgorithms", "pubkeyacceptedkeytypes", "compression",
+    "userknownhostsfile", "stricthostkeychecking", "connecttimeout", "serveraliveinterval",
+    "serveralivecountmax", "localforward", "remoteforward", "dynamicforward",
+];
+
+struct Tokens<'a> {
+    host_name: &'a str,
+    original_host: &'a str,
+    port: u16,
+    user: &'a str,
+    env: &'a Environment,
+}
+
+impl Tokens<'_> {
+    fn expand(&self, value: &str) -> Result<String> {
+        let mut output = String::new();
+        let mut chars = value.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                output.push(c);
+                continue
+            }
+
+            match chars.next() {
+                Some('%') => output.push('%'),
+                Some('d') => match self.env.home_dir.as_ref() {
+                    Some(home_dir) => output.push_str(&home_dir.to_string_lossy()),
+                    None => return Err(Error::SshConfig("home directory for token %d is unknown".into())),
+                },
+                Some('h') => output.push_str(self.host_name),
+                Some('n') => output.push_str(self.original_host),
+                Some('p') => output.push_str(&self.port.to_string()),
+                Some('r') => output.push_str(self.user),
+                Some('u') => output.push_str(&self.env.local_user),
+                Some(c) => return Err(Error::SshConfig(format!("unsupported token %{} in {:?}", c, value))),
+                None => return Err(Error::SshConfig(format!("unterminated token in {:?}", value))),
+            }
+        }
+        Ok(output)
+    }
+
+    fn expand_path(&self, value: &str) -> Result<PathBuf> {
+        let value = expand_tilde(value, self.env.home_dir.as_deref());
+        Ok(PathBuf::from(self.expand(&value.to_string_lossy())?))
+    }
+}
+
+fn expand_host_name(host_name: &str, original_host: &str) -> String {
+    // only %% and %h are expanded in HostName
+    let mut output = String::new();
+    let mut chars = host_name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '%' && chars.next_if_eq(&'h').is_some() {
+            output.push_str(original_host);
+        } else if c == '%' && chars.next_if_eq(&'%').is_some() {
+            output.push('%');
+        } else {
+            output.push(c);
+        }
+    }
+    output
+}
+
+fn expand_tilde(path: &str, home_dir: Option<&Path>) -> PathBuf {
+    match (path.strip_prefix('~'), home_dir) {
+        (Some(rest), Some(home_dir)) if rest.is_empty() || rest.starts_with('/') =>
+            home_dir.join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Expands wildcards `*` and `?` in the components of `path`, like the `glob()` function in C.
+fn expand_glob(path: &Path) -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::new()];
+    for component in path.components() {
+        let component = component.as_os_str();
+        let Some(pattern) = component.to_str().filter(|c| c.contains(['*', '?'])) else {
+            paths.iter_mut().for_each(|path| path.push(component));
+            continue
+        };
+
+        let mut expanded = Vec::new();
+        for dir in paths.iter() {
+            let Ok(entries) = std::fs::read_dir(if dir.as_os_str().is_empty() { Path::new(".") } else { dir }) else {
+                continue
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name();
+                let Some(name) = name.to_str() else { continue };
+                if name.starts_with('.') && !pattern.starts_with('.') {
+                    continue
+                }
+                if wildcard_matches(pattern, name) {
+                    expanded.push(dir.join(name));
+                }
+            }
+        }
+        expanded.sort();
+        paths = expanded;
+    }
+    paths
+}
+
+fn patterns_match(patterns: &[Pattern], value: &str) -> bool {
+    let mut matches = false;
+    for pattern in patterns.iter() {
+        if wildcard_matches(&pattern.pattern, value) {
+            if pattern.is_negated {
+                return false
+            }
+            matches = true;
+        }
+    }
+    matches
+}
+
+fn decode_bool(keyword: &str, value: &str) -> Result<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "yes" | "true" => Ok(true),
+        "no" | "false" => Ok(false),
+        _ => Err(invalid_value(keyword, value)),
+    }
+}
+
+/// Decodes a time interval such as `30`, `90s` or `1h30m`.
+fn decode_time(keyword: &str, value: &str) -> Result<Duration> {
+    let mut secs = 0u64;
+    let mut number = None::<u64>;
+    for c in value.chars() {
+        if let Some(digit) = c.to_digit(10) {
+            let n = number.unwrap_or(0).checked_mul(10).and_then(|n| n.checked_add(digit as u64));
+            number = Some(n.ok_or_else(|| invalid_value(keyword, value))?);
+            continue
+        }
+
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return Err(invalid_value(keyword, value)),
+        };
+        let n = number.take().ok_or_else(|| invalid_value(keyword, value))?;
+        secs = secs.saturating_add(n.saturating_mul(unit));
+    }
+
+    match number {
+        Some(n) => secs = secs.saturating_add(n),
+        None if value.is_empty() => return Err(invalid_value(keyword, value)),
+        None => {},
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+fn invalid_value(keyword: &str, value: &str) -> Error {
+    Error::SshConfig(format!("invalid value {:?} for {}", value, keyword))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env() -> Environment {
+        Environment { local_user: "alice".into(), home_dir: Some("/home/alice".into()) }
+    }
+
+    fn resolve(config: &str, host: &str) -> HostConfig {
+        let config = Config::decode(config, Path::new("/nonexistent"), &env()).unwrap();
+        config.resolve_in(host, &env()).unwrap()
+    }
+
+    #[test]
+    fn test_split_line() {
+        fn check(line: &str, keyword: &str, args: &[&str]) {
+            let (k, a) = split_line(line).unwrap().unwrap();
+            assert_eq!(k, keyword);
+            assert_eq!(a, args);
+        }
+
+        check("Host example.com", "host", &["example.com"]);
+        check("  HostName=example.com", "hostname", &["example.com"]);
+        check("Port = 2222 # comment", "port", &["2222"]);
+        check("IdentityFile \"~/my keys/id\" other", "identityfile", &["~/my keys/id", "other"]);
+        check("Ciphers\taes128-ctr,aes256-ctr", "ciphers", &["aes128-ctr,aes256-ctr"]);
+        assert!(split_line("   ").unwrap().is_none());
+        assert!(split_line("# Host foo").unwrap().is_none());
+        assert!(split_line("User \"bob").is_err());
+    }
+
+    #[test]
+    fn test_decode_time() {
+        assert_eq!(decode_time("X", "30").unwrap(), Duration::from_secs(30));
+        assert_eq!(decode_time("X", "1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(decode_time("X", "2m10").unwrap(), Duration::from_secs(130));
+        assert!(decode_time("X", "").is_err());
+        assert!(decode_time("X", "10x").is_err());
+        assert!(decode_time("X", "m").is_err());
+    }
+
+    #[test]
+    fn test_first_match_wins() {
+        let config = "
+            User default
+
+            Host bastion
+                HostName bastion.example.com
+                Port 2222
+                User admin
+
+            Host *.internal !secret.internal
+                ProxyJump bastion,other:22
+                IdentityFile ~/.ssh/internal_%r
+                Port 22
+
+            Host *
+                IdentityFile %d/.ssh/id_ed25519
+                ServerAliveInterval 1m
+                ForwardAgent yes
+        ";
+
+        let cfg = resolve(config, "bastion");
+        assert_eq!(cfg.host_name, "bastion.example.com");
+        assert_eq!(cfg.port, 2222);
+        assert_eq!(cfg.user.as_deref(), Some("default"));
+        assert_eq!(cfg.identity_files, vec![PathBuf::from("/home/alice/.ssh/id_ed25519")]);
+        assert_eq!(cfg.server_alive_interval, Some(Duration::from_secs(60)));
+        assert_eq!(cfg.options.get("forwardagent"), Some(&vec!["yes".to_owned()]));
+
+        let cfg = resolve(config, "DB.internal");
+        assert_eq!(cfg.host_name, "db.internal");
+        assert_eq!(cfg.port, 22);
+        assert_eq!(cfg.proxy_jump, vec!["bastion".to_owned(), "other:22".to_owned()]);
+        assert_eq!(cfg.identity_files, vec![
+            PathBuf::from("/home/alice/.ssh/internal_default"),
+            PathBuf::from("/home/alice/.ssh/id_ed25519"),
+        ]);
+
+        let cfg = resolve(config, "secret.internal");
+        assert!(cfg.proxy_jump.is_empty());
+        assert_eq!(cfg.identity_files.len(), 1);
+    }
+
+    #[test]
+    fn test_match() {
+        let config = "
+            Host web
+                HostName web%h.example.com
+
+            Match host *.example.com user bob
+                Port 2200
+
+            Match originalhost web !localuser alice
+                Port 2201
+
+            Match host web*.example.com
+                User bob
+                Compression yes
+
+            Match exec \"true\"
+                Port 2202
+
+            Match all
+                Port 2203
+        ";
+
+        let cfg = resolve(config, "web");
+        assert_eq!(cfg.host_name, "webweb.example.com");
+        assert_eq!(cfg.user.as_deref(), Some("bob"));
+        assert_eq!(cfg.compression, Some(true));
+        assert_eq!(cfg.port, 2203);
+
+        let cfg = resolve(&("User bob\n".to_owned() + config), "web");
+        assert_eq!(cfg.port, 2200);
+
+        let cfg = resolve(config, "other");
+        assert_eq!(cfg.host_name, "other");
+        assert_eq!(cfg.user, None);
+        assert_eq!(cfg.port, 2203);
+    }
+
+    #[test]
+    fn test_tokens() {
+        let config = "
+            Host gw
+                HostName gateway.example.com
+                Port 2022
+                User %u-admin
+                ProxyCommand nc -X 5 %h %p
+                IdentityFile ~/%n/%h-%p-%r
+                UserKnownHostsFile ~/.ssh/known_hosts ~/.ssh/known_hosts2
+
+            Host bad
+                IdentityFile ~/%C
+        ";
+
+        let cfg = resolve(config, "gw");
+        assert_eq!(cfg.proxy_command.as_deref(), Some("nc -X 5 gateway.example.com 2022"));
+        assert_eq!(cfg.identity_files, vec![
+            PathBuf::from("/home/alice/gw/gateway.example.com-2022-%u-admin"),
+        ]);
+        assert_eq!(cfg.user_known_hosts_files, vec![
+            PathBuf::from("/home/alice/.ssh/known_hosts"),
+            PathBuf::from("/home/alice/.ssh/known_hosts2"),
+        ]);
+
+        let config = Config::decode(config, Path::new("/nonexistent"), &env()).unwrap();
+        assert!(matches!(config.resolve_in("bad", &env()), Err(Error::SshConfig(_))));
+    }
+
+    #[test]
+    fn test_invalid() {
+        fn check(config: &str) {
+            let res = Config::decode(config, Path::new("/nonexistent"), &env());
+            assert!(matches!(res, Err(Error::SshConfig(_))));
+        }
+
+        check("Host");
+        check("Match");
+        check("Match host");
+        check("Match foo bar");
+        check("Port");
+
+        let config = Config::decode("Port http", Path::new("/nonexistent"), &env()).unwrap();
+        assert!(config.resolve_in("example.com", &env()).is_err());
+    }
+
+    #[test]
+    fn test_include() {
+        let dir = std::env::temp_dir().join(format!("makiko-ssh-config-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("config.d")).unwrap();
+        std::fs::write(dir.join("config.d/10-db"), "Host db\n  Port 2345\n").unwrap();
+        std::fs::write(dir.join("config.d/20-all"), "Host *\n  Port 2000\n  User carol\n").unwrap();
+        std::fs::write(dir.join("config.d/.hidden"), "Port 1\n").unwrap();
+        std::fs::write(dir.join("home-config"), "Host other\n  User erin\n").unwrap();
+
+        let config = "
+            Include ~/home-config
+
+            Host db web
+                Include config.d/*
+                User dave
+
+            Host web
+                Port 8022
+        ";
+        // the leading `~` is expanded to the home directory from the environment
+        let include_env = Environment { local_user: "alice".into(), home_dir: Some(dir.clone()) };
+        let config = Config::decode(config, &dir, &include_env).unwrap();
+
+        let cfg = config.resolve_in("db", &env()).unwrap();
+        assert_eq!(cfg.port, 2345);
+        assert_eq!(cfg.user.as_deref(), Some("carol"));
+
+        let cfg = config.resolve_in("web", &env()).unwrap();
+        assert_eq!(cfg.port, 2000);
+
+        let cfg = config.resolve_in("other", &env()).unwrap();
+        assert_eq!(cfg.port, 22);
+        assert_eq!(cfg.user.as_deref(), Some("erin"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,10 +1,14 @@
 use base64::Engine as _;
 use bytes::BytesMut;
+use futures_core::future::BoxFuture;
 use futures_core::ready;
 use rand::{CryptoRng, RngCore};
+use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Duration;
 use tokio::io::{AsyncRead, AsyncWrite};
+use crate::error::{Error, Result};
 
 
 pub trait AsyncReadWrite: AsyncRead + AsyncWrite {}
@@ -40,6 +44,38 @@ pub fn poll_read_buf(
 
 
 
+// adapted from `sync_wrapper::SyncFuture`: the future is only accessible through `Pin<&mut Self>`,
+// so sharing `&SyncFuture` between threads is harmless even if the future itself is not `Sync`
+pub struct SyncFuture<'a, T>(BoxFuture<'a, T>);
+
+unsafe impl<T> Sync for SyncFuture<'_, T> {}
+
+impl<'a, T> SyncFuture<'a, T> {
+    pub fn new(future: BoxFuture<'a, T>) -> Self {
+        SyncFuture(future)
+    }
+}
+
+impl<T> Future for SyncFuture<'_, T> {
+    type Output = T;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<T> {
+        self.get_mut().0.as_mut().poll(cx)
+    }
+}
+
+
+
+// awaits `future` for at most `duration` (or without limit if `duration` is `None`), returning
+// `error` on timeout
+pub async fn timeout<F: Future>(duration: Option<Duration>, error: Error, future: F) -> Result<F::Output> {
+    match duration {
+        Some(duration) => tokio::time::timeout(duration, future).await.map_err(|_| error),
+        None => Ok(future.await),
+    }
+}
+
+
+
 // adapted from an unpublished version of `rng_core`
 pub trait CryptoRngCore: CryptoRng + RngCore {
     fn as_rngcore(&mut self) -> &mut dyn RngCore;
@@ -54,3 +90,50 @@ impl<T: CryptoRng + RngCore> CryptoRngCore for T {
 pub fn base64_encode(data: &[u8]) -> String {
     base64::engine::general_purpose::STANDARD_NO_PAD.encode(data)
 }
+
+pub fn base64_decode(mut data_base64: &[u8]) -> Result<Vec<u8>, base64::DecodeError> {
+    while data_base64.last() == Some(&b'=') {
+        data_base64 = &data_base64[..data_base64.len() - 1];
+    }
+    base64::engine::general_purpose::STANDARD_NO_PAD.decode(data_base64)
+}
+
+/// Matches `text` against `pattern` with wildcards `*` (any string) and `?` (any character).
+pub fn wildcard_matches(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            backtrack = Some((star_p, star_t + 1));
+            p = star_p + 1;
+            t = star_t + 1;
+        } else {
+            return false
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcard_matches() {
+        assert!(wildcard_matches("*", ""));
+        assert!(wildcard_matches("*.example.com", "www.example.com"));
+        assert!(!wildcard_matches("*.example.com", "example.com"));
+        assert!(wildcard_matches("db?", "db1"));
+        assert!(!wildcard_matches("db?", "db12"));
+        assert!(wildcard_matches("a*b*c", "axxbyyc"));
+        assert!(!wildcard_matches("a*b*c", "axxbyy"));
+    }
+}
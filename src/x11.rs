@@ -0,0 +1,190 @@
+//! Helpers for X11 forwarding.
+//!
+//! To forward X11 connections from the server to your local X server, request the forwarding
+//! using [`Session::request_x11()`][crate::Session::request_x11()] and then handle the
+//! [`ClientEvent::X11`][crate::ClientEvent::X11] events, which the client produces for every X11
+//! connection that the server opens.
+//!
+//! The X11 clients on the server must authenticate to your X server, typically using the
+//! `MIT-MAGIC-COOKIE-1` protocol. However, if you sent your real cookie to the server, anybody
+//! with access to the server could use it to connect to your X server directly. Like OpenSSH, you
+//! can instead use an [`X11Cookie`], which sends a random fake cookie to the server; when the
+//! server opens an X11 connection, [`bridge()`] checks that the X11 client used the fake cookie
+//! and replaces it with the real one before forwarding the connection to your X server.
+use bytes::Bytes;
+use rand::RngCore as _;
+use rand::rngs::OsRng;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncReadExt as _, AsyncWriteExt as _};
+use crate::client::{AcceptX11, ChannelConfig, TunnelStream, X11Request};
+use crate::error::{Error, Result};
+
+/// Length of the fixed part of the X11 connection setup message.
+const SETUP_HEADER_LEN: usize = 12;
+
+/// Fake X11 authentication cookie.
+///
+/// This object holds your real X11 authentication cookie (which you can obtain using `xauth
+/// list`) and a random fake cookie of the same length. The fake cookie is sent to the server in
+/// the [`X11Request`] returned from [`request()`][Self::request()], and [`bridge()`] replaces it
+/// with the real cookie in every forwarded X11 connection.
+#[derive(Debug, Clone)]
+pub struct X11Cookie {
+    auth_protocol: String,
+    real_cookie: Bytes,
+    fake_cookie: Bytes,
+}
+
+impl X11Cookie {
+    /// Generate a fake cookie for the real `cookie` of the given authentication protocol.
+    ///
+    /// The `auth_protocol` is typically `"MIT-MAGIC-COOKIE-1"`, which uses a 16-byte cookie.
+    pub fn new(auth_protocol: String, real_cookie: Bytes) -> X11Cookie {
+        let mut fake_cookie = vec![0; real_cookie.len()];
+        OsRng.fill_bytes(&mut fake_cookie);
+        X11Cookie { auth_protocol, real_cookie, fake_cookie: fake_cookie.into() }
+    }
+
+    /// The X11 authentication protocol.
+    pub fn auth_protocol(&self) -> &str {
+        &self.auth_protocol
+    }
+
+    /// The fake cookie that is sent to the server.
+    pub fn fake_cookie(&self) -> &Bytes {
+        &self.fake_cookie
+    }
+
+    /// Create a request for [`Session::request_x11()`][crate::Session::request_x11()] that uses
+    /// the fake cookie.
+    pub fn request(&self, single_connection: bool, screen_number: u32) -> X11Request {
+        let auth_cookie = self.fake_cookie.iter().map(|byte| format!("{:02x}", byte)).collect();
+        X11Request {
+            single_connection,
+            auth_protocol: self.auth_protocol.clone(),
+            auth_cookie,
+            screen_number,
+        }
+    }
+}
+
+/// Accept a forwarded X11 connection and bridge it to a local X server.
+///
+/// You receive the `accept` object in [`ClientEvent::X11`][crate::ClientEvent::X11] after you
+/// requested X11 forwarding using [`Session::request_x11()`][crate::Session::request_x11()] with
+/// the [`X11Request`] from [`X11Cookie::request()`]. The `x11_stream` should be a new connection
+/// to your local X server.
+///
+/// We read the connection setup message that the X11 client sends first and check that it uses
+/// the fake `cookie`. If it does not, we close the connection and return [`Error::X11Auth`].
+/// Otherwise, we replace the fake cookie with the real one, send the message to the X server and
+/// then copy the data in both directions until both sides are closed.
+pub async fn bridge<S>(
+    accept: AcceptX11,
+    config: ChannelConfig,
+    cookie: &X11Cookie,
+    mut x11_stream: S,
+) -> Result<()>
+    where S: AsyncRead + AsyncWrite + Unpin
+{
+    let (tunnel, tunnel_rx) = accept.accept(config).await?;
+    let mut tunnel_stream = TunnelStream::new(tunnel, tunnel_rx);
+
+    let setup = rewrite_setup(&mut tunnel_stream, cookie).await?;
+    x11_stream.write_all(&setup).await.map_err(Error::X11Io)?;
+    tokio::io::copy_bidirectional(&mut tunnel_stream, &mut x11_stream).await
+        .map_err(Error::X11Io)?;
+    Ok(())
+}
+
+async fn rewrite_setup<R>(stream: &mut R, cookie: &X11Cookie) -> Result<Vec<u8>>
+    where R: AsyncRead + Unpin
+{
+    // X Window System Protocol, section 8 (Connection Setup)
+    let mut header = [0; SETUP_HEADER_LEN];
+    stream.read_exact(&mut header).await.map_err(Error::X11Io)?;
+    let big_endian = match header[0] {
+        b'B' => true,
+        b'l' => false,
+        _ => {
+            log::debug!("X11 connection setup has invalid byte order {:#x}", header[0]);
+            return Err(Error::X11Auth)
+        },
+    };
+    let get_u16 = |bytes: &[u8]| {
+        let bytes = [bytes[0], bytes[1]];
+        if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }
+    };
+    let proto_len = get_u16(&header[6..]) as usize;
+    let data_len = get_u16(&header[8..]) as usize;
+
+    let mut body = vec![0; pad4(proto_len) + pad4(data_len)];
+    stream.read_exact(&mut body).await.map_err(Error::X11Io)?;
+    let proto = &body[..proto_len];
+    let data = &body[pad4(proto_len)..][..data_len];
+
+    if proto != cookie.auth_protocol.as_bytes() || !constant_time_eq(data, &cookie.fake_cookie) {
+        log::debug!("X11 connection used unexpected authentication protocol or cookie");
+        return Err(Error::X11Auth)
+    }
+
+    let real_len = cookie.real_cookie.len() as u16;
+    let real_len = if big_endian { real_len.to_be_bytes() } else { real_len.to_le_bytes() };
+    header[8..10].copy_from_slice(&real_len);
+
+    let mut setup = header.to_vec();
+    setup.extend_from_slice(&body[..pad4(proto_len)]);
+    setup.extend_from_slice(&cookie.real_cookie);
+    setup.resize(setup.len() + pad4(cookie.real_cookie.len()) - cookie.real_cookie.len(), 0);
+    Ok(setup)
+}
+
+fn pad4(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_setup(big_endian: bool, proto: &[u8], data: &[u8]) -> Vec<u8> {
+        let put_u16 = |value: u16| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+        let mut setup = vec![if big_endian { b'B' } else { b'l' }, 0];
+        setup.extend_from_slice(&put_u16(11));
+        setup.extend_from_slice(&put_u16(0));
+        setup.extend_from_slice(&put_u16(proto.len() as u16));
+        setup.extend_from_slice(&put_u16(data.len() as u16));
+        setup.extend_from_slice(&[0, 0]);
+        setup.extend_from_slice(proto);
+        setup.resize(pad4(setup.len()), 0);
+        setup.extend_from_slice(data);
+        setup.resize(pad4(setup.len()), 0);
+        setup
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_setup() {
+        let real_cookie = Bytes::from_static(b"0123456789abcdef");
+        let cookie = X11Cookie::new("MIT-MAGIC-COOKIE-1".into(), real_cookie.clone());
+        assert_eq!(cookie.fake_cookie().len(), 16);
+        assert_ne!(cookie.fake_cookie(), &real_cookie);
+        assert_eq!(cookie.request(false, 0).auth_cookie.len(), 32);
+
+        for big_endian in [true, false] {
+            let setup = encode_setup(big_endian, b"MIT-MAGIC-COOKIE-1", cookie.fake_cookie());
+            let rewritten = rewrite_setup(&mut &setup[..], &cookie).await.unwrap();
+            assert_eq!(rewritten, encode_setup(big_endian, b"MIT-MAGIC-COOKIE-1", &real_cookie));
+        }
+
+        let setup = encode_setup(true, b"MIT-MAGIC-COOKIE-1", &real_cookie);
+        let res = rewrite_setup(&mut &setup[..], &cookie).await;
+        assert!(matches!(res, Err(Error::X11Auth)));
+
+        let setup = encode_setup(true, b"XDM-AUTHORIZATION-1", cookie.fake_cookie());
+        let res = rewrite_setup(&mut &setup[..], &cookie).await;
+        assert!(matches!(res, Err(Error::X11Auth)));
+    }
+}
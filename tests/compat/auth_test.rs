@@ -18,6 +18,11 @@ pub fn collect(suite: &mut TestSuite) {
     suite.add(TestCase::new("auth_password_already_authenticated", test_password_already_authenticated)
         .except_servers(vec!["tinyssh"]));
 
+    suite.add(TestCase::new("auth_keyboard_interactive_success", test_keyboard_interactive_success)
+        .only_servers(vec!["paramiko"]));
+    suite.add(TestCase::new("auth_keyboard_interactive_failure", test_keyboard_interactive_failure)
+        .only_servers(vec!["paramiko"]));
+
     let pubkey_variants = vec![
         (
             "edward",
@@ -70,6 +75,8 @@ pub fn collect(suite: &mut TestSuite) {
         .except_servers(vec!["lsh", "tinyssh"]));
     suite.add(TestCase::new("auth_pubkey_algo_names", test_pubkey_algo_names)
         .except_servers(vec!["lsh", "tinyssh"]));
+    suite.add(TestCase::new("auth_pubkey_custom_signer", test_pubkey_custom_signer)
+        .except_servers(vec!["lsh"]));
 
     suite.add(TestCase::new("auth_pubkey_check_true_ed25519",
         |socket| test_pubkey_check(socket,
@@ -91,6 +98,27 @@ pub fn collect(suite: &mut TestSuite) {
             "edward".into(), keys::edward_ed25519(), &makiko::pubkey::SSH_RSA_SHA1, false))
         .except_servers(vec!["paramiko"]));
 
+    let cert_variants = vec![
+        ("ed25519", keys::edward_ed25519(), include_str!("../keys/edward_ed25519-cert.pub"),
+            vec![&makiko::pubkey::SSH_ED25519_CERT_V01]),
+        ("ecdsa_p256", keys::eda_ecdsa_p256(), include_str!("../keys/eda_ecdsa_p256-cert.pub"),
+            vec![&makiko::pubkey::ECDSA_SHA2_NISTP256_CERT_V01]),
+        ("rsa_2048", keys::ruth_rsa_2048(), include_str!("../keys/ruth_rsa_2048-cert.pub"),
+            vec![&makiko::pubkey::RSA_SHA2_256_CERT_V01, &makiko::pubkey::RSA_SHA2_512_CERT_V01]),
+    ];
+
+    for (key_name, privkey, cert_data, algos) in cert_variants.into_iter() {
+        for algo in algos.into_iter() {
+            let privkey = privkey.clone();
+            let case_name = format!("auth_pubkey_cert_{}_{}_success", key_name, algo.name);
+            suite.add(TestCase::new(&case_name, move |socket| {
+                test_pubkey_cert_success(socket, privkey.clone(), cert_data, algo)
+            }).only_servers(vec!["openssh"]));
+        }
+    }
+    suite.add(TestCase::new("auth_pubkey_cert_bad_principal", test_pubkey_cert_bad_principal)
+        .only_servers(vec!["openssh"]));
+
     suite.add(TestCase::new("auth_none_success", test_none_success)
         .except_servers(vec!["tinyssh", "lsh"]));
     suite.add(TestCase::new("auth_none_failure", test_none_failure));
@@ -157,6 +185,43 @@ async fn test_password_already_authenticated(socket: TcpStream) -> Result<()> {
 
 
 
+async fn test_keyboard_interactive_success(socket: TcpStream) -> Result<()> {
+    test_auth(socket, |client| async move {
+        let res = client.auth_keyboard_interactive("alice".into()).await?;
+        let request = match res {
+            makiko::AuthKeyboardInteractiveResult::InfoRequest(request) => request,
+            res => bail!("expected info request, got {:?}", res),
+        };
+        ensure!(request.name == "alice login", "{:?}", request);
+        ensure!(request.instruction == "Enter your password and code", "{:?}", request);
+        let prompts = request.prompts.iter()
+            .map(|prompt| (prompt.prompt.as_str(), prompt.echo))
+            .collect::<Vec<_>>();
+        ensure!(prompts == vec![("Password: ", false), ("Code: ", true)], "{:?}", request);
+
+        let res = client.auth_keyboard_interactive_respond(vec!["alicealice".into(), "1234".into()]).await?;
+        ensure!(matches!(res, makiko::AuthKeyboardInteractiveResult::Success), "expected success, got {:?}", res);
+        check_authenticated(client).await
+    }).await
+}
+
+async fn test_keyboard_interactive_failure(socket: TcpStream) -> Result<()> {
+    test_auth(socket, |client| async move {
+        let res = client.auth_keyboard_interactive("alice".into()).await?;
+        ensure!(matches!(res, makiko::AuthKeyboardInteractiveResult::InfoRequest(_)),
+            "expected info request, got {:?}", res);
+
+        let res = client.auth_keyboard_interactive_respond(vec!["alicealice".into(), "0000".into()]).await?;
+        match res {
+            makiko::AuthKeyboardInteractiveResult::Failure(fail) => {
+                ensure!(fail.methods_can_continue.contains(&"keyboard-interactive".into()), "{:?}", fail);
+            },
+            res => bail!("expected failure, got {:?}", res),
+        }
+        check_not_authenticated(client).await
+    }).await
+}
+
 async fn test_pubkey_success(
     socket: TcpStream,
     username: String,
@@ -171,6 +236,58 @@ async fn test_pubkey_success(
     }).await
 }
 
+async fn test_pubkey_cert_success(
+    socket: TcpStream,
+    privkey: makiko::Privkey,
+    cert_data: &'static str,
+    algo: &'static makiko::PubkeyAlgo,
+) -> Result<()> {
+    let cert = makiko::keys::decode_openssh_cert(cert_data.as_bytes())?;
+    test_auth(socket, move |client| async move {
+        ensure!(!client.is_authenticated()?);
+        let res = client.auth_pubkey_cert("carol".into(), privkey, &cert, algo).await?;
+        ensure!(matches!(res, makiko::AuthPubkeyResult::Success), "expected success, got {:?}", res);
+        check_authenticated(client).await
+    }).await
+}
+
+async fn test_pubkey_cert_bad_principal(socket: TcpStream) -> Result<()> {
+    let cert = makiko::keys::decode_openssh_cert(include_str!("../keys/edward_ed25519-cert.pub").as_bytes())?;
+    test_auth(socket, move |client| async move {
+        let res = client.auth_pubkey_cert(
+            "edward".into(), keys::edward_ed25519(), &cert, &makiko::pubkey::SSH_ED25519_CERT_V01).await?;
+        ensure!(matches!(res, makiko::AuthPubkeyResult::Failure(_)), "expected failure, got {:?}", res);
+        check_not_authenticated(client).await
+    }).await
+}
+
+async fn test_pubkey_custom_signer(socket: TcpStream) -> Result<()> {
+    // signer that holds the key "remotely" and takes some time to produce the signature
+    struct SlowSigner(makiko::Privkey);
+
+    impl makiko::Signer for SlowSigner {
+        fn pubkey(&self) -> makiko::Pubkey {
+            self.0.pubkey()
+        }
+
+        fn sign<'a>(&'a self, algo: &'static makiko::PubkeyAlgo, data: &'a [u8])
+            -> BoxFuture<'a, makiko::Result<makiko::bytes::Bytes>>
+        {
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                makiko::Signer::sign(&self.0, algo, data).await
+            })
+        }
+    }
+
+    test_auth(socket, |client| async move {
+        let signer = SlowSigner(keys::edward_ed25519());
+        let res = client.auth_pubkey("edward".into(), signer, &makiko::pubkey::SSH_ED25519).await?;
+        ensure!(matches!(res, makiko::AuthPubkeyResult::Success), "expected success, got {:?}", res);
+        check_authenticated(client).await
+    }).await
+}
+
 async fn test_pubkey_failure(socket: TcpStream) -> Result<()> {
     test_auth(socket, |client| async move {
         ensure!(!client.is_authenticated()?);
@@ -13,6 +13,7 @@ use crate::ssh_server::SshServer;
 mod auth_test;
 mod nursery;
 mod session_test;
+mod sftp_test;
 mod smoke_test;
 mod ssh_server;
 mod tunnel_test;
@@ -131,6 +132,9 @@ async fn main() -> ExitCode {
     }
 }
 
+// servers that run only the test cases that name them in `only_servers()`
+const EXPLICIT_SERVERS: &[&str] = &["openssh_pq"];
+
 async fn run_all_tests(selector: TestSelector) -> Result<TestResult> {
     let server_names = vec![
         "openssh",
@@ -138,6 +142,7 @@ async fn run_all_tests(selector: TestSelector) -> Result<TestResult> {
         "tinyssh",
         "lsh",
         "paramiko",
+        "openssh_pq",
     ];
 
     let docker = Docker::connect_with_local_defaults()
@@ -148,6 +153,7 @@ async fn run_all_tests(selector: TestSelector) -> Result<TestResult> {
     auth_test::collect(&mut suite);
     session_test::collect(&mut suite);
     tunnel_test::collect(&mut suite);
+    sftp_test::collect(&mut suite);
 
     let mut ctx = TestCtx { docker, selector, suite, result: TestResult::default() };
     for server_name in server_names.into_iter() {
@@ -173,6 +179,8 @@ async fn run_server_tests(ctx: &mut TestCtx, server_name: &str) -> Result<()> {
             if !servers.contains(server_name) {
                 continue
             }
+        } else if EXPLICIT_SERVERS.contains(&server_name) {
+            continue
         }
 
         if let Some(servers) = case.except_servers.as_ref() {
@@ -7,9 +7,10 @@ use rand_chacha::ChaCha8Rng;
 use std::cmp::min;
 use std::future::Future;
 use std::time::Duration;
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
 use tokio::net::TcpStream;
 use tokio::sync::{mpsc, oneshot};
-use crate::{TestSuite, TestCase};
+use crate::{TestSuite, TestCase, keys};
 use crate::nursery::Nursery;
 use crate::smoke_test::authenticate_alice;
 
@@ -38,6 +39,10 @@ pub fn collect(suite: &mut TestSuite) {
         .only_servers(vec!["openssh"]));
     suite.add(TestCase::new("session_close", test_close)
         .except_servers(vec!["tinyssh"]));
+    suite.add(TestCase::new("session_obscure_keystroke_timing", test_obscure_keystroke_timing)
+        .only_servers(vec!["openssh"]));
+    suite.add(TestCase::new("session_agent_forward", test_agent_forward)
+        .only_servers(vec!["openssh"]));
 }
 
 
@@ -287,6 +292,150 @@ async fn test_close(socket: TcpStream) -> Result<()> {
 }
 
 
+async fn test_obscure_keystroke_timing(socket: TcpStream) -> Result<()> {
+    let config = makiko::ClientConfig::default_compatible_less_secure().with(|c| {
+        c.obscure_keystroke_timing = Some(Duration::from_millis(20));
+    });
+    test_session_config(socket, config, |session, mut session_rx| async move {
+        let (nursery, mut nursery_stream) = Nursery::new();
+
+        let (stdout_tx, stdout_rx) = oneshot::channel();
+        nursery.spawn(async move {
+            let mut stdout = BytesMut::new();
+            while let Some(event) = session_rx.recv().await? {
+                if let makiko::SessionEvent::StdoutData(chunk) = event {
+                    stdout.put(chunk);
+                } else if let makiko::SessionEvent::Eof = event {
+                    break;
+                }
+            }
+            let _: Result<_, _> = stdout_tx.send(stdout.freeze());
+            Ok(())
+        });
+
+        nursery.spawn(async move {
+            let pty_request = makiko::PtyRequest {
+                term: "vt100".into(),
+                width: 80,
+                height: 24,
+                ..Default::default()
+            };
+            session.request_pty(&pty_request)?.wait().await?;
+            session.exec("read line; echo \"got $line\"".as_bytes())?.wait().await?;
+
+            // type the line slowly, like a human
+            for &key in b"hello\r" {
+                session.send_stdin(Bytes::copy_from_slice(&[key])).await?;
+                tokio::time::sleep(Duration::from_millis(30)).await;
+            }
+
+            let stdout = stdout_rx.await?;
+            ensure!(stdout.windows(9).any(|w| w == b"got hello"), "received unexpected stdout {:?}", stdout);
+            Ok(())
+        });
+
+        drop(nursery);
+        nursery_stream.try_run().await
+    }).await
+}
+
+async fn test_agent_forward(socket: TcpStream) -> Result<()> {
+    let (nursery, mut nursery_stream) = Nursery::new();
+    let (client, mut client_rx, client_fut) = makiko::Client::open(
+        socket, makiko::ClientConfig::default_compatible_less_secure())?;
+
+    nursery.spawn(async move {
+        client_fut.await?;
+        Ok(())
+    });
+
+    // bridge every forwarded connection to a fresh fake agent
+    nursery.spawn(enclose!{(nursery) async move {
+        while let Some(event) = client_rx.recv().await? {
+            match event {
+                makiko::ClientEvent::ServerPubkey(_pubkey, accept_tx) => accept_tx.accept(),
+                makiko::ClientEvent::AgentForward(accept) => {
+                    log::debug!("received agent forward");
+                    let (agent_stream, stream) = tokio::io::duplex(4096);
+                    nursery.spawn(run_fake_agent(agent_stream));
+                    nursery.spawn(async move {
+                        makiko::agent::bridge(accept, makiko::ChannelConfig::default(), stream).await?;
+                        Ok(())
+                    });
+                },
+                _ => {},
+            }
+        }
+        Ok(())
+    }});
+
+    nursery.spawn(enclose!{(nursery) async move {
+        authenticate_alice(&client).await?;
+        let (session, mut session_rx) = client.open_session(makiko::ChannelConfig::default()).await?;
+
+        let (stdout_tx, stdout_rx) = oneshot::channel();
+        nursery.spawn(async move {
+            let mut stdout = BytesMut::new();
+            while let Some(event) = session_rx.recv().await? {
+                if let makiko::SessionEvent::StdoutData(chunk) = event {
+                    stdout.put(chunk);
+                } else if let makiko::SessionEvent::Eof = event {
+                    break;
+                }
+            }
+            let _: Result<_, _> = stdout_tx.send(stdout.freeze());
+            Ok(())
+        });
+
+        session.request_agent_forward()?.wait().await
+            .context("could not request agent forwarding")?;
+        session.exec("ssh-add -l".as_bytes())?.wait().await?;
+
+        let stdout = stdout_rx.await?;
+        let stdout = String::from_utf8_lossy(&stdout);
+        let fingerprint = keys::edward_ed25519().pubkey().fingerprint();
+        ensure!(stdout.contains(&fingerprint) && stdout.contains("makiko test key"),
+            "received unexpected stdout {:?}", stdout);
+
+        client.disconnect(makiko::DisconnectError::by_app())?;
+        Ok(())
+    }});
+
+    drop(nursery);
+    nursery_stream.try_run().await
+}
+
+// minimal stand-in for `ssh-agent` that lists a single key and refuses everything else
+async fn run_fake_agent(mut stream: tokio::io::DuplexStream) -> Result<()> {
+    const FAILURE: u8 = 5;
+    const REQUEST_IDENTITIES: u8 = 11;
+    const IDENTITIES_ANSWER: u8 = 12;
+
+    let pubkey_blob = keys::edward_ed25519().pubkey().encode();
+    while let Ok(len) = stream.read_u32().await {
+        let mut request = vec![0; len as usize];
+        stream.read_exact(&mut request).await?;
+
+        let mut reply = BytesMut::new();
+        if request.first() == Some(&REQUEST_IDENTITIES) {
+            let comment = "makiko test key";
+            reply.put_u8(IDENTITIES_ANSWER);
+            reply.put_u32(1);
+            reply.put_u32(pubkey_blob.len() as u32);
+            reply.put_slice(&pubkey_blob);
+            reply.put_u32(comment.len() as u32);
+            reply.put_slice(comment.as_bytes());
+        } else {
+            reply.put_u8(FAILURE);
+        }
+
+        stream.write_u32(reply.len() as u32).await?;
+        stream.write_all(&reply).await?;
+    }
+    Ok(())
+}
+
+
 async fn test_session<F, Fut>(socket: TcpStream, f: F) -> Result<()>
     where F: FnOnce(makiko::Session, makiko::SessionReceiver) -> Fut + Send + Sync + 'static,
           Fut: Future<Output = Result<()>> + Send + Sync + 'static,
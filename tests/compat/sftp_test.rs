@@ -0,0 +1,138 @@
+use anyhow::{Result, ensure, Context as _};
+use bytes::Bytes;
+use rand::{RngCore as _, SeedableRng as _};
+use rand_chacha::ChaCha8Rng;
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+use tokio::net::TcpStream;
+use crate::{TestSuite, TestCase};
+use crate::nursery::Nursery;
+use crate::smoke_test::authenticate_alice;
+
+pub fn collect(suite: &mut TestSuite) {
+    suite.add(TestCase::new("sftp_file", test_file)
+        .only_servers(vec!["openssh"]));
+    suite.add(TestCase::new("sftp_dir", test_dir)
+        .only_servers(vec!["openssh"]));
+}
+
+async fn test_file(socket: TcpStream) -> Result<()> {
+    test_sftp(socket, |sftp| async move {
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let mut content = vec![0u8; 1_000_000];
+        rng.fill_bytes(&mut content);
+
+        let options = makiko::sftp::OpenOptions::new()
+            .write(true).create(true).truncate(true).mode(0o640).clone();
+        let file = sftp.open_file("sftp_test_file", &options).await
+            .context("could not open file for writing")?;
+        let mut writer = file.writer(0);
+        writer.write_all(&content).await.context("could not write to file")?;
+        writer.shutdown().await?;
+        file.close().await?;
+
+        let attrs = sftp.stat("sftp_test_file").await?;
+        ensure!(attrs.size == Some(content.len() as u64), "received size {:?}", attrs.size);
+        ensure!(attrs.is_file(), "received permissions {:?}", attrs.permissions);
+        ensure!(attrs.permissions.map(|x| x & 0o777) == Some(0o640),
+            "received permissions {:?}", attrs.permissions);
+
+        let options = makiko::sftp::OpenOptions::new().read(true).clone();
+        let file = sftp.open_file("sftp_test_file", &options).await
+            .context("could not open file for reading")?;
+        let mut read_content = Vec::new();
+        file.reader(0).read_to_end(&mut read_content).await.context("could not read file")?;
+        ensure!(read_content == content, "read content does not match written content");
+
+        let chunk = file.read_at(1000, 100).await?.context("expected data")?;
+        ensure!(chunk == Bytes::copy_from_slice(&content[1000..1100]), "read_at returned wrong data");
+        ensure!(file.read_at(2_000_000, 100).await?.is_none(), "expected eof");
+        ensure!(file.stat().await?.size == Some(content.len() as u64), "fstat returned wrong size");
+        file.close().await?;
+
+        sftp.rename("sftp_test_file", "sftp_test_file_2").await?;
+        sftp.symlink("sftp_test_file_2", "sftp_test_link").await?;
+        ensure!(sftp.readlink("sftp_test_link").await? == "sftp_test_file_2", "readlink returned wrong path");
+        ensure!(sftp.lstat("sftp_test_link").await?.is_symlink(), "expected symlink");
+        ensure!(sftp.stat("sftp_test_link").await?.is_file(), "expected file");
+
+        sftp.remove("sftp_test_link").await?;
+        sftp.remove("sftp_test_file_2").await?;
+        match sftp.stat("sftp_test_file_2").await {
+            Err(makiko::Error::Sftp(err)) if err.status_code == makiko::codes::sftp_status::NO_SUCH_FILE => {},
+            res => anyhow::bail!("expected no such file, received {:?}", res),
+        }
+        Ok(())
+    }).await
+}
+
+async fn test_dir(socket: TcpStream) -> Result<()> {
+    test_sftp(socket, |sftp| async move {
+        let home = sftp.realpath(".").await?;
+        ensure!(home.starts_with('/'), "realpath returned {:?}", home);
+
+        let dir_path = format!("{}/sftp_test_dir", home);
+        sftp.mkdir(&dir_path, &makiko::sftp::FileAttrs::new()).await?;
+        ensure!(sftp.stat(&dir_path).await?.is_dir(), "expected directory");
+
+        let options = makiko::sftp::OpenOptions::new().write(true).create_new(true).clone();
+        for name in ["a", "b", "c"] {
+            sftp.open_file(&format!("{}/{}", dir_path, name), &options).await?.close().await?;
+        }
+
+        let mut names = sftp.read_dir(&dir_path).await?.into_iter()
+            .map(|entry| entry.filename)
+            .filter(|name| name != "." && name != "..")
+            .collect::<Vec<_>>();
+        names.sort();
+        ensure!(names == vec!["a", "b", "c"], "read_dir returned {:?}", names);
+
+        for name in ["a", "b", "c"] {
+            sftp.remove(&format!("{}/{}", dir_path, name)).await?;
+        }
+        sftp.rmdir(&dir_path).await?;
+        Ok(())
+    }).await
+}
+
+async fn test_sftp<F, Fut>(socket: TcpStream, f: F) -> Result<()>
+    where F: FnOnce(makiko::sftp::Sftp) -> Fut + Send + 'static,
+          Fut: std::future::Future<Output = Result<()>> + Send + 'static,
+{
+    let (nursery, mut nursery_stream) = Nursery::new();
+    let config = makiko::ClientConfig::default_compatible_less_secure();
+    let (client, mut client_rx, client_fut) = makiko::Client::open(socket, config)?;
+
+    nursery.spawn(async move {
+        client_fut.await?;
+        Ok(())
+    });
+
+    nursery.spawn(async move {
+        while let Some(event) = client_rx.recv().await? {
+            if let makiko::ClientEvent::ServerPubkey(_pubkey, accept_tx) = event {
+                accept_tx.accept();
+            }
+        }
+        Ok(())
+    });
+
+    let sftp_nursery = nursery.clone();
+    nursery.spawn(async move {
+        authenticate_alice(&client).await?;
+        let (sftp, sftp_fut) = makiko::sftp::Sftp::open(&client, makiko::sftp::SftpConfig::default()).await
+            .context("could not open sftp")?;
+        sftp_nursery.spawn(async move {
+            sftp_fut.await?;
+            Ok(())
+        });
+
+        f(sftp.clone()).await?;
+
+        sftp.close()?;
+        client.disconnect(makiko::DisconnectError::by_app())?;
+        Ok(())
+    });
+
+    drop(nursery);
+    nursery_stream.try_run().await
+}
@@ -2,6 +2,7 @@ use anyhow::{Result, Context as _, ensure, bail};
 use bytes::{BytesMut, BufMut as _};
 use enclose::enclose;
 use std::mem::drop;
+use std::time::Duration;
 use tokio::net::TcpStream;
 use tokio::sync::oneshot;
 use crate::{TestSuite, TestCase, keys};
@@ -16,6 +17,22 @@ pub fn collect(suite: &mut TestSuite) {
         smoke_test(socket, makiko::ClientConfig::default())
     }).except_servers(vec!["lsh"]));
 
+    suite.add(TestCase::new("smoke_require_strict_kex", |socket| {
+        smoke_test(socket, makiko::ClientConfig::default_compatible_less_secure().with(|c| {
+            c.require_strict_kex = true;
+        }))
+    }).only_servers(vec!["openssh"]));
+
+    suite.add(TestCase::new("smoke_connection_info", test_connection_info)
+        .only_servers(vec!["openssh", "dropbear"]));
+
+    suite.add(TestCase::new("smoke_probe", test_probe));
+
+    suite.add(TestCase::new("smoke_keepalive", test_keepalive)
+        .except_servers(vec!["lsh"]));
+
+    suite.add(TestCase::new("smoke_ping", test_ping)
+        .except_servers(vec!["lsh"]));
 
     let cipher_algos = vec![
         (&makiko::cipher::AES128_CTR, vec!["openssh", "dropbear", "paramiko"]),
@@ -41,10 +58,18 @@ pub fn collect(suite: &mut TestSuite) {
     ];
 
     let kex_algos = vec![
+        (&makiko::kex::MLKEM768X25519_SHA256, vec!["openssh_pq"]),
+        (&makiko::kex::SNTRUP761X25519_SHA512, vec!["openssh_pq"]),
+        (&makiko::kex::SNTRUP761X25519_SHA512_OPENSSH, vec!["openssh", "openssh_pq"]),
         (&makiko::kex::CURVE25519_SHA256, vec!["openssh", "dropbear", "tinyssh"]),
         (&makiko::kex::CURVE25519_SHA256_LIBSSH, vec!["openssh", "dropbear", "tinyssh", "paramiko"]),
+        (&makiko::kex::ECDH_SHA2_NISTP256, vec!["openssh", "dropbear", "paramiko"]),
+        (&makiko::kex::ECDH_SHA2_NISTP384, vec!["openssh", "dropbear", "paramiko"]),
+        (&makiko::kex::ECDH_SHA2_NISTP521, vec!["openssh", "dropbear", "paramiko"]),
         (&makiko::kex::DIFFIE_HELLMAN_GROUP14_SHA1, vec!["openssh", "dropbear", "paramiko", "lsh"]),
         (&makiko::kex::DIFFIE_HELLMAN_GROUP14_SHA256, vec!["openssh", "dropbear", "paramiko"]),
+        (&makiko::kex::DIFFIE_HELLMAN_GROUP_EXCHANGE_SHA256, vec!["openssh"]),
+        (&makiko::kex::DIFFIE_HELLMAN_GROUP_EXCHANGE_SHA1, vec!["openssh"]),
         // these kexes are very slow
         //(&makiko::kex::DIFFIE_HELLMAN_GROUP16_SHA512, vec!["openssh", "paramiko"]),
         //(&makiko::kex::DIFFIE_HELLMAN_GROUP18_SHA512, vec!["openssh"]),
@@ -52,11 +77,20 @@ pub fn collect(suite: &mut TestSuite) {
         (&makiko::kex::DIFFIE_HELLMAN_GROUP1_SHA1, vec!["openssh", "paramiko", "lsh"]),
     ];
 
+    let compress_algos = vec![
+        (&makiko::compress::ZLIB_OPENSSH, vec!["openssh", "dropbear", "paramiko"]),
+        (&makiko::compress::ZLIB, vec!["paramiko"]),
+    ];
+
     let pubkey_algos = vec![
         (&makiko::pubkey::SSH_ED25519, vec!["openssh", "dropbear", "tinyssh", "paramiko"]),
         (&makiko::pubkey::ECDSA_SHA2_NISTP256, vec!["openssh", "dropbear", "paramiko"]),
         (&makiko::pubkey::ECDSA_SHA2_NISTP384, vec!["openssh", "dropbear", "paramiko"]),
         (&makiko::pubkey::SSH_RSA_SHA1, vec!["openssh", "dropbear", "paramiko", "lsh"]),
+        (&makiko::pubkey::SSH_ED25519_CERT_V01, vec!["openssh"]),
+        (&makiko::pubkey::ECDSA_SHA2_NISTP256_CERT_V01, vec!["openssh"]),
+        (&makiko::pubkey::RSA_SHA2_256_CERT_V01, vec!["openssh"]),
+        (&makiko::pubkey::RSA_SHA2_512_CERT_V01, vec!["openssh"]),
     ];
 
     for (algo, servers) in cipher_algos.into_iter() {
@@ -83,6 +117,14 @@ pub fn collect(suite: &mut TestSuite) {
         }).only_servers(servers));
     }
 
+    for (algo, servers) in compress_algos.into_iter() {
+        suite.add(TestCase::new(&format!("smoke_compress_{}", algo.name), |socket| {
+            smoke_test(socket, makiko::ClientConfig::default_compatible_less_secure().with(|c| {
+                c.compression_algos = vec![algo];
+            }))
+        }).only_servers(servers));
+    }
+
     for (algo, servers) in pubkey_algos.into_iter() {
         suite.add(TestCase::new(&format!("smoke_pubkey_{}", algo.name), |socket| {
             smoke_test(socket, makiko::ClientConfig::default_compatible_less_secure().with(|c| {
@@ -153,6 +195,131 @@ async fn smoke_test(socket: TcpStream, config: makiko::ClientConfig) -> Result<(
     nursery_stream.try_run().await
 }
 
+async fn test_connection_info(socket: TcpStream) -> Result<()> {
+    let (nursery, mut nursery_stream) = Nursery::new();
+    let config = makiko::ClientConfig::default_compatible_less_secure().with(|c| {
+        c.cipher_algos = vec![&makiko::cipher::AES128_CTR];
+    });
+    let (client, mut client_rx, client_fut) = makiko::Client::open(socket, config)?;
+
+    nursery.spawn(async move {
+        client_fut.await.context("error while handling SSH connection")?;
+        Ok(())
+    });
+
+    nursery.spawn(async move {
+        while let Some(event) = client_rx.recv().await? {
+            if let makiko::ClientEvent::ServerPubkey(_pubkey, accept_tx) = event {
+                accept_tx.accept();
+            }
+        }
+        Ok(())
+    });
+
+    nursery.spawn(async move {
+        authenticate_alice(&client).await?;
+
+        let server_ident = client.server_ident()?.context("expected server ident")?;
+        ensure!(server_ident.starts_with(b"SSH-2.0-"), "unexpected server ident {:?}", server_ident);
+        ensure!(client.kex_count()? == 1, "expected one kex, got {}", client.kex_count()?);
+
+        let algos = client.negotiated_algos()?.context("expected negotiated algos")?;
+        ensure!(algos.cipher_cts.name == "aes128-ctr" && algos.cipher_stc.name == "aes128-ctr",
+            "unexpected ciphers {:?}", algos);
+        ensure!(algos.mac_cts.is_some() && algos.mac_stc.is_some(), "expected macs {:?}", algos);
+
+        let session_id = client.session_id()?.context("expected session id")?;
+        client.rekey().await?;
+        ensure!(client.kex_count()? == 2, "expected two kexes, got {}", client.kex_count()?);
+        ensure!(client.session_id()? == Some(session_id), "session id changed after rekey");
+
+        client.disconnect(makiko::DisconnectError::by_app())?;
+        Ok(())
+    });
+
+    drop(nursery);
+    nursery_stream.try_run().await
+}
+
+async fn test_probe(socket: TcpStream) -> Result<()> {
+    let report = makiko::probe::probe(socket).await?;
+    log::debug!("probe report {:?}", report);
+    ensure!(report.server_ident.starts_with(b"SSH-2.0-"), "unexpected server ident {:?}", report.server_ident);
+    ensure!(!report.kex_algos.is_empty(), "expected some kex algos");
+    ensure!(!report.cipher_algos_cts.is_empty(), "expected some ciphers");
+    ensure!(report.findings().iter().any(|f| f.security != makiko::probe::AlgoSecurity::Unknown),
+        "expected some known algos");
+    Ok(())
+}
+
+async fn test_keepalive(socket: TcpStream) -> Result<()> {
+    let (nursery, mut nursery_stream) = Nursery::new();
+    let config = makiko::ClientConfig::default_compatible_less_secure().with(|c| {
+        c.keepalive_interval = Some(Duration::from_millis(100));
+        c.keepalive_count_max = 2;
+    });
+    let (client, mut client_rx, client_fut) = makiko::Client::open(socket, config)?;
+
+    nursery.spawn(async move {
+        client_fut.await.context("error while handling SSH connection")?;
+        Ok(())
+    });
+
+    nursery.spawn(async move {
+        while let Some(event) = client_rx.recv().await? {
+            if let makiko::ClientEvent::ServerPubkey(_pubkey, accept_tx) = event {
+                accept_tx.accept();
+            }
+        }
+        Ok(())
+    });
+
+    nursery.spawn(async move {
+        authenticate_alice(&client).await?;
+        // the server should answer our keepalives, so the connection should survive
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        ensure!(client.is_authenticated()?, "expected to stay authenticated");
+        client.disconnect(makiko::DisconnectError::by_app())?;
+        Ok(())
+    });
+
+    drop(nursery);
+    nursery_stream.try_run().await
+}
+
+async fn test_ping(socket: TcpStream) -> Result<()> {
+    let (nursery, mut nursery_stream) = Nursery::new();
+    let config = makiko::ClientConfig::default_compatible_less_secure();
+    let (client, mut client_rx, client_fut) = makiko::Client::open(socket, config)?;
+
+    nursery.spawn(async move {
+        client_fut.await.context("error while handling SSH connection")?;
+        Ok(())
+    });
+
+    nursery.spawn(async move {
+        while let Some(event) = client_rx.recv().await? {
+            if let makiko::ClientEvent::ServerPubkey(_pubkey, accept_tx) = event {
+                accept_tx.accept();
+            }
+        }
+        Ok(())
+    });
+
+    nursery.spawn(async move {
+        authenticate_alice(&client).await?;
+        for _ in 0..3 {
+            let rtt = client.ping().await.context("could not ping the server")?;
+            ensure!(rtt < Duration::from_secs(5), "unexpected round-trip time {:?}", rtt);
+        }
+        client.disconnect(makiko::DisconnectError::by_app())?;
+        Ok(())
+    });
+
+    drop(nursery);
+    nursery_stream.try_run().await
+}
+
 pub(super) async fn authenticate_alice(client: &makiko::Client) -> Result<()> {
     let res = client.auth_password("alice".into(), "alicealice".into()).await?;
     if !matches!(res, makiko::AuthPasswordResult::Success) {
@@ -1,8 +1,9 @@
 use anyhow::{Result, bail, ensure, Context as _};
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut, BufMut as _};
 use futures::future::BoxFuture;
 use std::future::Future;
 use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
 use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{oneshot, mpsc};
@@ -21,6 +22,12 @@ pub fn collect(suite: &mut TestSuite) {
         .except_servers(vec!["paramiko", "tinyssh", "lsh"]));
     suite.add(TestCase::new("tunnel_proxy_jump", test_proxy_jump)
         .except_servers(vec!["paramiko", "tinyssh"]));
+    suite.add(TestCase::new("tunnel_connect_jump_chain", test_connect_jump_chain)
+        .except_servers(vec!["paramiko", "tinyssh"]));
+    suite.add(TestCase::new("tunnel_streamlocal_local", test_streamlocal_local)
+        .only_servers(vec!["openssh"]));
+    suite.add(TestCase::new("tunnel_streamlocal_remote", test_streamlocal_remote)
+        .only_servers(vec!["openssh"]));
 }
 
 async fn test_local_simple(socket: TcpStream) -> Result<()> {
@@ -172,6 +179,168 @@ async fn test_proxy_jump(socket: TcpStream) -> Result<()> {
     }).await
 }
 
+async fn test_connect_jump_chain(socket: TcpStream) -> Result<()> {
+    test_tunnel(socket, |outer_client, _, peer_addr, _| async move {
+        let (nursery, mut nursery_stream) = Nursery::new();
+        let connect_addr = (peer_addr.ip().to_string(), peer_addr.port());
+
+        let mut clients = vec![outer_client];
+        for _ in 0..2 {
+            let config = makiko::ClientConfig::default_compatible_less_secure();
+            let (client, mut client_rx, client_fut) = clients.last().unwrap().connect_jump(
+                makiko::ChannelConfig::default(), connect_addr.clone(), config,
+            ).await.context("could not connect through jump host")?;
+
+            nursery.spawn(async move {
+                client_fut.await?;
+                Ok(())
+            });
+
+            nursery.spawn(async move {
+                while let Some(event) = client_rx.recv().await? {
+                    if let makiko::ClientEvent::ServerPubkey(_pubkey, accept_tx) = event {
+                        accept_tx.accept();
+                    }
+                }
+                Ok(())
+            });
+
+            authenticate_alice(&client).await?;
+            clients.push(client);
+        }
+
+        // disconnect the nested clients from the innermost, the outer client is disconnected by
+        // `test_tunnel()`
+        for client in clients[1..].iter().rev() {
+            client.disconnect(makiko::DisconnectError::by_app())?;
+        }
+
+        drop(nursery);
+        nursery_stream.try_run().await
+    }).await
+}
+
+async fn test_streamlocal_local(socket: TcpStream) -> Result<()> {
+    test_streamlocal(socket, |client, _| async move {
+        let socket_path = format!("/tmp/makiko-{:016x}.sock", rand::random::<u64>());
+
+        // listen on the socket on the server with netcat, which prints the received data to stdout
+        let command = format!("printf server-to-client | nc -lUN {}", socket_path);
+        let (session, stdout_rx) = exec_stdout(&client, command).await?;
+
+        // netcat may not listen yet, so we need to retry
+        let mut attempts = 0;
+        let (tunnel, mut tunnel_rx) = loop {
+            match client.connect_streamlocal(makiko::ChannelConfig::default(), socket_path.clone()).await {
+                Ok(res) => break res,
+                Err(makiko::Error::ChannelOpen(err)) if attempts < 50 => {
+                    log::debug!("could not open streamlocal tunnel, retrying: {}", err);
+                    attempts += 1;
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                },
+                Err(err) => return Err(err).context("could not open streamlocal tunnel"),
+            }
+        };
+
+        check_tunnel_simple(&tunnel, &mut tunnel_rx).await?;
+
+        let stdout = stdout_rx.await?;
+        ensure!(stdout.as_ref() == b"client-to-server", "received stdout {:?}", stdout);
+        drop(session);
+        Ok(())
+    }).await
+}
+
+async fn test_streamlocal_remote(socket: TcpStream) -> Result<()> {
+    test_streamlocal(socket, |client, mut tunnel_accept_rx| async move {
+        let socket_path = format!("/tmp/makiko-{:016x}.sock", rand::random::<u64>());
+        client.bind_streamlocal(socket_path.clone())?.wait().await
+            .context("could not bind streamlocal tunnel")?;
+
+        // connect to the socket on the server with netcat, which prints the received data to stdout
+        let command = format!("printf server-to-client | nc -UN {}", socket_path);
+        let (session, stdout_rx) = exec_stdout(&client, command).await?;
+
+        let accept = tunnel_accept_rx.recv().await
+            .context("did not receive an AcceptStreamlocalTunnel")?;
+        ensure!(accept.socket_path == socket_path,
+            "socket path {:?} != {:?}", accept.socket_path, socket_path);
+        let (tunnel, mut tunnel_rx) = accept.accept(makiko::ChannelConfig::default()).await
+            .context("could not accept streamlocal tunnel")?;
+
+        check_tunnel_simple(&tunnel, &mut tunnel_rx).await?;
+
+        let stdout = stdout_rx.await?;
+        ensure!(stdout.as_ref() == b"client-to-server", "received stdout {:?}", stdout);
+        drop(session);
+
+        client.unbind_streamlocal(socket_path)?.wait().await
+            .context("could not unbind streamlocal tunnel")?;
+        Ok(())
+    }).await
+}
+
+// executes the command in a new session and returns all its stdout when it finishes
+async fn exec_stdout(client: &makiko::Client, command: String)
+    -> Result<(makiko::Session, oneshot::Receiver<Bytes>)>
+{
+    let (session, mut session_rx) = client.open_session(makiko::ChannelConfig::default()).await?;
+    let (stdout_tx, stdout_rx) = oneshot::channel();
+    tokio::spawn(async move {
+        let mut stdout = BytesMut::new();
+        while let Ok(Some(event)) = session_rx.recv().await {
+            if let makiko::SessionEvent::StdoutData(chunk) = event {
+                stdout.put(chunk);
+            } else if let makiko::SessionEvent::Eof = event {
+                break;
+            }
+        }
+        let _: Result<_, _> = stdout_tx.send(stdout.freeze());
+    });
+
+    session.exec(command.as_bytes())?.wait().await
+        .context("could not execute command")?;
+    Ok((session, stdout_rx))
+}
+
+async fn test_streamlocal<F, Fut>(socket: TcpStream, f: F) -> Result<()>
+    where F: FnOnce(makiko::Client, mpsc::Receiver<makiko::AcceptStreamlocalTunnel>)
+            -> Fut + Send + Sync + 'static,
+          Fut: Future<Output = Result<()>> + Send + Sync + 'static,
+{
+    let (nursery, mut nursery_stream) = Nursery::new();
+    let config = makiko::ClientConfig::default_compatible_less_secure();
+    let (client, mut client_rx, client_fut) = makiko::Client::open(socket, config)?;
+
+    nursery.spawn(async move {
+        client_fut.await?;
+        Ok(())
+    });
+
+    let (tunnel_accept_tx, tunnel_accept_rx) = mpsc::channel(1);
+    nursery.spawn(async move {
+        while let Some(event) = client_rx.recv().await? {
+            if let makiko::ClientEvent::ServerPubkey(_pubkey, accept_tx) = event {
+                accept_tx.accept();
+            } else if let makiko::ClientEvent::StreamlocalTunnel(tunnel_accept) = event {
+                tunnel_accept_tx.send(tunnel_accept).await
+                    .context("could not handle ClientEvent::StreamlocalTunnel")?;
+            }
+        }
+        Ok(())
+    });
+
+    nursery.spawn(async move {
+        authenticate_alice(&client).await?;
+        f(client.clone(), tunnel_accept_rx).await?;
+        client.disconnect(makiko::DisconnectError::by_app())?;
+        Ok(())
+    });
+
+    drop(nursery);
+    nursery_stream.try_run().await
+}
+
 async fn test_tunnel<F, Fut>(socket: TcpStream, f: F) -> Result<()>
     where F: FnOnce(makiko::Client, IpAddr, SocketAddr, mpsc::Receiver<makiko::AcceptTunnel>)
             -> Fut + Send + Sync + 'static,
@@ -228,3 +228,39 @@ mod fingerprint {
         check_fingerprint(keys::eda_ecdsa_p384(), "SHA256:8vBuizZHVX0885H8gCJQTzpf73/S9y3vT3VAHtuBikY");
     }
 }
+
+mod cert {
+    use super::keys;
+
+    fn check_openssh_cert(privkey: makiko::Privkey, cert_data: &str, key_id: &str, serial: u64) {
+        let cert = makiko::keys::decode_openssh_cert(cert_data.as_bytes())
+            .expect("could not decode certificate");
+        assert_eq!(cert.pubkey, privkey.pubkey());
+        assert_eq!(cert.cert_type, makiko::pubkey::CertType::User);
+        assert_eq!(cert.key_id, key_id);
+        assert_eq!(cert.serial, serial);
+        assert_eq!(cert.valid_principals, vec!["carol".to_string()]);
+        assert_eq!(cert.signature_key.fingerprint(), "SHA256:P1nxcckCH8/XeI4jdGzFjW70LHSt4Pkns83HSdYzdjU");
+        cert.verify_signature().expect("could not verify certificate signature");
+        assert_eq!(makiko::Certificate::decode(cert.encode()).unwrap(), cert);
+    }
+
+    #[test] fn test_decode_edward_ed25519_cert() {
+        check_openssh_cert(keys::edward_ed25519(), include_str!("edward_ed25519-cert.pub"),
+            "edward as carol", 1);
+    }
+    #[test] fn test_decode_eda_ecdsa_p256_cert() {
+        check_openssh_cert(keys::eda_ecdsa_p256(), include_str!("eda_ecdsa_p256-cert.pub"),
+            "eda as carol", 2);
+    }
+    #[test] fn test_decode_ruth_rsa_2048_cert() {
+        check_openssh_cert(keys::ruth_rsa_2048(), include_str!("ruth_rsa_2048-cert.pub"),
+            "ruth as carol", 3);
+    }
+
+    #[test] fn test_decode_cert_bad_type() {
+        let cert_data = include_str!("edward_ed25519-cert.pub")
+            .replacen("ssh-ed25519-cert-v01@openssh.com", "ssh-rsa-cert-v01@openssh.com", 1);
+        assert!(makiko::keys::decode_openssh_cert(cert_data.as_bytes()).is_err());
+    }
+}
@@ -38,6 +38,17 @@ class Server(paramiko.ServerInterface):
         print(entry, flush=True)
         return paramiko.AUTH_FAILED
 
+    def check_auth_interactive(self, username, submethods):
+        if username == "alice":
+            return paramiko.InteractiveQuery(
+                "alice login", "Enter your password and code", ("Password: ", False), ("Code: ", True))
+        return paramiko.AUTH_FAILED
+
+    def check_auth_interactive_response(self, responses):
+        if list(responses) == ["alicealice", "1234"]:
+            return paramiko.AUTH_SUCCESSFUL
+        return paramiko.AUTH_FAILED
+
     def check_auth_none(self, username):
         if username == "queen":
             return paramiko.AUTH_SUCCESSFUL
@@ -56,7 +67,7 @@ class Server(paramiko.ServerInterface):
 
     def get_allowed_auths(self, username):
         if username == "alice":
-            return "password"
+            return "password,keyboard-interactive"
         return ""
 
 def run_channel(server, channel):
@@ -95,6 +106,7 @@ def run_client(server_keys, client_sock, client_addr):
     logger.info(f"received connection from {client_addr!r}")
 
     trans = paramiko.Transport(client_sock)
+    trans.use_compression(True)
     try:
         for key in server_keys:
             trans.add_server_key(key)